@@ -0,0 +1,194 @@
+//! Authorization
+//!
+//! Restrict who may install where, by Unix group. Rules are configured in the
+//! `[[authorization]]` array of any config layer. A rule applies to an install when
+//! each of its conditions which is set matches, and the current user must then
+//! belong to at least one of its groups:
+//!
+//! ```toml
+//! [[authorization]]
+//! context = "facility"
+//! groups = ["pipeline"]
+//!
+//! [[authorization]]
+//! context = "shared"
+//! sites = ["all"]
+//! groups = ["pipeline", "show-leads"]
+//!
+//! [[authorization]]
+//! level = "dev01"
+//! groups = ["dev01-td"]
+//! ```
+//!
+//! Conditions: `context` (facility | shared | user), `level` (a level-spec, which
+//! also matches the levels beneath it, eg `dev01` matches `dev01.rd.9999`) and
+//! `sites` (matches if any of the install's sites is listed, where a request for
+//! every site, or for all but some, counts as `all`).
+//!
+//! The current user and their groups are looked up via `id`, unless the install
+//! has been given a `UserGroups` to check instead.
+
+// Internal crate imports
+use crate::context::Context;
+use crate::level::Level;
+use crate::site::Site;
+use crate::PkMakeError;
+
+// External crate imports
+use anyhow::anyhow;
+use anyhow::Error as AnyError;
+use serde::Deserialize;
+use subprocess::{Exec, Redirection};
+
+/// A rule naming the groups permitted to install to matching destinations
+#[derive(Debug, PartialEq, Eq, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthRule {
+    pub context: Option<String>,
+    pub level: Option<String>,
+    pub sites: Vec<String>,
+    pub groups: Vec<String>,
+}
+
+/// The resolved destination of an install
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AuthSubject {
+    pub context: Context,
+    /// The level, or None for the facility
+    pub level: Option<Level>,
+    pub sites: Vec<Site>,
+}
+
+impl AuthSubject {
+    // describe the destination for error messages
+    fn describe(&self) -> String {
+        let level = match self.level {
+            Some(ref level) => format!("level {}", level),
+            None => "the facility level".to_string(),
+        };
+        let sites = self.sites.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+        format!(
+            "to {} ({} context) at {}",
+            level,
+            self.context.as_str(),
+            sites.join(", ")
+        )
+    }
+}
+
+impl AuthRule {
+    /// Whether the rule applies to the subject
+    pub fn applies_to(&self, subject: &AuthSubject) -> Result<bool, PkMakeError> {
+        if let Some(ref context) = self.context {
+            if Context::from(context)? != subject.context {
+                return Ok(false);
+            }
+        }
+        if let Some(ref spec) = self.level {
+            let level = subject
+                .level
+                .as_ref()
+                .map(|l| l.to_string())
+                .unwrap_or_else(|| Level::Facility.to_string());
+            if !within(&level, spec) {
+                return Ok(false);
+            }
+        }
+        if !self.sites.is_empty()
+            && !subject
+                .sites
+                .iter()
+                .any(|s| self.sites.iter().any(|r| s.matches(r)))
+        {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+}
+
+// whether level is spec, or beneath it, comparing components case insensitively
+fn within(level: &str, spec: &str) -> bool {
+    let level = level.split('.').collect::<Vec<_>>();
+    let spec = spec.split('.').collect::<Vec<_>>();
+    spec.len() <= level.len()
+        && spec
+            .iter()
+            .zip(level.iter())
+            .all(|(s, l)| s.eq_ignore_ascii_case(l))
+}
+
+/// A user, along with the Unix groups they belong to
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct UserGroups {
+    pub user: String,
+    pub groups: Vec<String>,
+}
+
+impl UserGroups {
+    /// New up a UserGroups
+    pub fn new<I, S>(user: &str, groups: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            user: user.to_string(),
+            groups: groups.into_iter().map(|g| g.into()).collect(),
+        }
+    }
+
+    /// The current user and their groups, via `id`
+    pub fn current() -> Result<Self, AnyError> {
+        let user = id("id -un")?;
+        let groups = id("id -Gn")?;
+        Ok(Self::new(user.trim(), groups.split_whitespace()))
+    }
+
+    /// Whether the user belongs to any of the groups
+    pub fn in_any(&self, groups: &[String]) -> bool {
+        groups.iter().any(|g| self.groups.contains(g))
+    }
+}
+
+// run an `id` command, returning its output
+fn id(cmd: &str) -> Result<String, AnyError> {
+    let capture = Exec::shell(cmd)
+        .stdout(Redirection::Pipe)
+        .stderr(Redirection::Pipe)
+        .capture()?;
+    if !capture.success() {
+        return Err(anyhow!(
+            "Unable to look up the current user's groups via '{}': {}",
+            cmd,
+            capture.stderr_str().trim()
+        ));
+    }
+    Ok(capture.stdout_str())
+}
+
+/// Verify that the user belongs to one of the groups of every rule which applies to
+/// the subject. The first rule which is not satisfied is reported.
+pub fn authorize(
+    rules: &[AuthRule],
+    subject: &AuthSubject,
+    user: &UserGroups,
+) -> Result<(), PkMakeError> {
+    for rule in rules {
+        if rule.applies_to(subject)? && !user.in_any(&rule.groups) {
+            return Err(PkMakeError::Unauthorized {
+                user: user.user.clone(),
+                destination: subject.describe(),
+                groups: if rule.groups.is_empty() {
+                    "(no groups are permitted)".to_string()
+                } else {
+                    rule.groups.join(", ")
+                },
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[path = "./unit_tests/authorization_test.rs"]
+mod authorization_test;
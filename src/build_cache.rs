@@ -0,0 +1,420 @@
+//! BuildCache
+//!
+//! Skip rebuilding flavors whose inputs have not changed. Each flavor has a cache
+//! key, which is a sha256 digest of:
+//!
+//! - the tracked source files (per git or svn, or every package source otherwise)
+//! - the manifest
+//! - the effective overrides, defines and platforms
+//! - whether the docs are built, and whether packages resolve from the work area
+//!   (`--work`)
+//! - the flavor
+//!
+//! After a successful build, the key is recorded next to the flavor's dist artifact
+//! (`<dist_dir>/<package_id>.cachekey`), and in the cache index under the private
+//! directory (`private/build_cache.toml`). A flavor is up to date when its artifact
+//! exists and both records match the current key.
+//!
+//! Both `pk-make build` and the build step of `pk-make install` plan their builds
+//! with a `CachePlan`, so either may skip the flavors which the other built. A
+//! flavor's records are removed before it is rebuilt, so that a failed build does
+//! not leave a stale key behind.
+
+// Internal crate imports
+use crate::build_env::BuildEnv;
+use crate::dist::IGNORED;
+use crate::flavor::Flavor;
+use crate::platform::Platform;
+use crate::vcs::Vcs;
+use crate::ManifestInfo;
+use crate::OverridePair;
+
+// External crate imports
+use anyhow::anyhow;
+use anyhow::Error as AnyError;
+use indexmap::IndexSet as HashSet;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use subprocess::{Exec, Redirection};
+
+/// The name of the cache index, within the private directory
+const INDEX: &str = "build_cache.toml";
+/// The extension of the key file recorded next to each dist artifact
+pub(crate) const KEY_EXTENSION: &str = "cachekey";
+
+/// A single flavor's cache record
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub key: String,
+    pub artifact: PathBuf,
+}
+
+/// The cache index, keyed by package id
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct BuildCache {
+    path: PathBuf,
+    entries: BTreeMap<String, CacheEntry>,
+}
+
+impl BuildCache {
+    /// Load the cache index from the private directory. A missing index is empty.
+    pub fn load(private_dir: &Path) -> Result<Self, AnyError> {
+        let path = private_dir.join(INDEX);
+        let entries = if path.is_file() {
+            let contents = std::fs::read_to_string(&path)?;
+            toml::from_str(&contents)
+                .map_err(|e| anyhow!("Unable to read build cache '{}': {}", path.display(), e))?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self { path, entries })
+    }
+
+    /// Whether the artifact for package_id was built from inputs with the given key
+    pub fn is_current(&self, package_id: &str, artifact: &Path, key: &str) -> bool {
+        let recorded = std::fs::read_to_string(key_file(artifact)).unwrap_or_default();
+        artifact.is_dir()
+            && recorded.trim() == key
+            && self
+                .entries
+                .get(package_id)
+                .map(|entry| entry.key == key && entry.artifact == artifact)
+                .unwrap_or(false)
+    }
+
+    /// Record the key of a freshly built artifact, next to the artifact and in the
+    /// index. The index must be saved to persist the record.
+    pub fn record(&mut self, package_id: &str, artifact: &Path, key: &str) -> Result<(), AnyError> {
+        std::fs::write(key_file(artifact), format!("{}\n", key))?;
+        self.entries.insert(
+            package_id.to_string(),
+            CacheEntry {
+                key: key.to_string(),
+                artifact: artifact.to_path_buf(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Remove the records of an artifact which is about to be rebuilt. The index
+    /// must be saved to persist the removal.
+    pub fn forget(&mut self, package_id: &str, artifact: &Path) -> Result<(), AnyError> {
+        let key_file = key_file(artifact);
+        if key_file.exists() {
+            std::fs::remove_file(key_file)?;
+        }
+        self.entries.remove(package_id);
+        Ok(())
+    }
+
+    /// Write the index to the private directory
+    pub fn save(&self) -> Result<(), AnyError> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, toml::to_string(&self.entries)?)?;
+        Ok(())
+    }
+
+    /// Retrieve the record for a package id
+    pub fn get(&self, package_id: &str) -> Option<&CacheEntry> {
+        self.entries.get(package_id)
+    }
+}
+
+/// The options of a build which its cache keys cover
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BuildInputs<'a> {
+    pub dist_dir: Option<&'a str>,
+    pub flavors: Option<&'a HashSet<Flavor>>,
+    pub platforms: Option<&'a HashSet<Platform>>,
+    pub overrides: Option<&'a [OverridePair]>,
+    pub defines: Option<&'a [String]>,
+    pub with_docs: bool,
+    pub work: bool,
+}
+
+/// The flavors to build, along with the cache keys to record once they are built,
+/// and the flavors which are already up to date.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct CachePlan {
+    cache: BuildCache,
+    pub stale: Vec<CacheTarget>,
+    pub current: Vec<Flavor>,
+}
+
+/// A flavor's dist artifact and cache key
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct CacheTarget {
+    pub flavor: Flavor,
+    pub package_id: String,
+    pub artifact: PathBuf,
+    pub key: String,
+}
+
+impl CachePlan {
+    /// Calculate the cache key of each flavor, and split the flavors into those
+    /// which must be built and those which are up to date. Every flavor is built
+    /// when force is set (ie a clean or rebuild was requested).
+    pub fn new(build_env: &BuildEnv, inputs: &BuildInputs, force: bool) -> Result<Self, AnyError> {
+        let manifest_info = ManifestInfo::from_path(build_env.manifest.as_ref())?;
+        // pk build runs from the package root, so relative dist dirs are too
+        let dist_dir = inputs
+            .dist_dir
+            .map(|d| build_env.package_root.join(d))
+            .unwrap_or_else(|| build_env.dist_dir.clone());
+        let sources = sources_digest(
+            &build_env.package_root,
+            &build_env.manifest,
+            build_env.vcs.as_ref(),
+            &dist_dir,
+        )?;
+        let platforms = match inputs.platforms {
+            Some(platforms) => platforms.iter().map(|p| p.as_str()).collect::<Vec<_>>(),
+            None => vec![build_env.dd_os.as_str()],
+        };
+        let overrides = inputs
+            .overrides
+            .map(|v| v.iter().map(|o| o.as_str()).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let defines = inputs
+            .defines
+            .map(|v| v.iter().map(|d| d.as_str()).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let with_docs = if inputs.with_docs { "true" } else { "false" };
+        let work = if inputs.work { "true" } else { "false" };
+        let flavors = match inputs.flavors {
+            Some(flavors) => flavors.iter().cloned().collect::<Vec<_>>(),
+            None => manifest_info.flavors.clone(),
+        };
+
+        let mut plan = CachePlan {
+            cache: BuildCache::load(&build_env.private_dir)?,
+            stale: Vec::new(),
+            current: Vec::new(),
+        };
+        for flavor in flavors {
+            let package_id = manifest_info.package_id(&flavor);
+            let artifact = dist_dir.join(&package_id);
+            let key = key(
+                &sources,
+                &[
+                    ("overrides", overrides.clone()),
+                    ("defines", defines.clone()),
+                    ("platforms", platforms.clone()),
+                    ("with_docs", vec![with_docs]),
+                    ("work", vec![work]),
+                    ("flavor", vec![flavor.as_str()]),
+                ],
+            );
+            if !force && plan.cache.is_current(&package_id, &artifact, &key) {
+                plan.current.push(flavor);
+            } else {
+                plan.stale.push(CacheTarget {
+                    flavor,
+                    package_id,
+                    artifact,
+                    key,
+                });
+            }
+        }
+        Ok(plan)
+    }
+
+    /// Whether every flavor is up to date
+    pub fn is_current(&self) -> bool {
+        self.stale.is_empty()
+    }
+
+    /// The pk build `--flavour` flag selecting the stale flavors, when some of the
+    /// flavors are up to date
+    pub fn flavor_str(&self) -> Option<String> {
+        if self.current.is_empty() {
+            return None;
+        }
+        Some(format!(
+            " --flavour={}",
+            self.stale
+                .iter()
+                .map(|t| t.flavor.as_str())
+                .collect::<Vec<_>>()
+                .join(",")
+        ))
+    }
+
+    /// Report the flavors which are up to date, and so are not built
+    pub fn report(&self) {
+        if !self.current.is_empty() {
+            println!(
+                "Skipping flavors with an unchanged cache key: {}",
+                self.current
+                    .iter()
+                    .map(|f| f.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+
+    /// Remove the records of the stale flavors before they are built, and save the
+    /// index, so that a failed build leaves none behind
+    pub fn invalidate(&mut self) -> Result<(), AnyError> {
+        for target in &self.stale {
+            self.cache.forget(&target.package_id, &target.artifact)?;
+        }
+        self.cache.save()
+    }
+
+    /// Record the keys of the stale flavors which were built, and save the index
+    pub fn record(&mut self) -> Result<(), AnyError> {
+        for target in &self.stale {
+            if target.artifact.is_dir() {
+                self.cache
+                    .record(&target.package_id, &target.artifact, &target.key)?;
+            }
+        }
+        self.cache.save()
+    }
+}
+
+/// Digest the tracked source files and manifest of the package rooted at
+/// package_root. The result is combined with per flavor inputs by `key`. When the
+/// vcs cannot list the tracked files, every file under the package root is used,
+/// except for those in the private and vcs directories, and the dist_dir.
+pub fn sources_digest(
+    package_root: &Path,
+    manifest: &Path,
+    vcs: Option<&Vcs>,
+    dist_dir: &Path,
+) -> Result<String, AnyError> {
+    let mut hasher = Sha256::new();
+    for file in tracked_files(package_root, vcs, dist_dir)? {
+        let path = package_root.join(&file);
+        if !path.is_file() {
+            continue;
+        }
+        hasher.update(file.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(std::fs::read(&path)?);
+        hasher.update([0]);
+    }
+    hasher.update(b"manifest\0");
+    hasher.update(std::fs::read(manifest)?);
+    Ok(hex(&hasher.finalize()))
+}
+
+/// Combine the sources digest with the named inputs of a single flavor's build.
+/// Each input is a list of values, which are length prefixed so that no two
+/// distinct lists digest alike (eg `["a,b"]` and `["a", "b"]`).
+pub fn key(sources: &str, inputs: &[(&str, Vec<&str>)]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(sources.as_bytes());
+    for (name, values) in inputs {
+        hasher.update([0]);
+        hasher.update(name.as_bytes());
+        hasher.update((values.len() as u64).to_le_bytes());
+        for value in values {
+            hasher.update((value.len() as u64).to_le_bytes());
+            hasher.update(value.as_bytes());
+        }
+    }
+    hex(&hasher.finalize())
+}
+
+// the file holding the key recorded next to an artifact
+pub(crate) fn key_file(artifact: &Path) -> PathBuf {
+    let mut file = artifact.as_os_str().to_owned();
+    file.push(".");
+    file.push(KEY_EXTENSION);
+    PathBuf::from(file)
+}
+
+// the files tracked by the vcs, relative to package_root and sorted. Falls back on
+// every package source when the vcs is unknown, or cannot be queried.
+fn tracked_files(
+    package_root: &Path,
+    vcs: Option<&Vcs>,
+    dist_dir: &Path,
+) -> Result<Vec<PathBuf>, AnyError> {
+    let listed = match vcs {
+        Some(Vcs::Git) => vcs_list(package_root, "git ls-files", |l| l.to_string()),
+        Some(Vcs::Svn) => vcs_list(package_root, "svn list -R", |l| {
+            l.trim_end_matches('/').to_string()
+        }),
+        _ => None,
+    };
+    let mut files = match listed {
+        Some(files) => files,
+        None => {
+            let mut files = Vec::new();
+            walk(package_root, package_root, dist_dir, &mut files)?;
+            files
+        }
+    };
+    files.sort();
+    Ok(files)
+}
+
+// run a vcs listing command in package_root, returning None if it fails
+fn vcs_list(
+    package_root: &Path,
+    cmd: &str,
+    to_path: fn(&str) -> String,
+) -> Option<Vec<PathBuf>> {
+    let capture = Exec::shell(cmd)
+        .cwd(package_root)
+        .stdout(Redirection::Pipe)
+        .stderr(Redirection::Pipe)
+        .capture()
+        .ok()?;
+    if !capture.success() {
+        return None;
+    }
+    Some(
+        capture
+            .stdout_str()
+            .lines()
+            .filter(|l| !l.is_empty())
+            .map(|l| PathBuf::from(to_path(l)))
+            .collect(),
+    )
+}
+
+// collect the files under dir, relative to package_root, skipping the directories
+// which do not hold package sources
+fn walk(
+    package_root: &Path,
+    dir: &Path,
+    dist_dir: &Path,
+    files: &mut Vec<PathBuf>,
+) -> Result<(), AnyError> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let metadata = std::fs::symlink_metadata(&path)?;
+        if metadata.is_dir() {
+            let ignored = dir == package_root
+                && path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .map(|n| IGNORED.contains(&n))
+                    .unwrap_or(false);
+            if !ignored && path != dist_dir {
+                walk(package_root, &path, dist_dir, files)?;
+            }
+        } else if let Ok(relative) = path.strip_prefix(package_root) {
+            files.push(relative.to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+// lower case hex encoding of a digest
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+#[path = "./unit_tests/build_cache_test.rs"]
+mod build_cache_test;
@@ -3,26 +3,71 @@
 //! Model information which must be gleaned from the environment
 
 // internal crate imports
-use crate::platform::Platform;
+use crate::platform::{Platform, PlatformRegistry};
+use crate::show_pattern::ShowPattern;
 use crate::vcs::Vcs;
+use crate::PkMakeError;
 
 // external crate imports
 use anyhow::anyhow;
 use anyhow::Error as AnyError;
-use std::path::PathBuf;
-use std::str::FromStr;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// The file consulted to identify the host platform when DD_OS is not set
+const OS_RELEASE: &str = "/etc/os-release";
+
+/// Where the host platform, dd_os, was determined from
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PlatformSource {
+    /// The DD_OS environment variable
+    DdOs,
+    /// An os-release file, matched against the PlatformRegistry
+    OsRelease(PathBuf),
+}
+
+impl fmt::Display for PlatformSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DdOs => write!(f, "DD_OS"),
+            Self::OsRelease(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Where the show, dd_show, was determined from
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ShowSource {
+    /// The DD_SHOW environment variable
+    DdShow,
+    /// The current directory, matched against a ShowPattern
+    Path { path: PathBuf, pattern: ShowPattern },
+}
+
+impl fmt::Display for ShowSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DdShow => write!(f, "DD_SHOW"),
+            Self::Path { path, pattern } => {
+                write!(f, "{} (matching {})", path.display(), pattern)
+            }
+        }
+    }
+}
 
 /// Environment related values used generally as fallbacks when 
 /// not explicitly provided by the user.
 pub struct BuildEnv {
     pub package_root: PathBuf,
     pub dd_os: Platform,
+    pub dd_os_source: PlatformSource,
     pub private_dir: PathBuf,
     pub build_dir: PathBuf,
     pub dist_dir: PathBuf,
     pub vcs: Option<Vcs>,
     pub manifest: PathBuf,
     pub dd_show: Option<String>,
+    pub dd_show_source: Option<ShowSource>,
 }
 
 const MANIFESTS: [&str; 2] = ["manifest.yaml", "pk.yaml"];
@@ -34,6 +79,29 @@ impl BuildEnv {
     pub fn new<I>(package_root: I) -> Result<Self, AnyError>
     where
         I: AsRef<std::path::Path>, //Into<PathBuf>,
+    {
+        Self::with_platforms(package_root, &PlatformRegistry::builtin())
+    }
+
+    /// New up a BuildEnv, identifying the host platform via the supplied registry.
+    /// The show is inferred via the builtin patterns, which suffice for every target
+    /// but install.
+    pub fn with_platforms<I>(package_root: I, platforms: &PlatformRegistry) -> Result<Self, AnyError>
+    where
+        I: AsRef<std::path::Path>,
+    {
+        Self::with_registries(package_root, platforms, &ShowPattern::builtin())
+    }
+
+    /// New up a BuildEnv, identifying the host platform via the supplied registry,
+    /// and inferring the show via the supplied patterns, rather than the builtin ones.
+    pub fn with_registries<I>(
+        package_root: I,
+        platforms: &PlatformRegistry,
+        show_patterns: &[ShowPattern],
+    ) -> Result<Self, AnyError>
+    where
+        I: AsRef<std::path::Path>,
     {
         //let package_root = package_root.into();
         let package_root = std::fs::canonicalize(package_root)?;
@@ -42,7 +110,13 @@ impl BuildEnv {
         }
         let root = package_root.clone();
 
-        let dd_os = Platform::from_str(&std::env::var("DD_OS")?)?;
+        let (dd_os, dd_os_source) = Self::host_platform(platforms)?;
+        if dd_os_source != PlatformSource::DdOs {
+            eprintln!(
+                "DD_OS is not set. Using platform '{}' detected from {}",
+                dd_os, dd_os_source
+            );
+        }
 
         // calc private dir
         let mut private_dir = root.clone();
@@ -68,18 +142,91 @@ impl BuildEnv {
         */
         let manifest = Self::get_manifest(package_root.clone())?;
 
-        let dd_show = std::env::var("DD_SHOW").ok();
+        let cwd = std::env::current_dir().unwrap_or_else(|_| package_root.clone());
+        let (dd_show, dd_show_source) = match Self::resolve_show(
+            std::env::var("DD_SHOW").ok(),
+            &cwd,
+            show_patterns,
+        ) {
+            Some((show, source)) => (Some(show), Some(source)),
+            None => (None, None),
+        };
         Ok(BuildEnv {
             package_root,
             dd_os,
+            dd_os_source,
             private_dir,
             build_dir,
             dist_dir,
             vcs,
             manifest,
             dd_show,
+            dd_show_source,
+        })
+    }
+    /// Determine the host platform from DD_OS, falling back on /etc/os-release
+    pub fn host_platform(
+        registry: &PlatformRegistry,
+    ) -> Result<(Platform, PlatformSource), PkMakeError> {
+        let os_release = PathBuf::from(OS_RELEASE);
+        let contents = std::fs::read_to_string(&os_release).ok();
+        Self::resolve_host_platform(
+            std::env::var("DD_OS").ok(),
+            contents.as_deref().map(|c| (os_release, c)),
+            registry,
+        )
+    }
+
+    /// Determine the host platform given the value of DD_OS, and the path and
+    /// contents of an os-release file. DD_OS takes precedence when it is set.
+    pub fn resolve_host_platform(
+        dd_os: Option<String>,
+        os_release: Option<(PathBuf, &str)>,
+        registry: &PlatformRegistry,
+    ) -> Result<(Platform, PlatformSource), PkMakeError> {
+        if let Some(dd_os) = dd_os {
+            let platform = registry
+                .get(&dd_os)
+                .cloned()
+                .ok_or(PkMakeError::InvalidPlatform(dd_os))?;
+            return Ok((platform, PlatformSource::DdOs));
+        }
+        match os_release {
+            None => Err(PkMakeError::UndetectedPlatform(format!(
+                "{} could not be read",
+                OS_RELEASE
+            ))),
+            Some((path, contents)) => match registry.match_os_release(contents) {
+                Some(platform) => Ok((platform.clone(), PlatformSource::OsRelease(path))),
+                None => Err(PkMakeError::UndetectedPlatform(format!(
+                    "no platform matches {}",
+                    path.display()
+                ))),
+            },
+        }
+    }
+
+    /// Determine the show given the value of DD_SHOW, falling back on the first of the
+    /// patterns which matches the supplied directory.
+    pub fn resolve_show(
+        dd_show: Option<String>,
+        cwd: &Path,
+        patterns: &[ShowPattern],
+    ) -> Option<(String, ShowSource)> {
+        if let Some(dd_show) = dd_show {
+            return Some((dd_show, ShowSource::DdShow));
+        }
+        patterns.iter().find_map(|pattern| {
+            pattern.show_from(cwd).map(|show| {
+                let source = ShowSource::Path {
+                    path: cwd.to_path_buf(),
+                    pattern: pattern.clone(),
+                };
+                (show, source)
+            })
         })
     }
+
     // retrieve the manifest if possible
     fn get_manifest(mut manifest: PathBuf) -> Result<PathBuf, AnyError> {
         let mut found = false;
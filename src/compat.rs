@@ -0,0 +1,152 @@
+//! Compat
+//!
+//! Makebridge compatibility. Translates make style `KEY=VALUE` variables into
+//! pk-make arguments, so that `pk-make compat install CONTEXT=shared SITES=all`
+//! behaves like `pk-make install --context shared --site all`.
+//!
+//! | Variable           | Translation                                  |
+//! |--------------------|----------------------------------------------|
+//! | CONTEXT            | --context                                    |
+//! | LEVEL              | --level                                      |
+//! | SHOW               | --show                                       |
+//! | WITH_DOCS          | --skip-docs when false                       |
+//! | DRY_RUN            | --dry-run when true                          |
+//! | FLAVOUR (FLAVOR)   | --flavor, once per comma separated entry     |
+//! | SITES              | --site, once per comma separated entry       |
+//! | BUILD_DIR          | --build-dir                                  |
+//! | PLATFORMS          | --platform, once per comma separated entry   |
+//! | VERBOSE            | --verbose when true                          |
+//! | VCS                | --vcs                                        |
+//!
+//! Variables which are unknown, or which the target does not accept, are
+//! reported as unsupported and otherwise ignored.
+
+// Internal crate imports
+use crate::PkMakeError;
+
+/// The targets which may be invoked in compat mode
+pub const TARGETS: [&str; 4] = ["build", "install", "docs", "test"];
+
+/// How a Makebridge variable is translated into pk-make arguments
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Translation {
+    /// `--flag <value>`
+    Value(&'static str),
+    /// `--flag <entry>` for each comma separated entry
+    List(&'static str),
+    /// `--flag` when the value is true
+    Flag(&'static str),
+    /// `--flag` when the value is false
+    NegatedFlag(&'static str),
+}
+
+/// The result of translating Makebridge variables
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MakeArgs {
+    /// The equivalent pk-make command line, including the program name
+    pub args: Vec<String>,
+    /// Variables which were ignored, as supplied
+    pub unsupported: Vec<String>,
+}
+
+impl MakeArgs {
+    /// Translate a target and its `KEY=VALUE` variables into pk-make arguments
+    pub fn translate<S>(target: &str, vars: &[S]) -> Result<Self, PkMakeError>
+    where
+        S: AsRef<str>,
+    {
+        let target = target.to_lowercase();
+        if !TARGETS.contains(&target.as_str()) {
+            return Err(PkMakeError::InvalidMakeVar(format!(
+                "unsupported target '{}'",
+                target
+            )));
+        }
+        let mut args = vec!["pk-make".to_string(), target.clone()];
+        let mut unsupported = Vec::new();
+        for var in vars {
+            let var = var.as_ref();
+            let mut pieces = var.splitn(2, '=');
+            let key = pieces.next().unwrap_or("").trim().to_uppercase();
+            let value = pieces
+                .next()
+                .ok_or_else(|| PkMakeError::InvalidMakeVar(var.to_string()))?
+                .trim();
+            let translation = match translation(&key) {
+                Some(translation) if accepts(&target, &key) => translation,
+                _ => {
+                    unsupported.push(var.to_string());
+                    continue;
+                }
+            };
+            match translation {
+                Translation::Value(flag) => {
+                    args.push(flag.to_string());
+                    args.push(value.to_string());
+                }
+                Translation::List(flag) => {
+                    for entry in value.split(',').map(|e| e.trim()).filter(|e| !e.is_empty()) {
+                        args.push(flag.to_string());
+                        args.push(entry.to_string());
+                    }
+                }
+                Translation::Flag(flag) => {
+                    if parse_bool(var, value)? {
+                        args.push(flag.to_string());
+                    }
+                }
+                Translation::NegatedFlag(flag) => {
+                    if !parse_bool(var, value)? {
+                        args.push(flag.to_string());
+                    }
+                }
+            }
+        }
+        Ok(Self { args, unsupported })
+    }
+
+    /// Whether the translated command requested verbose or dry run output
+    pub fn is_chatty(&self) -> bool {
+        self.args.iter().any(|a| a == "--verbose" || a == "--dry-run")
+    }
+}
+
+// look up the translation for a variable
+fn translation(key: &str) -> Option<Translation> {
+    match key {
+        "CONTEXT" => Some(Translation::Value("--context")),
+        "LEVEL" => Some(Translation::Value("--level")),
+        "SHOW" => Some(Translation::Value("--show")),
+        "WITH_DOCS" => Some(Translation::NegatedFlag("--skip-docs")),
+        "DRY_RUN" => Some(Translation::Flag("--dry-run")),
+        "FLAVOUR" | "FLAVOR" => Some(Translation::List("--flavor")),
+        "SITES" => Some(Translation::List("--site")),
+        "BUILD_DIR" => Some(Translation::Value("--build-dir")),
+        "PLATFORMS" => Some(Translation::List("--platform")),
+        "VERBOSE" => Some(Translation::Flag("--verbose")),
+        "VCS" => Some(Translation::Value("--vcs")),
+        _ => None,
+    }
+}
+
+// whether the target accepts the option a variable translates to
+fn accepts(target: &str, key: &str) -> bool {
+    match key {
+        "DRY_RUN" | "VERBOSE" | "FLAVOUR" | "FLAVOR" | "PLATFORMS" => true,
+        "LEVEL" | "WITH_DOCS" => target == "build" || target == "install",
+        _ => target == "install",
+    }
+}
+
+// make style booleans
+fn parse_bool(var: &str, value: &str) -> Result<bool, PkMakeError> {
+    match value.to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "" | "0" | "false" | "no" | "off" => Ok(false),
+        _ => Err(PkMakeError::InvalidMakeVar(var.to_string())),
+    }
+}
+
+#[cfg(test)]
+#[path = "./unit_tests/compat_test.rs"]
+mod compat_test;
@@ -0,0 +1,660 @@
+//! Config
+//!
+//! Layered configuration files which supply defaults for each of the subcommands.
+//! Configuration is read from up to three locations, in increasing order of precedence:
+//!
+//! - **system** - `/etc/pk-make.toml`
+//! - **user** - `~/.config/pk-make/config.toml` (honoring `XDG_CONFIG_HOME`)
+//! - **package** - `.pk-make.toml` in the package root
+//!
+//! Values supplied on the command line always win. Missing files are skipped.
+//!
+//! Named profiles bundle together options for a subcommand, and are selected via
+//! `--profile <name>`. Values from the profile take precedence over the plain section
+//! defaults in every layer, but not over the command line.
+//!
+//! # Example
+//! ```toml
+//! show_patterns = ["/mnt/projects/*/{show}/..."]
+//! shows = ["dev01"]
+//! shows_root = "/jobs"
+//! history_file = "/var/tmp/pk-make-history.jsonl"
+//!
+//! [install]
+//! context = "shared"
+//! sites = ["portland", "vancouver"]
+//! max_jobs = 8
+//!
+//! [build]
+//! with_docs = false
+//! platforms = ["cent7"]
+//!
+//! [[sites]]
+//! id = "london"
+//! name = "London"
+//!
+//! [site_groups]
+//! na = ["playa", "portland", "vancouver", "montreal"]
+//!
+//! [[platforms]]
+//! name = "rocky9_64"
+//! aliases = ["rocky9"]
+//! os = "linux"
+//!
+//! [hooks]
+//! post-build = ["cp LICENSE $PK_HOOK_DIST_DIR/"]
+//!
+//! [[policies]]
+//! id = "shared-cent7"
+//! when = { context = "shared" }
+//! require = { platforms = ["cent7_64"] }
+//!
+//! [[authorization]]
+//! context = "facility"
+//! groups = ["pipeline"]
+//!
+//! [profiles.show-release.install]
+//! context = "shared"
+//! sites = ["all"]
+//! with_docs = false
+//! ```
+
+// Internal crate imports
+use crate::authorization::AuthRule;
+use crate::hooks::{HookPoint, Hooks};
+use crate::named_site::{NamedSite, SiteRegistry};
+use crate::platform::{Platform, PlatformRegistry};
+use crate::policy::{self, Policy};
+use crate::show_pattern::ShowPattern;
+use crate::show_registry::ShowRegistry;
+use crate::traits::Tabulate;
+use crate::PkMakeError;
+
+// External crate imports
+use anyhow::anyhow;
+use anyhow::Error as AnyError;
+use prettytable::{row, Table};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Location of the system wide config file
+const SYSTEM_CONFIG: &str = "/etc/pk-make.toml";
+/// Name of the package level config file, which lives in the package root
+const PACKAGE_CONFIG: &str = ".pk-make.toml";
+
+/// The subcommands which may have defaults configured for them.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Section {
+    Build,
+    Install,
+    Docs,
+    Test,
+    Run,
+}
+
+impl Section {
+    /// All of the sections, in display order
+    pub const ALL: [Section; 5] = [
+        Section::Build,
+        Section::Install,
+        Section::Docs,
+        Section::Test,
+        Section::Run,
+    ];
+
+    /// Return a &str representation of the Section
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Build => "build",
+            Self::Install => "install",
+            Self::Docs => "docs",
+            Self::Test => "test",
+            Self::Run => "run",
+        }
+    }
+}
+
+/// Identifies which layer a configuration value came from.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum ConfigSource {
+    System,
+    User,
+    Package,
+}
+
+impl ConfigSource {
+    /// Return a &str representation of the ConfigSource
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::System => "system",
+            Self::User => "user",
+            Self::Package => "package",
+        }
+    }
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Defaults which may be configured for a subcommand. Values are stored as
+/// they appear in the file, and converted using the same FromStr impls as the
+/// command line when they are requested.
+#[derive(Debug, PartialEq, Eq, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TargetDefaults {
+    pub sites: Option<Vec<String>>,
+    pub platforms: Option<Vec<String>>,
+    pub flavors: Option<Vec<String>>,
+    pub context: Option<String>,
+    pub max_jobs: Option<u8>,
+    pub with_docs: Option<bool>,
+    pub defines: Option<Vec<String>>,
+}
+
+/// A named set of defaults, selected on the command line via `--profile`. Each
+/// subcommand has its own table within the profile, eg `[profiles.show-release.install]`.
+#[derive(Debug, PartialEq, Eq, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub build: TargetDefaults,
+    pub install: TargetDefaults,
+    pub docs: TargetDefaults,
+    pub test: TargetDefaults,
+    pub run: TargetDefaults,
+}
+
+impl Profile {
+    /// Retrieve the defaults for a particular section
+    pub fn section(&self, section: Section) -> &TargetDefaults {
+        match section {
+            Section::Build => &self.build,
+            Section::Install => &self.install,
+            Section::Docs => &self.docs,
+            Section::Test => &self.test,
+            Section::Run => &self.run,
+        }
+    }
+}
+
+/// The contents of a single config file.
+#[derive(Debug, PartialEq, Eq, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    pub build: TargetDefaults,
+    pub install: TargetDefaults,
+    pub docs: TargetDefaults,
+    pub test: TargetDefaults,
+    pub run: TargetDefaults,
+    pub sites: Vec<NamedSite>,
+    pub site_groups: BTreeMap<String, Vec<String>>,
+    pub platforms: Vec<Platform>,
+    pub show_patterns: Vec<ShowPattern>,
+    pub shows: Vec<String>,
+    pub shows_root: Option<PathBuf>,
+    pub history_file: Option<PathBuf>,
+    pub hooks: Hooks,
+    pub policies: Vec<Policy>,
+    pub authorization: Vec<AuthRule>,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl ConfigFile {
+    /// Parse a ConfigFile from a str of toml
+    pub fn parse(contents: &str) -> Result<Self, AnyError> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    /// Read and parse a ConfigFile from a Path
+    pub fn from_path(path: &Path) -> Result<Self, AnyError> {
+        let contents = std::fs::read_to_string(path)?;
+        Self::parse(&contents)
+            .map_err(|e| anyhow!("Unable to parse config '{}': {}", path.display(), e))
+    }
+
+    /// Retrieve the defaults for a particular section
+    pub fn section(&self, section: Section) -> &TargetDefaults {
+        match section {
+            Section::Build => &self.build,
+            Section::Install => &self.install,
+            Section::Docs => &self.docs,
+            Section::Test => &self.test,
+            Section::Run => &self.run,
+        }
+    }
+}
+
+/// A parsed config file along with where it was found.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ConfigLayer {
+    pub source: ConfigSource,
+    pub path: PathBuf,
+    pub file: ConfigFile,
+}
+
+impl ConfigLayer {
+    /// Describe the layer for reporting purposes, eg `user (/home/foo/.config/pk-make/config.toml)`
+    pub fn describe(&self) -> String {
+        format!("{} ({})", self.source, self.path.display())
+    }
+}
+
+/// The merged configuration. Layers are stored in increasing order of precedence;
+/// lookups walk them from highest to lowest.
+#[derive(Debug, PartialEq, Eq, Default, Clone)]
+pub struct Config {
+    layers: Vec<ConfigLayer>,
+}
+
+impl Config {
+    /// Load the system, user, and package config files, skipping any which do not exist.
+    pub fn load(package_root: &Path) -> Result<Self, AnyError> {
+        let mut candidates = vec![(ConfigSource::System, PathBuf::from(SYSTEM_CONFIG))];
+        if let Some(user) = Self::user_config_path() {
+            candidates.push((ConfigSource::User, user));
+        }
+        candidates.push((ConfigSource::Package, package_root.join(PACKAGE_CONFIG)));
+
+        let mut layers = Vec::new();
+        for (source, path) in candidates {
+            if path.is_file() {
+                let file = ConfigFile::from_path(&path)?;
+                layers.push(ConfigLayer { source, path, file });
+            }
+        }
+        Ok(Self::from_layers(layers))
+    }
+
+    /// Construct a Config from a list of layers. The layers are sorted by precedence.
+    pub fn from_layers(mut layers: Vec<ConfigLayer>) -> Self {
+        layers.sort_by_key(|l| l.source);
+        Self { layers }
+    }
+
+    /// The path of the user config file, if a home directory may be determined
+    pub fn user_config_path() -> Option<PathBuf> {
+        let config_home = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => {
+                let mut home = PathBuf::from(std::env::var_os("HOME")?);
+                home.push(".config");
+                home
+            }
+        };
+        Some(config_home.join("pk-make").join("config.toml"))
+    }
+
+    /// Retrieve the layers which were loaded, in increasing order of precedence
+    pub fn layers(&self) -> &[ConfigLayer] {
+        &self.layers
+    }
+
+    /// Retrieve the defaults for a section of the config
+    pub fn defaults(&self, section: Section) -> Defaults<'_> {
+        Defaults {
+            config: self,
+            section,
+            profile: None,
+        }
+    }
+
+    /// Retrieve the defaults for a section of the config, with the values from the named
+    /// profile taking precedence over those of the section. It is an error for the profile
+    /// not to define any values for the section.
+    pub fn profile_defaults<'a>(
+        &'a self,
+        section: Section,
+        profile: &'a str,
+    ) -> Result<Defaults<'a>, PkMakeError> {
+        let profiles = self
+            .layers
+            .iter()
+            .filter_map(|layer| layer.file.profiles.get(profile))
+            .collect::<Vec<_>>();
+        if profiles.is_empty() {
+            return Err(PkMakeError::UnknownProfile(profile.to_string()));
+        }
+        if profiles
+            .iter()
+            .all(|p| *p.section(section) == TargetDefaults::default())
+        {
+            return Err(PkMakeError::UnknownProfile(format!(
+                "{} (it defines no {} options)",
+                profile,
+                section.as_str()
+            )));
+        }
+        Ok(Defaults {
+            config: self,
+            section,
+            profile: Some(profile),
+        })
+    }
+
+    /// The patterns used to infer the show from the current directory. Configured
+    /// patterns are tried in order of precedence, followed by the builtin patterns.
+    pub fn show_patterns(&self) -> Vec<ShowPattern> {
+        self.layers
+            .iter()
+            .rev()
+            .flat_map(|layer| layer.file.show_patterns.iter().cloned())
+            .chain(ShowPattern::builtin())
+            .collect()
+    }
+
+    /// Build the ShowRegistry from the shows listed in each layer, plus the directories
+    /// under the highest precedence shows_root. None if no shows are configured.
+    pub fn show_registry(&self) -> Result<Option<ShowRegistry>, AnyError> {
+        let shows_root = self
+            .layers
+            .iter()
+            .rev()
+            .find_map(|layer| layer.file.shows_root.as_ref());
+        if shows_root.is_none() && self.layers.iter().all(|l| l.file.shows.is_empty()) {
+            return Ok(None);
+        }
+        let mut registry = ShowRegistry::default();
+        for layer in &self.layers {
+            registry.extend(layer.file.shows.iter().cloned());
+        }
+        if let Some(root) = shows_root {
+            registry.scan(root)?;
+        }
+        Ok(Some(registry))
+    }
+
+    /// The history journal configured by the highest precedence layer, if any
+    pub fn history_file(&self) -> Option<&Path> {
+        self.layers
+            .iter()
+            .rev()
+            .find_map(|layer| layer.file.history_file.as_deref())
+    }
+
+    /// The hooks of every layer, in order of increasing precedence
+    pub fn hooks(&self) -> Hooks {
+        let mut hooks = Hooks::default();
+        for layer in &self.layers {
+            hooks.extend(&layer.file.hooks);
+        }
+        hooks
+    }
+
+    /// The policies of every layer. A policy may not redefine one with the same id.
+    pub fn policies(&self) -> Result<Vec<Policy>, PkMakeError> {
+        policy::merge(
+            self.layers
+                .iter()
+                .map(|layer| (layer.describe(), layer.file.policies.as_slice())),
+        )
+    }
+
+    /// The authorization rules of every layer, all of which apply
+    pub fn authorization(&self) -> Vec<AuthRule> {
+        self.layers
+            .iter()
+            .flat_map(|layer| layer.file.authorization.iter().cloned())
+            .collect()
+    }
+
+    /// The names of the profiles defined across all of the layers
+    pub fn profile_names(&self) -> Vec<&str> {
+        let mut names = self
+            .layers
+            .iter()
+            .flat_map(|layer| layer.file.profiles.keys().map(|k| k.as_str()))
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    /// Build the SiteRegistry from the builtin sites, extended by the sites and site
+    /// groups defined in each layer in order of precedence.
+    pub fn site_registry(&self) -> Result<SiteRegistry, PkMakeError> {
+        let mut registry = SiteRegistry::builtin();
+        for layer in &self.layers {
+            registry.extend(&layer.file.sites)?;
+        }
+        for layer in &self.layers {
+            for (group, members) in &layer.file.site_groups {
+                registry.add_group(group, members)?;
+            }
+        }
+        Ok(registry)
+    }
+
+    /// Build the PlatformRegistry from the builtin platforms, extended by the platforms
+    /// defined in each layer in order of precedence.
+    pub fn platform_registry(&self) -> Result<PlatformRegistry, PkMakeError> {
+        let mut registry = PlatformRegistry::builtin();
+        for layer in &self.layers {
+            registry.extend(&layer.file.platforms)?;
+        }
+        Ok(registry)
+    }
+}
+
+// accessor for one of the list valued fields of TargetDefaults
+type ListField = fn(&TargetDefaults) -> Option<&Vec<String>>;
+
+/// Where a configured value came from: the layer, and the profile within it if any.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Origin<'a> {
+    pub layer: &'a ConfigLayer,
+    pub profile: Option<&'a str>,
+}
+
+impl<'a> Origin<'a> {
+    /// Describe the origin for reporting purposes, eg `profile show-release in user (...)`
+    pub fn describe(&self) -> String {
+        match self.profile {
+            Some(profile) => format!("profile {} in {}", profile, self.layer.describe()),
+            None => self.layer.describe(),
+        }
+    }
+}
+
+/// Typed access to the merged defaults for a single Section, optionally overlaid
+/// with a profile.
+pub struct Defaults<'a> {
+    config: &'a Config,
+    section: Section,
+    profile: Option<&'a str>,
+}
+
+impl<'a> Defaults<'a> {
+    /// The name of the profile in use, if any
+    pub fn profile(&self) -> Option<&'a str> {
+        self.profile
+    }
+
+    /// The default sites, converted via FromStr
+    pub fn sites<T: FromStr>(&self) -> Result<Option<Vec<T>>, T::Err> {
+        parse_list(self.lookup(|d| d.sites.as_ref()))
+    }
+
+    /// The default platforms, converted via FromStr
+    pub fn platforms<T: FromStr>(&self) -> Result<Option<Vec<T>>, T::Err> {
+        parse_list(self.lookup(|d| d.platforms.as_ref()))
+    }
+
+    /// The default flavors, converted via FromStr
+    pub fn flavors<T: FromStr>(&self) -> Result<Option<Vec<T>>, T::Err> {
+        parse_list(self.lookup(|d| d.flavors.as_ref()))
+    }
+
+    /// The default context, converted via FromStr
+    pub fn context<T: FromStr>(&self) -> Result<Option<T>, T::Err> {
+        self.lookup(|d| d.context.as_ref())
+            .map(|(v, _)| T::from_str(v))
+            .transpose()
+    }
+
+    /// The default maximum number of jobs
+    pub fn max_jobs(&self) -> Option<u8> {
+        self.lookup(|d| d.max_jobs.as_ref()).map(|(v, _)| *v)
+    }
+
+    /// Whether docs should be built by default
+    pub fn with_docs(&self) -> Option<bool> {
+        self.lookup(|d| d.with_docs.as_ref()).map(|(v, _)| *v)
+    }
+
+    /// The default defines
+    pub fn defines(&self) -> Option<Vec<String>> {
+        self.lookup(|d| d.defines.as_ref()).map(|(v, _)| v.clone())
+    }
+
+    /// Report each field which has been set, as (field, value, origin) tuples.
+    pub fn entries(&self) -> Vec<(&'static str, String, Origin<'a>)> {
+        let mut entries = Vec::new();
+        let lists: [(&'static str, ListField); 4] = [
+            ("sites", |d| d.sites.as_ref()),
+            ("platforms", |d| d.platforms.as_ref()),
+            ("flavors", |d| d.flavors.as_ref()),
+            ("defines", |d| d.defines.as_ref()),
+        ];
+        for (name, get) in lists.iter() {
+            if let Some((v, origin)) = self.lookup(get) {
+                entries.push((*name, v.join(", "), origin));
+            }
+        }
+        if let Some((v, origin)) = self.lookup(|d| d.context.as_ref()) {
+            entries.push(("context", v.clone(), origin));
+        }
+        if let Some((v, origin)) = self.lookup(|d| d.max_jobs.as_ref()) {
+            entries.push(("max_jobs", v.to_string(), origin));
+        }
+        if let Some((v, origin)) = self.lookup(|d| d.with_docs.as_ref()) {
+            entries.push(("with_docs", v.to_string(), origin));
+        }
+        entries
+    }
+
+    // find the highest precedence value, checking the profile in each layer before
+    // falling back to the section in each layer.
+    fn lookup<T, F>(&self, get: F) -> Option<(&'a T, Origin<'a>)>
+    where
+        F: Fn(&'a TargetDefaults) -> Option<&'a T>,
+    {
+        let layers = &self.config.layers;
+        let section = self.section;
+        let from_profile = self.profile.and_then(|profile| {
+            layers.iter().rev().find_map(|layer| {
+                layer
+                    .file
+                    .profiles
+                    .get(profile)
+                    .and_then(|p| get(p.section(section)))
+                    .map(|v| (v, Origin { layer, profile: Some(profile) }))
+            })
+        });
+        from_profile.or_else(|| {
+            layers.iter().rev().find_map(|layer| {
+                get(layer.file.section(section)).map(|v| (v, Origin { layer, profile: None }))
+            })
+        })
+    }
+}
+
+// convert an optional list of strings into a list of T
+fn parse_list<T: FromStr>(
+    value: Option<(&Vec<String>, Origin<'_>)>,
+) -> Result<Option<Vec<T>>, T::Err> {
+    value
+        .map(|(v, _)| v.iter().map(|s| T::from_str(s)).collect())
+        .transpose()
+}
+
+//
+// Tabulate implementation - used by `pk-make config show`
+//
+impl Tabulate for Config {
+    fn create_table(&self) -> Table {
+        let mut table = Table::new();
+        table.add_row(row!["Section", "Field", "Value", "Source"]);
+        for section in Section::ALL.iter() {
+            for (field, value, origin) in self.defaults(*section).entries() {
+                table.add_row(row![section.as_str(), field, value, origin.describe()]);
+            }
+        }
+        for profile in self.profile_names() {
+            for section in Section::ALL.iter() {
+                let defaults = match self.profile_defaults(*section, profile) {
+                    Ok(defaults) => defaults,
+                    Err(_) => continue,
+                };
+                for (field, value, origin) in defaults.entries() {
+                    if origin.profile.is_some() {
+                        let name = format!("profiles.{}.{}", profile, section.as_str());
+                        table.add_row(row![name, field, value, origin.layer.describe()]);
+                    }
+                }
+            }
+        }
+        for layer in &self.layers {
+            for site in &layer.file.sites {
+                table.add_row(row!["sites", site.as_str(), site.name(), layer.describe()]);
+            }
+            for (group, members) in &layer.file.site_groups {
+                table.add_row(row!["site_groups", group, members.join(", "), layer.describe()]);
+            }
+            for platform in &layer.file.platforms {
+                table.add_row(row!["platforms", platform.as_str(), platform.os(), layer.describe()]);
+            }
+            if !layer.file.shows.is_empty() {
+                table.add_row(row!["shows", "", layer.file.shows.join(", "), layer.describe()]);
+            }
+            if let Some(root) = layer.file.shows_root.as_ref() {
+                table.add_row(row!["shows_root", "", root.display(), layer.describe()]);
+            }
+            if let Some(file) = layer.file.history_file.as_ref() {
+                table.add_row(row!["history_file", "", file.display(), layer.describe()]);
+            }
+            for policy in &layer.file.policies {
+                let description = policy.description.as_deref().unwrap_or("");
+                table.add_row(row!["policies", policy.id, description, layer.describe()]);
+            }
+            for rule in &layer.file.authorization {
+                let mut applies = Vec::new();
+                if let Some(ref context) = rule.context {
+                    applies.push(format!("context={}", context));
+                }
+                if let Some(ref level) = rule.level {
+                    applies.push(format!("level={}", level));
+                }
+                if !rule.sites.is_empty() {
+                    applies.push(format!("sites={}", rule.sites.join(",")));
+                }
+                table.add_row(row![
+                    "authorization",
+                    applies.join(" "),
+                    rule.groups.join(", "),
+                    layer.describe()
+                ]);
+            }
+            for point in &HookPoint::ALL {
+                for cmd in layer.file.hooks.get(*point) {
+                    table.add_row(row!["hooks", point, cmd, layer.describe()]);
+                }
+            }
+            for pattern in &layer.file.show_patterns {
+                table.add_row(row!["show_patterns", "", pattern, layer.describe()]);
+            }
+        }
+        table
+    }
+}
+
+//
+// Import Tests
+//
+#[cfg(test)]
+#[path = "./unit_tests/config_test.rs"]
+mod config_test;
@@ -0,0 +1,43 @@
+//! Dist
+//!
+//! Inspect the dist artifacts produced by `pk build`. Before installing,
+//! `verify_artifacts` checks that every artifact is a non empty directory.
+//!
+//! Whether an artifact needs rebuilding is decided by the build cache.
+
+// Internal crate imports
+use crate::PkMakeError;
+
+// External crate imports
+use std::path::PathBuf;
+
+/// Directories directly under the package root which do not hold package sources
+pub(crate) const IGNORED: [&str; 3] = ["private", ".git", ".svn"];
+
+/// Verify that every artifact is a non empty directory. All of the artifacts which
+/// are not are reported together, attributed to package (eg `name-version`).
+pub fn verify_artifacts(package: &str, artifacts: &[PathBuf]) -> Result<(), PkMakeError> {
+    let missing = artifacts
+        .iter()
+        .filter_map(|artifact| {
+            let reason = match std::fs::read_dir(artifact).map(|mut e| e.next().is_none()) {
+                Err(_) if artifact.exists() => "is not a directory",
+                Err(_) => "does not exist",
+                Ok(true) => "is empty",
+                Ok(false) => return None,
+            };
+            Some(format!("{} ({})", artifact.display(), reason))
+        })
+        .collect::<Vec<_>>();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(PkMakeError::MissingArtifacts {
+        package: package.to_string(),
+        missing: missing.join("\n  "),
+    })
+}
+
+#[cfg(test)]
+#[path = "./unit_tests/dist_test.rs"]
+mod dist_test;
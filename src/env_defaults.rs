@@ -0,0 +1,197 @@
+//! EnvDefaults
+//!
+//! Environment variable fallbacks for the command line options. Every option may
+//! be supplied as `PK_MAKE_<OPTION>`, eg `PK_MAKE_SITES`, `PK_MAKE_FLAVORS`,
+//! `PK_MAKE_LEVEL`, `PK_MAKE_MAX_JOBS`. Values supplied on the command line win
+//! over the environment, which in turn wins over the config files.
+//!
+//! - list valued options are comma separated, eg `PK_MAKE_SITES=portland,vancouver`
+//! - flags accept `1`, `true`, `yes`, `on` and `0`, `false`, `no`, `off`
+//! - empty variables are treated as unset
+//!
+//! The command line is parsed first, and the options it does not supply are then
+//! filled from the variables, using the same FromStr impls as the command line.
+//! Sites and platforms are checked against the configured registries. Every error
+//! names the offending variable.
+
+// Internal crate imports
+use crate::named_site::SiteRegistry;
+use crate::platform::PlatformRegistry;
+use crate::site::Site;
+use crate::PkMakeError;
+
+// External crate imports
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Prefix shared by all of the environment variables
+pub const PREFIX: &str = "PK_MAKE_";
+
+/// A snapshot of the `PK_MAKE_*` environment variables
+#[derive(Debug, PartialEq, Eq, Default, Clone)]
+pub struct EnvDefaults {
+    vars: BTreeMap<String, String>,
+}
+
+impl EnvDefaults {
+    /// Capture the `PK_MAKE_*` variables from the process environment
+    pub fn from_env() -> Self {
+        Self::from_vars(std::env::vars())
+    }
+
+    /// Capture the `PK_MAKE_*` variables from the supplied (name, value) pairs
+    pub fn from_vars<I>(vars: I) -> Self
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        Self {
+            vars: vars
+                .into_iter()
+                .filter(|(name, value)| name.starts_with(PREFIX) && !value.trim().is_empty())
+                .collect(),
+        }
+    }
+
+    /// The variables which are set, as (name, value) pairs in name order
+    pub fn vars(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.vars.iter().map(|(k, v)| (k.as_str(), v.trim()))
+    }
+
+    /// Retrieve the raw value of a variable, if set
+    pub fn get(&self, var: &str) -> Option<&str> {
+        self.vars.get(var).map(|v| v.trim())
+    }
+
+    /// Retrieve a flag. Unset variables are false.
+    pub fn flag(&self, var: &str) -> Result<bool, PkMakeError> {
+        match self.get(var).map(|v| v.to_lowercase()) {
+            None => Ok(false),
+            Some(value) => match value.as_str() {
+                "1" | "true" | "yes" | "on" => Ok(true),
+                "0" | "false" | "no" | "off" => Ok(false),
+                _ => Err(invalid(var, format!("'{}' is not a boolean", value))),
+            },
+        }
+    }
+
+    /// Retrieve a path value
+    pub fn path(&self, var: &str) -> Option<PathBuf> {
+        self.get(var).map(PathBuf::from)
+    }
+
+    /// Retrieve the entries of a comma separated list, trimmed, skipping empty ones
+    pub fn items(&self, var: &str) -> Option<Vec<&str>> {
+        self.get(var).map(|v| split_list(v).collect())
+    }
+
+    /// Retrieve a value, converted via FromStr
+    pub fn value<T>(&self, var: &str) -> Result<Option<T>, PkMakeError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.get(var)
+            .map(|v| v.parse().map_err(|e| invalid(var, e)))
+            .transpose()
+    }
+
+    /// Retrieve a comma separated list, converting each entry via FromStr
+    pub fn list<T>(&self, var: &str) -> Result<Option<Vec<T>>, PkMakeError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.items(var)
+            .map(|items| {
+                items
+                    .into_iter()
+                    .map(|v| v.parse().map_err(|e| invalid(var, e)))
+                    .collect()
+            })
+            .transpose()
+    }
+
+    /// Retrieve the sites in `PK_MAKE_SITES`, verifying that each is `local`, `all`,
+    /// or a site, group or exclusion known to the registry
+    pub fn sites(&self, registry: &SiteRegistry) -> Result<Option<Vec<String>>, PkMakeError> {
+        let var = "PK_MAKE_SITES";
+        self.checked_items(var, |site| Site::parse(site, registry).map(|_| ()))
+    }
+
+    /// Retrieve the platforms in `PK_MAKE_PLATFORMS`, verifying that each is known to
+    /// the registry
+    pub fn platforms(
+        &self,
+        registry: &PlatformRegistry,
+    ) -> Result<Option<Vec<String>>, PkMakeError> {
+        let var = "PK_MAKE_PLATFORMS";
+        self.checked_items(var, |platform| registry.parse(platform).map(|_| ()))
+    }
+
+    /// Fill value from the variable, unless it was supplied on the command line
+    pub fn fill<T>(&self, value: &mut Option<T>, var: &str) -> Result<(), PkMakeError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        if value.is_none() {
+            *value = self.value(var)?;
+        }
+        Ok(())
+    }
+
+    /// Fill a list from the variable, unless it was supplied on the command line
+    pub fn fill_list<T>(&self, value: &mut Option<Vec<T>>, var: &str) -> Result<(), PkMakeError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        if value.is_none() {
+            *value = self.list(var)?;
+        }
+        Ok(())
+    }
+
+    /// Set a flag from the variable, unless it was set on the command line
+    pub fn fill_flag(&self, value: &mut bool, var: &str) -> Result<(), PkMakeError> {
+        if !*value {
+            *value = self.flag(var)?;
+        }
+        Ok(())
+    }
+
+    // retrieve the entries of a list, verifying each via check
+    fn checked_items<F>(&self, var: &str, check: F) -> Result<Option<Vec<String>>, PkMakeError>
+    where
+        F: Fn(&str) -> Result<(), PkMakeError>,
+    {
+        match self.items(var) {
+            None => Ok(None),
+            Some(items) => {
+                for item in &items {
+                    check(item).map_err(|e| invalid(var, e))?;
+                }
+                Ok(Some(items.into_iter().map(String::from).collect()))
+            }
+        }
+    }
+}
+
+// split a comma separated list, ignoring empty entries
+fn split_list(value: &str) -> impl Iterator<Item = &str> {
+    value.split(',').map(|v| v.trim()).filter(|v| !v.is_empty())
+}
+
+// construct the error for an invalid variable
+fn invalid<E: Display>(var: &str, reason: E) -> PkMakeError {
+    PkMakeError::InvalidEnvVar {
+        var: var.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+#[path = "./unit_tests/env_defaults_test.rs"]
+mod env_defaults_test;
@@ -6,14 +6,56 @@ pub enum PkMakeError {
     ConvertFrom(String),
     #[error("Invalid Site:'{0}'")]
     InvalidSite(String),
+    #[error("Invalid Site Group:'{0}'")]
+    InvalidSiteGroup(String),
+    #[error("The requested sites, '{0}', leave no sites to install to")]
+    NoSites(String),
     #[error("Invalid Flavor:'{0}'")]
     InvalidFlavor(String),
     #[error("Invalid Context:'{0}'")]
     InvalidContext(String),
+    #[error("Invalid Level:'{0}'")]
+    InvalidLevel(String),
     #[error("Invalid Platform:'{0}'")]
     InvalidPlatform(String),
+    #[error("Platform '{0}' is not buildable. We have no build machines for it")]
+    UnbuildablePlatform(String),
+    #[error("Unable to determine the host platform: DD_OS is not set, and {0}")]
+    UndetectedPlatform(String),
+    #[error("Invalid value for environment variable {var}: {reason}")]
+    InvalidEnvVar { var: String, reason: String },
+    #[error("Invalid Makebridge variable:'{0}'")]
+    InvalidMakeVar(String),
+    #[error("Unknown Profile:'{0}'")]
+    UnknownProfile(String),
+    #[error("Invalid Show Pattern:'{0}'")]
+    InvalidShowPattern(String),
+    #[error("Unknown show '{show}'.{suggestions} Use --allow-unknown-show to install to a new show.")]
+    UnknownShow { show: String, suggestions: String },
+    #[error("Missing dist artifacts for {package}:\n  {missing}")]
+    MissingArtifacts { package: String, missing: String },
     #[error("Invalid Vcs:'{0}'")]
     InvalidVcs(String),
     #[error("Missing Vcs from path:'{0}'")]
     MissingVcs(String),
+    #[error("Invalid Date:'{0}'. Expected YYYY-MM-DD or YYYY-MM-DDTHH:MM:SS")]
+    InvalidDate(String),
+    #[error("Invalid Hook Point:'{0}'")]
+    InvalidHookPoint(String),
+    #[error("Unknown Policy:'{0}'")]
+    UnknownPolicy(String),
+    #[error("Policy '{id}' in {layer} redefines the policy from {original}. A policy may not be redefined")]
+    PolicyRedefined { id: String, layer: String, original: String },
+    #[error("Install violates policy:\n  {0}")]
+    PolicyViolations(String),
+    #[error("Invalid Stage:'{0}'. Expected build, test or install")]
+    InvalidStage(String),
+    #[error("Refusing to clean '{0}', which is not within the package's private directory")]
+    UnsafeClean(String),
+    #[error("User '{user}' is not authorized to install {destination}. Membership of one of these groups is required: {groups}")]
+    Unauthorized {
+        user: String,
+        destination: String,
+        groups: String,
+    },
 }
@@ -0,0 +1,233 @@
+//! History
+//!
+//! A local journal of installs. Every install which is not a dry run appends a
+//! record, as a single line of json, whether it succeeds or fails. The journal is
+//! per user, and lives at `~/.local/share/pk-make/history.jsonl` (honoring
+//! `XDG_DATA_HOME`) unless `history_file` is configured, or `PK_MAKE_HISTORY_FILE`
+//! is set.
+//!
+//! `pk-make history` queries the journal, optionally narrowed by package and by
+//! date.
+
+// Internal crate imports
+use crate::traits::Tabulate;
+use crate::PkMakeError;
+
+// External crate imports
+use anyhow::anyhow;
+use anyhow::Error as AnyError;
+use prettytable::{row, Table};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A single install, as recorded in the journal
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+pub struct HistoryRecord {
+    /// When the install started, as a UTC timestamp, eg `2020-06-01T12:30:00Z`
+    pub timestamp: String,
+    pub user: String,
+    pub package: String,
+    pub version: String,
+    pub flavors: Vec<String>,
+    pub sites: Vec<String>,
+    pub platforms: Vec<String>,
+    pub level: Option<String>,
+    /// The vcs revision of the package root
+    pub revision: Option<String>,
+    /// The exit status of the pk commands, if they ran to completion
+    pub exit_status: Option<u32>,
+    /// The reason the install failed, if it failed before, or without, exiting
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+impl HistoryRecord {
+    /// Whether the install succeeded
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none() && self.exit_status == Some(0)
+    }
+
+    // summarize the outcome for the history table
+    fn status(&self) -> String {
+        match (self.exit_status, self.error.as_ref()) {
+            (_, Some(error)) => format!("error: {}", error),
+            (Some(0), None) => "ok".to_string(),
+            (Some(code), None) => format!("exit {}", code),
+            (None, None) => "unknown".to_string(),
+        }
+    }
+}
+
+/// The journal file
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Journal {
+    path: PathBuf,
+}
+
+impl Journal {
+    /// New up a Journal at the supplied path
+    pub fn new<I>(path: I) -> Self
+    where
+        I: Into<PathBuf>,
+    {
+        Self { path: path.into() }
+    }
+
+    /// The default path of the journal, if a home directory may be determined
+    pub fn default_path() -> Option<PathBuf> {
+        let data_home = match std::env::var_os("XDG_DATA_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => {
+                let mut home = PathBuf::from(std::env::var_os("HOME")?);
+                home.push(".local");
+                home.push("share");
+                home
+            }
+        };
+        Some(data_home.join("pk-make").join("history.jsonl"))
+    }
+
+    /// Retrieve the path to the journal
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append a record to the journal, creating it if need be
+    pub fn append(&self, record: &HistoryRecord) -> Result<(), AnyError> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+        Ok(())
+    }
+
+    /// Read every record from the journal, oldest first. A missing journal is empty.
+    pub fn records(&self) -> Result<Vec<HistoryRecord>, AnyError> {
+        if !self.path.is_file() {
+            return Ok(Vec::new());
+        }
+        std::fs::read_to_string(&self.path)?
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(idx, line)| {
+                serde_json::from_str(line).map_err(|e| {
+                    anyhow!("{}:{}: invalid record: {}", self.path.display(), idx + 1, e)
+                })
+            })
+            .collect()
+    }
+
+    /// Read the records for a package (all packages if None) which were made on
+    /// or after since, a date (`YYYY-MM-DD`) or timestamp.
+    pub fn query(
+        &self,
+        package: Option<&str>,
+        since: Option<&str>,
+    ) -> Result<Vec<HistoryRecord>, AnyError> {
+        if let Some(since) = since {
+            validate_date(since)?;
+        }
+        Ok(self
+            .records()?
+            .into_iter()
+            .filter(|r| package.map(|p| r.package == p).unwrap_or(true))
+            .filter(|r| since.map(|s| r.timestamp.as_str() >= s).unwrap_or(true))
+            .collect())
+    }
+}
+
+/// Format a SystemTime as a UTC timestamp, eg `2020-06-01T12:30:00Z`
+pub fn timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let secs_of_day = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+/// Verify that since is a date (`YYYY-MM-DD`), optionally followed by a time
+/// (`THH:MM:SS`), as used by `history --since`
+pub fn validate_date(since: &str) -> Result<(), PkMakeError> {
+    let template = "0000-00-00T00:00:00";
+    let valid = (since.len() == 10 || since.len() == template.len())
+        && since.chars().zip(template.chars()).all(|(c, t)| match t {
+            '0' => c.is_ascii_digit(),
+            _ => c == t,
+        });
+    if valid {
+        Ok(())
+    } else {
+        Err(PkMakeError::InvalidDate(since.to_string()))
+    }
+}
+
+// convert days since the unix epoch to a (year, month, day) in the proleptic
+// gregorian calendar
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// The records returned by a query, for display
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct History {
+    pub records: Vec<HistoryRecord>,
+}
+
+impl Tabulate for History {
+    fn create_table(&self) -> Table {
+        let mut table = Table::new();
+        table.add_row(row![
+            "Timestamp", "User", "Package", "Flavors", "Sites", "Platforms", "Level",
+            "Revision", "Status", "Duration"
+        ]);
+        for record in &self.records {
+            table.add_row(row![
+                record.timestamp,
+                record.user,
+                format!("{}-{}", record.package, record.version),
+                record.flavors.join("\n"),
+                record.sites.join("\n"),
+                record.platforms.join("\n"),
+                record.level.as_deref().unwrap_or(""),
+                record
+                    .revision
+                    .as_deref()
+                    .map(|r| r.chars().take(10).collect::<String>())
+                    .unwrap_or_default(),
+                record.status(),
+                format!("{:.1}s", record.duration_ms as f64 / 1000.0)
+            ]);
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+#[path = "./unit_tests/history_test.rs"]
+mod history_test;
@@ -0,0 +1,264 @@
+//! Hooks
+//!
+//! Shell commands run at fixed points around the build and install phases, eg to
+//! notify a tracking tool before a shared install, or to copy license files into the
+//! dist after a build. Hooks are configured in the `[hooks]` table of any config
+//! layer, and the hooks of every layer run, in order of increasing precedence
+//! (system, then user, then package):
+//!
+//! ```toml
+//! [hooks]
+//! pre-install = ["notify-tracker --package $PK_HOOK_PACKAGE --level $PK_HOOK_LEVEL"]
+//! post-build = ["cp LICENSE $PK_HOOK_DIST_DIR/"]
+//! on-failure = ["notify-tracker --failed"]
+//! ```
+//!
+//! Each hook runs from the package root via the same executor as the pk commands,
+//! with the resolved context supplied as `PK_HOOK_*` environment variables. A hook
+//! which fails aborts the run, other than an `on-failure` hook, which only warns.
+
+// Internal crate imports
+use crate::flavor::Flavor;
+use crate::level::Level;
+use crate::utils::exec_cmd_with_env;
+use crate::BuildEnv;
+use crate::ManifestInfo;
+use crate::PkMakeError;
+
+// External crate imports
+use anyhow::anyhow;
+use anyhow::Error as AnyError;
+// IndexSet provides consistent ordering of keys based on insertion
+// order
+use indexmap::IndexSet as HashSet;
+use serde::Deserialize;
+use std::convert::TryFrom;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// The points at which hooks may run
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum HookPoint {
+    PreBuild,
+    PostBuild,
+    PreInstall,
+    PostInstall,
+    OnFailure,
+}
+
+impl HookPoint {
+    /// Every hook point, in the order in which they may run
+    pub const ALL: [HookPoint; 5] = [
+        Self::PreBuild,
+        Self::PostBuild,
+        Self::PreInstall,
+        Self::PostInstall,
+        Self::OnFailure,
+    ];
+
+    /// Retrieve the name of the hook point, as used in config
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::PreBuild => "pre-build",
+            Self::PostBuild => "post-build",
+            Self::PreInstall => "pre-install",
+            Self::PostInstall => "post-install",
+            Self::OnFailure => "on-failure",
+        }
+    }
+
+    /// Overrides the auto generated trait impl of from to provide a
+    /// fallible version
+    pub fn from<I>(input: I) -> Result<Self, PkMakeError>
+    where
+        I: AsRef<str> + std::fmt::Debug,
+    {
+        match input.as_ref() {
+            "pre-build" => Ok(Self::PreBuild),
+            "post-build" => Ok(Self::PostBuild),
+            "pre-install" => Ok(Self::PreInstall),
+            "post-install" => Ok(Self::PostInstall),
+            "on-failure" => Ok(Self::OnFailure),
+            _ => Err(PkMakeError::InvalidHookPoint(input.as_ref().to_string())),
+        }
+    }
+}
+
+impl fmt::Display for HookPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for HookPoint {
+    type Err = PkMakeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from(s)
+    }
+}
+
+impl TryFrom<&str> for HookPoint {
+    type Error = PkMakeError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::from(value)
+    }
+}
+
+impl TryFrom<String> for HookPoint {
+    type Error = PkMakeError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::from(value)
+    }
+}
+
+/// The commands configured for each hook point
+#[derive(Debug, PartialEq, Eq, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Hooks {
+    pub pre_build: Vec<String>,
+    pub post_build: Vec<String>,
+    pub pre_install: Vec<String>,
+    pub post_install: Vec<String>,
+    pub on_failure: Vec<String>,
+}
+
+impl Hooks {
+    /// Retrieve the commands for a hook point
+    pub fn get(&self, point: HookPoint) -> &[String] {
+        match point {
+            HookPoint::PreBuild => &self.pre_build,
+            HookPoint::PostBuild => &self.post_build,
+            HookPoint::PreInstall => &self.pre_install,
+            HookPoint::PostInstall => &self.post_install,
+            HookPoint::OnFailure => &self.on_failure,
+        }
+    }
+
+    /// Append the commands of another set of hooks, which run after these
+    pub fn extend(&mut self, other: &Hooks) {
+        self.pre_build.extend(other.pre_build.iter().cloned());
+        self.post_build.extend(other.post_build.iter().cloned());
+        self.pre_install.extend(other.pre_install.iter().cloned());
+        self.post_install.extend(other.post_install.iter().cloned());
+        self.on_failure.extend(other.on_failure.iter().cloned());
+    }
+
+    /// Whether no hooks are configured
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Run the commands for a hook point in order, from the package root, stopping
+    /// at the first which fails. When dry_run is set, the commands are printed
+    /// instead.
+    pub fn run(
+        &self,
+        point: HookPoint,
+        context: &HookContext,
+        dry_run: bool,
+        verbose: bool,
+    ) -> Result<(), AnyError> {
+        let env = context.env(point);
+        for cmd in self.get(point) {
+            if dry_run || verbose {
+                println!("hook {}: {}", point, cmd);
+            }
+            if dry_run {
+                continue;
+            }
+            let exit_status = exec_cmd_with_env(cmd, &context.package_root, &env)?;
+            if !exit_status.success() {
+                return Err(anyhow!(
+                    "The {} hook '{}' failed with {:?}",
+                    point,
+                    cmd,
+                    exit_status
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Run the on-failure hooks, supplying the reason for the failure. As the run
+    /// has already failed, a failing on-failure hook only produces a warning.
+    pub fn run_on_failure(&self, context: &HookContext, error: &str, verbose: bool) {
+        let context = HookContext {
+            error: Some(error.to_string()),
+            ..context.clone()
+        };
+        if let Err(e) = self.run(HookPoint::OnFailure, &context, false, verbose) {
+            eprintln!("Warning: {}", e);
+        }
+    }
+}
+
+/// The resolved context which hooks receive as environment variables
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct HookContext {
+    pub package_root: PathBuf,
+    pub package: String,
+    pub version: String,
+    pub flavors: Vec<String>,
+    pub level: Option<String>,
+    pub dist_dir: PathBuf,
+    /// The reason the run failed, supplied to on-failure hooks
+    pub error: Option<String>,
+}
+
+impl HookContext {
+    /// Resolve the context of the package in build_env. The flavors default to
+    /// those of the manifest, and a relative dist_dir is relative to the package
+    /// root.
+    pub fn new(
+        build_env: &BuildEnv,
+        dist_dir: Option<&str>,
+        flavors: Option<&HashSet<Flavor>>,
+        level: Option<&Level>,
+    ) -> Result<Self, AnyError> {
+        let manifest_info = ManifestInfo::from_path(build_env.manifest.as_ref())?;
+        let flavors = match flavors {
+            Some(flavors) => flavors.iter().collect::<Vec<_>>(),
+            None => manifest_info.flavors.iter().collect::<Vec<_>>(),
+        };
+        Ok(Self {
+            package_root: build_env.package_root.clone(),
+            package: manifest_info.name().to_string(),
+            version: manifest_info.version().to_string(),
+            flavors: flavors.iter().map(|f| f.as_str().to_string()).collect(),
+            level: level.map(|l| l.to_string()),
+            dist_dir: dist_dir
+                .map(|d| build_env.package_root.join(d))
+                .unwrap_or_else(|| build_env.dist_dir.clone()),
+            error: None,
+        })
+    }
+
+    /// The environment variables supplied to the hooks at point
+    pub fn env(&self, point: HookPoint) -> Vec<(String, String)> {
+        let mut env = vec![
+            ("PK_HOOK", point.as_str().to_string()),
+            ("PK_HOOK_PACKAGE_ROOT", display(&self.package_root)),
+            ("PK_HOOK_PACKAGE", self.package.clone()),
+            ("PK_HOOK_VERSION", self.version.clone()),
+            ("PK_HOOK_FLAVORS", self.flavors.join(",")),
+            ("PK_HOOK_LEVEL", self.level.clone().unwrap_or_default()),
+            ("PK_HOOK_DIST_DIR", display(&self.dist_dir)),
+        ];
+        if let Some(ref error) = self.error {
+            env.push(("PK_HOOK_ERROR", error.clone()));
+        }
+        env.into_iter().map(|(k, v)| (k.to_string(), v)).collect()
+    }
+}
+
+fn display(path: &Path) -> String {
+    path.display().to_string()
+}
+
+#[cfg(test)]
+#[path = "./unit_tests/hooks_test.rs"]
+mod hooks_test;
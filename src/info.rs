@@ -0,0 +1,159 @@
+//! Info
+//!
+//! Summarize what pk-make makes of a checkout, for `pk-make info`: the resolved
+//! BuildEnv, the parsed manifest, and the defaults each target would apply, from
+//! the config files (overlaid with a profile, if requested) and the `PK_MAKE_*`
+//! environment variables.
+
+// Internal crate imports
+use crate::config::{Config, Section};
+use crate::traits::Tabulate;
+use crate::BuildEnv;
+use crate::EnvDefaults;
+use crate::ManifestInfo;
+use crate::PkMakeError;
+
+// External crate imports
+use anyhow::Error as AnyError;
+use prettytable::{row, Table};
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// A default which a target would apply when the option is not supplied
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct TargetDefault {
+    pub target: String,
+    pub field: String,
+    pub value: String,
+    /// The config layer, and profile, the value came from
+    pub source: String,
+}
+
+/// The resolved environment and manifest of a package
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct PackageInfo {
+    pub package_root: PathBuf,
+    pub dd_os: String,
+    pub dd_os_source: String,
+    pub dd_show: Option<String>,
+    pub dd_show_source: Option<String>,
+    pub vcs: Option<String>,
+    /// The manifest found by BuildEnv, ie manifest.yaml, pk.yaml or manifest/manifest
+    pub manifest: PathBuf,
+    pub name: String,
+    pub version: String,
+    pub flavors: Vec<String>,
+    pub description: Option<String>,
+    pub url: Option<String>,
+    pub profile: Option<String>,
+    pub defaults: Vec<TargetDefault>,
+    /// The `PK_MAKE_*` variables which are set, as (name, value) pairs
+    pub environment: Vec<(String, String)>,
+}
+
+impl PackageInfo {
+    /// New up a PackageInfo. A profile, if supplied, must be defined by the config,
+    /// and overlays the sections it defines options for.
+    pub fn new(
+        build_env: &BuildEnv,
+        config: &Config,
+        profile: Option<&str>,
+        env: &EnvDefaults,
+    ) -> Result<Self, AnyError> {
+        let manifest_info = ManifestInfo::from_path(&build_env.manifest)?;
+        if let Some(profile) = profile {
+            if !config.profile_names().contains(&profile) {
+                return Err(PkMakeError::UnknownProfile(profile.to_string()).into());
+            }
+        }
+        let mut defaults = Vec::new();
+        for section in Section::ALL.iter() {
+            let section_defaults = profile
+                .and_then(|p| config.profile_defaults(*section, p).ok())
+                .unwrap_or_else(|| config.defaults(*section));
+            for (field, value, origin) in section_defaults.entries() {
+                defaults.push(TargetDefault {
+                    target: section.as_str().to_string(),
+                    field: field.to_string(),
+                    value,
+                    source: origin.describe(),
+                });
+            }
+        }
+        Ok(Self {
+            package_root: build_env.package_root.clone(),
+            dd_os: build_env.dd_os.to_string(),
+            dd_os_source: build_env.dd_os_source.to_string(),
+            dd_show: build_env.dd_show.clone(),
+            dd_show_source: build_env.dd_show_source.as_ref().map(|s| s.to_string()),
+            vcs: build_env.vcs.as_ref().map(|v| v.to_string()),
+            manifest: build_env.manifest.clone(),
+            flavors: manifest_info
+                .flavors
+                .iter()
+                .map(|f| f.as_str().to_string())
+                .collect(),
+            name: manifest_info.name,
+            version: manifest_info.version,
+            description: manifest_info.description,
+            url: manifest_info.url,
+            profile: profile.map(|p| p.to_string()),
+            defaults,
+            environment: env
+                .vars()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        })
+    }
+}
+
+//
+// Tabulate implementation
+//
+impl Tabulate for PackageInfo {
+    fn create_table(&self) -> Table {
+        let none = || "None".to_string();
+        let mut table = Table::new();
+        table.add_row(row!["Field", "Value"]);
+        table.add_row(row!["package_root", self.package_root.display()]);
+        table.add_row(row![
+            "dd_os",
+            format!("{} (from {})", self.dd_os, self.dd_os_source)
+        ]);
+        table.add_row(row![
+            "dd_show",
+            match (&self.dd_show, &self.dd_show_source) {
+                (Some(show), Some(source)) => format!("{} (from {})", show, source),
+                (Some(show), None) => show.clone(),
+                _ => none(),
+            }
+        ]);
+        table.add_row(row!["vcs", self.vcs.clone().unwrap_or_else(none)]);
+        table.add_row(row!["manifest", self.manifest.display()]);
+        table.add_row(row!["name", self.name]);
+        table.add_row(row!["version", self.version]);
+        table.add_row(row!["flavors", self.flavors.join("\n")]);
+        table.add_row(row![
+            "description",
+            self.description.clone().unwrap_or_else(none)
+        ]);
+        table.add_row(row!["url", self.url.clone().unwrap_or_else(none)]);
+        if let Some(ref profile) = self.profile {
+            table.add_row(row!["profile", profile]);
+        }
+        for default in &self.defaults {
+            table.add_row(row![
+                format!("{}.{}", default.target, default.field),
+                format!("{} (from {})", default.value, default.source)
+            ]);
+        }
+        for (name, value) in &self.environment {
+            table.add_row(row![name, value]);
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+#[path = "./unit_tests/info_test.rs"]
+mod info_test;
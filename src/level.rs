@@ -0,0 +1,177 @@
+//! Level
+//!
+//! Models the pk level-spec which identifies where a recipe executes. A level is
+//! either `facility`, or a show optionally narrowed by further components (eg
+//! sequence and shot), optionally followed by `.work` to target the work area:
+//!
+//! - `facility`
+//! - `dev01`
+//! - `dev01.work`
+//! - `dev01.rd.9999.work`
+//!
+//! A Level converts to and from the Context plus show pair familiar from the
+//! previous build system.
+
+// Internal crate imports
+use crate::context::Context;
+use crate::PkMakeError;
+
+// External crate imports
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+/// The level-spec for the facility
+const FACILITY: &str = "facility";
+/// The final level-spec component identifying the work area
+const WORK: &str = "work";
+
+/// A validated pk level-spec
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Level {
+    Facility,
+    /// A show level. `path` holds any components below the show, and `work`
+    /// indicates the work area (ie a trailing `.work`)
+    Show {
+        show: String,
+        path: Vec<String>,
+        work: bool,
+    },
+}
+
+impl Level {
+    /// Construct the Level corresponding to a context and show. The facility show
+    /// always maps to the facility level.
+    pub fn from_context<I>(context: &Context, show: I) -> Result<Self, PkMakeError>
+    where
+        I: AsRef<str>,
+    {
+        let show = show.as_ref();
+        if context == &Context::Facility || show.eq_ignore_ascii_case(FACILITY) {
+            return Ok(Level::Facility);
+        }
+        validate_component(show, show)?;
+        Ok(Level::Show {
+            show: show.to_string(),
+            path: Vec::new(),
+            work: context == &Context::User,
+        })
+    }
+
+    /// The Context implied by the level
+    pub fn context(&self) -> Context {
+        match self {
+            Self::Facility => Context::Facility,
+            Self::Show { work: true, .. } => Context::User,
+            Self::Show { .. } => Context::Shared,
+        }
+    }
+
+    /// The show, unless this is the facility level
+    pub fn show(&self) -> Option<&str> {
+        match self {
+            Self::Facility => None,
+            Self::Show { show, .. } => Some(show.as_str()),
+        }
+    }
+
+    /// Whether this is the facility level
+    pub fn is_facility(&self) -> bool {
+        self == &Self::Facility
+    }
+
+    /// Whether the level targets a work area
+    pub fn is_work(&self) -> bool {
+        matches!(self, Self::Show { work: true, .. })
+    }
+
+    /// Overrides the auto generated trait impl of from to provide a
+    /// fallible version
+    pub fn from<I>(input: I) -> Result<Self, PkMakeError>
+    where
+        I: AsRef<str>,
+    {
+        Self::try_from(input.as_ref())
+    }
+}
+
+// level-spec components must be non empty and consist of alphanumerics, `_` or `-`.
+fn validate_component(component: &str, spec: &str) -> Result<(), PkMakeError> {
+    if component.is_empty()
+        || !component
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(PkMakeError::InvalidLevel(spec.to_string()));
+    }
+    Ok(())
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Facility => write!(f, "{}", FACILITY),
+            Self::Show { show, path, work } => {
+                write!(f, "{}", show)?;
+                for component in path {
+                    write!(f, ".{}", component)?;
+                }
+                if *work {
+                    write!(f, ".{}", WORK)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = PkMakeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case(FACILITY) {
+            return Ok(Level::Facility);
+        }
+        let mut components = s.split('.').collect::<Vec<_>>();
+        let work = components.len() > 1 && components.last() == Some(&WORK);
+        if work {
+            components.pop();
+        }
+        for component in &components {
+            validate_component(component, s)?;
+            // facility has no sub levels, and work may only appear at the end
+            if component.eq_ignore_ascii_case(FACILITY) || *component == WORK {
+                return Err(PkMakeError::InvalidLevel(s.to_string()));
+            }
+        }
+        let show = components.remove(0).to_string();
+        Ok(Level::Show {
+            show,
+            path: components.into_iter().map(|c| c.to_string()).collect(),
+            work,
+        })
+    }
+}
+
+impl TryFrom<&str> for Level {
+    type Error = PkMakeError;
+
+    fn try_from(input: &str) -> Result<Self, Self::Error> {
+        Self::from_str(input)
+    }
+}
+
+impl TryFrom<String> for Level {
+    type Error = PkMakeError;
+
+    fn try_from(input: String) -> Result<Self, Self::Error> {
+        Self::from_str(&input)
+    }
+}
+
+//
+// Import Tests
+//
+#[cfg(test)]
+#[path = "./unit_tests/level_test.rs"]
+mod level_test;
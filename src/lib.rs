@@ -1,27 +1,50 @@
-#[macro_use]
-extern crate prettytable;
+pub mod authorization;
+pub mod build_cache;
 pub mod build_env;
+pub mod compat;
+pub mod config;
 pub mod context;
+pub mod dist;
+pub mod env_defaults;
 pub mod error;
 pub mod flavor;
+pub mod history;
+pub mod hooks;
+pub mod info;
+pub mod level;
 pub mod manifest_info;
 pub mod named_site;
 pub mod override_pair;
 pub mod platform;
+pub mod policy;
+pub mod show_pattern;
+pub mod show_registry;
 pub mod site;
+pub mod staging;
 pub mod targets;
 pub mod traits;
 pub mod utils;
 pub mod vcs;
 
+pub use authorization::{AuthRule, UserGroups};
+pub use build_cache::BuildCache;
 pub use build_env::BuildEnv;
+pub use config::Config;
 pub use context::Context;
+pub use env_defaults::EnvDefaults;
 pub use error::PkMakeError;
 pub use flavor::Flavor;
+pub use history::{History, HistoryRecord, Journal};
+pub use hooks::{HookContext, HookPoint, Hooks};
+pub use info::PackageInfo;
+pub use level::Level;
 pub use manifest_info::{Manifest, ManifestInfo};
-pub use named_site::NamedSite;
+pub use named_site::{NamedSite, SiteRegistry};
 pub use override_pair::OverridePair;
-pub use platform::Platform;
+pub use platform::{Platform, PlatformRegistry};
+pub use policy::Policy;
+pub use show_pattern::ShowPattern;
+pub use show_registry::ShowRegistry;
 pub use site::Site;
 pub use targets::*;
 pub use vcs::Vcs;
@@ -3,15 +3,27 @@
 //! Implements pk-make cli and invokes pk_make lib
 // crate imports
 use anyhow::Error as AnyError;
-use pk_make::{context, flavor, platform, site, OverridePair, Vcs};
-use pk_make::targets::{Build, Docs, Install, Run, Test};
-use pk_make::traits::Doit;
-use std::path::PathBuf;
+use pk_make::{
+    context, flavor, BuildEnv, Config, EnvDefaults, History, Journal, Level, OverridePair,
+    PackageInfo, PkMakeError, PlatformRegistry, ShowRegistry, SiteRegistry, Vcs,
+};
+use pk_make::compat::MakeArgs;
+use pk_make::config::{Defaults, Section};
+use pk_make::targets::pipeline::Stage;
+use pk_make::targets::{
+    Build, Clean, Docs, Install, Pipeline, Promote, Run, Test, Uninstall,
+};
+use pk_make::traits::{Doit, Tabulate};
+use std::path::{Path, PathBuf};
 use structopt::StructOpt;
 
 
 #[derive(Debug, StructOpt)]
-#[structopt(name = "pk-make", about = "Invoke pk recipes.")]
+#[structopt(
+    name = "pk-make",
+    about = "Invoke pk recipes.",
+    after_help = "Each option may also be supplied via a PK_MAKE_<OPTION> environment variable, eg PK_MAKE_SITES=portland,vancouver"
+)]
 enum Opt {
     /// Build one or more flavors of a package
     #[structopt(display_order = 1)]
@@ -19,6 +31,9 @@ enum Opt {
         /// clean
         #[structopt(long)]
         clean: bool,
+        /// Build every flavor, even those whose cache key is unchanged
+        #[structopt(long)]
+        rebuild: bool,
         /// Do not build the docs when building the main artifact(s)
         #[structopt(long = "skip-docs")]
         skip_docs: bool,
@@ -35,9 +50,9 @@ enum Opt {
         #[structopt(short, long)]
         flavor: Option<Vec<flavor::Flavor>>,
 
-        /// The target level's repository specified as a level-spec
+        /// The target level's repository specified as a level-spec, eg facility | <show> | <show>.work
         #[structopt(short = "L", long)]
-        level: Option<String>,
+        level: Option<Level>,
 
         /// Only write out package metadata
         #[structopt(long = "metadata-only")]
@@ -49,7 +64,7 @@ enum Opt {
 
         /// Provide the platform(s) to build for. This flag may be repeated.
         #[structopt(short = "P", long)]
-        platform: Option<Vec<platform::Platform>>,
+        platform: Option<Vec<String>>,
 
         /// Provide more verbose output
         #[structopt(short, long)]
@@ -63,6 +78,10 @@ enum Opt {
         #[structopt(long)]
         work: bool,
 
+        /// Apply the named profile from the config files
+        #[structopt(long)]
+        profile: Option<String>,
+
         /// Optionally specify a path to the package root directory
         #[structopt(short = "r", long = "package-root", parse(from_os_str))]
         package_root: Option<PathBuf>,
@@ -74,10 +93,27 @@ enum Opt {
         #[structopt(long)]
         clean: bool,
 
+        /// Build every flavor, even those whose cache key is unchanged
+        #[structopt(long)]
+        rebuild: bool,
+
         /// Print out commands but do not execute them
         #[structopt(short = "n", long = "dry-run")]
         dry_run: bool,
 
+        /// Report where each package would be installed, without building or installing
+        #[structopt(long)]
+        preview: bool,
+
+        /// Install the existing dist artifacts without building
+        #[structopt(long = "no-build")]
+        no_build: bool,
+
+        /// Stage the dist to <to-dir>/<name>/<version>[_flavor]/<platform>, and record it in
+        /// an index file, instead of installing it to a level
+        #[structopt(long = "to-dir", parse(from_os_str))]
+        to_dir: Option<PathBuf>,
+
         /// Do not build the docs as part of the install  
         #[structopt(long = "skip-docs")]
         skip_docs: bool,
@@ -90,14 +126,18 @@ enum Opt {
         #[structopt(long)]
         show: Option<String>,
 
+        /// Permit a show which is not in the list of known shows
+        #[structopt(long = "allow-unknown-show")]
+        allow_unknown_show: bool,
+
         /// The site or sites. Site may be all | local | <site>. This may be
         /// repeated one or more times
         #[structopt(short, long)]
-        site: Option<Vec<site::Site>>,
+        site: Option<Vec<String>>,
 
         /// Optionally provide the platform or platforms to build for
         #[structopt(short = "P", long)]
-        platform: Option<Vec<platform::Platform>>,
+        platform: Option<Vec<String>>,
 
         /// Optionally provide the flavor or flavors to build. May be vanilla, %, or a flavor name
         #[structopt(short, long)]
@@ -115,9 +155,9 @@ enum Opt {
         #[structopt(short, long = "dist-dir")]
         dist_dir: Option<String>,
 
-        /// The target level's repository specified as a level-spec
+        /// The target level's repository specified as a level-spec, eg facility | <show> | <show>.work
         #[structopt(short = "L", long)]
-        level: Option<String>,
+        level: Option<Level>,
 
         /// Override version from version-lock
         #[structopt(short, long = "override")]
@@ -131,6 +171,11 @@ enum Opt {
         #[structopt(long)]
         work: bool,
 
+        /// Waive a violation of the configured policy with this id, if the policy
+        /// permits it. This may be repeated one or more times
+        #[structopt(long = "override-policy")]
+        override_policy: Option<Vec<String>>,
+
         /// choose a vcs system manually (sometimes necessary)
         #[structopt(long)]
         vcs: Option<Vcs>,
@@ -143,6 +188,10 @@ enum Opt {
         #[structopt(short = "j", long = "max-jobs")]
         max_jobs: Option<u8>,
 
+        /// Apply the named profile from the config files
+        #[structopt(long)]
+        profile: Option<String>,
+
         /// Optionally specify a path to the package root directory
         #[structopt(short = "r", long = "package-root", parse(from_os_str))]
         package_root: Option<PathBuf>,
@@ -168,12 +217,16 @@ enum Opt {
 
         /// Optionally provide the platform or platforms to build for
         #[structopt(short = "P", long)]
-        platform: Option<Vec<platform::Platform>>,
+        platform: Option<Vec<String>>,
 
         /// Optionally provide the flavor or flavors to build. May be vanilla, %, or a flavor name
         #[structopt(short, long)]
         flavor: Option<Vec<flavor::Flavor>>,
 
+        /// Apply the named profile from the config files
+        #[structopt(long)]
+        profile: Option<String>,
+
         /// Optionally specify a path to the package root directory
         #[structopt(short = "r", long = "package-root", parse(from_os_str))]
         package_root: Option<PathBuf>,
@@ -195,7 +248,7 @@ enum Opt {
 
         /// Optionally provide the platform or platforms to build for
         #[structopt(short = "P", long)]
-        platform: Option<Vec<platform::Platform>>,
+        platform: Option<Vec<String>>,
 
         /// Optionally provide the flavor or flavors to build. May be vanilla, %, or a flavor name
         #[structopt(short, long)]
@@ -205,6 +258,10 @@ enum Opt {
         #[structopt(short = "D", long)]
         define: Option<Vec<String>>,
 
+        /// Apply the named profile from the config files
+        #[structopt(long)]
+        profile: Option<String>,
+
         /// Optionally specify a path to the package root directory
         #[structopt(short = "r", long = "package-root", parse(from_os_str))]
         package_root: Option<PathBuf>,
@@ -219,6 +276,10 @@ enum Opt {
         #[structopt(short = "n", long = "dry-run")]
         dry_run: bool,
 
+        /// Apply the named profile from the config files
+        #[structopt(long)]
+        profile: Option<String>,
+
         /// Optionally specify a path to the package root directory
         #[structopt(short = "r", long = "package-root", parse(from_os_str))]
         package_root: Option<PathBuf>,
@@ -229,7 +290,7 @@ enum Opt {
 
         /// Optionally provide the platform or platforms to build for
         #[structopt(short = "P", long)]
-        platform: Option<Vec<platform::Platform>>,
+        platform: Option<Vec<String>>,
 
         /// Optionally provide the flavor or flavors to build. May be vanilla, %, or a flavor name
         #[structopt(short, long)]
@@ -237,13 +298,636 @@ enum Opt {
 
         vars: Vec<String>,
     },
+    #[structopt(display_order = 6)]
+    /// Install an existing work build at show level, without rebuilding
+    Promote {
+        /// The work level to promote from, eg <show>.work
+        #[structopt(long)]
+        from: Option<Level>,
+
+        /// The level to promote to, eg <show>
+        #[structopt(long)]
+        to: Option<Level>,
+
+        /// Print out commands but do not execute them
+        #[structopt(short = "n", long = "dry-run")]
+        dry_run: bool,
+
+        /// Controls verbose output to shell
+        #[structopt(short, long)]
+        verbose: bool,
+
+        /// Override the default Output Distribution Directory holding the artifacts
+        #[structopt(short, long = "dist-dir")]
+        dist_dir: Option<String>,
+
+        /// Permit a show which is not in the list of known shows
+        #[structopt(long = "allow-unknown-show")]
+        allow_unknown_show: bool,
+
+        /// The site or sites. Site may be all | local | <site>. This may be
+        /// repeated one or more times
+        #[structopt(short, long)]
+        site: Option<Vec<String>>,
+
+        /// Optionally provide the platform or platforms to install
+        #[structopt(short = "P", long)]
+        platform: Option<Vec<String>>,
+
+        /// Waive a violation of the configured policy with this id, if the policy
+        /// permits it. This may be repeated one or more times
+        #[structopt(long = "override-policy")]
+        override_policy: Option<Vec<String>>,
+
+        /// Optionally provide the flavor or flavors to promote. Defaults to all of them
+        #[structopt(short, long)]
+        flavor: Option<Vec<flavor::Flavor>>,
+
+        /// Specify the maximum number of workers used
+        #[structopt(short = "j", long = "max-jobs")]
+        max_jobs: Option<u8>,
+
+        /// Optionally specify a path to the package root directory
+        #[structopt(short = "r", long = "package-root", parse(from_os_str))]
+        package_root: Option<PathBuf>,
+    },
+    /// Invoke a target using Makebridge style KEY=VALUE variables,
+    /// eg `pk-make compat install CONTEXT=shared SITES=all FLAVOUR=a,b`
+    #[structopt(display_order = 7)]
+    Compat {
+        /// The target to invoke. May be build | install | docs | test
+        target: String,
+
+        /// Makebridge variables, eg CONTEXT=shared
+        vars: Vec<String>,
+    },
+    #[structopt(display_order = 8)]
+    /// Inspect the layered pk-make configuration
+    Config {
+        #[structopt(subcommand)]
+        cmd: ConfigCmd,
+    },
+    #[structopt(display_order = 9)]
+    /// Remove the manifest's version of a package from the levels, sites, platforms
+    /// and flavors that install would install to
+    Uninstall {
+        #[structopt(flatten)]
+        remove: RemoveOpts,
+    },
+    #[structopt(display_order = 10)]
+    /// Roll back to an earlier version, by uninstalling the manifest's version
+    Rollback {
+        /// The earlier version to roll back to, which the history journal must record
+        /// as installed to the level, sites and platforms
+        #[structopt(long)]
+        to: String,
+
+        #[structopt(flatten)]
+        remove: RemoveOpts,
+    },
+    #[structopt(display_order = 11)]
+    /// Query the journal of past installs
+    History {
+        /// Only report installs of this package
+        #[structopt(long)]
+        package: Option<String>,
+        /// Only report installs made on or after this date (YYYY-MM-DD)
+        #[structopt(long)]
+        since: Option<String>,
+        /// Print the records as json rather than a table
+        #[structopt(long)]
+        json: bool,
+        /// Optionally specify the journal file. Defaults to the configured
+        /// history_file, or ~/.local/share/pk-make/history.jsonl
+        #[structopt(long, parse(from_os_str))]
+        file: Option<PathBuf>,
+        /// Optionally specify a path to the package root directory
+        #[structopt(short = "r", long = "package-root", parse(from_os_str))]
+        package_root: Option<PathBuf>,
+    },
+    #[structopt(display_order = 12)]
+    /// Run several targets in turn, eg `pipeline build test install` or
+    /// `pipeline build+test+install`, stopping at the first which fails
+    Pipeline {
+        /// The stages to run, in order: build | test | install. Stages may be
+        /// joined with +
+        #[structopt(required = true)]
+        stages: Vec<String>,
+
+        /// Print out commands but do not execute them
+        #[structopt(short = "n", long = "dry-run")]
+        dry_run: bool,
+
+        /// Controls  verbose output to shell
+        #[structopt(short, long)]
+        verbose: bool,
+
+        /// Clean the build before building
+        #[structopt(long)]
+        clean: bool,
+
+        /// Do not build the docs
+        #[structopt(long = "skip-docs")]
+        skip_docs: bool,
+
+        /// Context may be either facility | shared | user. Defaults to user.
+        #[structopt(short, long)]
+        context: Option<context::Context>,
+
+        /// The current show
+        #[structopt(long)]
+        show: Option<String>,
+
+        /// Permit a show which is not in the list of known shows
+        #[structopt(long = "allow-unknown-show")]
+        allow_unknown_show: bool,
+
+        /// The target level's repository specified as a level-spec, eg facility | <show> | <show>.work
+        #[structopt(short = "L", long)]
+        level: Option<Level>,
+
+        /// The site or sites to install to. Site may be all | local | <site>. This
+        /// may be repeated one or more times
+        #[structopt(short, long)]
+        site: Option<Vec<String>>,
+
+        /// Optionally provide the platform or platforms to build, test and install
+        #[structopt(short = "P", long)]
+        platform: Option<Vec<String>>,
+
+        /// Optionally provide the flavor or flavors. May be vanilla, %, or a flavor name
+        #[structopt(short, long)]
+        flavor: Option<Vec<flavor::Flavor>>,
+
+        /// Pass variable through to the recipes
+        #[structopt(short = "D", long)]
+        define: Option<Vec<String>>,
+
+        /// Waive a violation of the configured policy with this id, if the policy
+        /// permits it. This may be repeated one or more times
+        #[structopt(long = "override-policy")]
+        override_policy: Option<Vec<String>>,
+
+        /// Override the default Output Distribution Directory
+        #[structopt(short, long = "dist-dir")]
+        dist_dir: Option<String>,
+
+        /// Apply the named profile from the config files
+        #[structopt(long)]
+        profile: Option<String>,
+
+        /// Optionally specify a path to the package root directory
+        #[structopt(short = "r", long = "package-root", parse(from_os_str))]
+        package_root: Option<PathBuf>,
+    },
+    #[structopt(display_order = 13)]
+    /// Remove build output from the package's private directory
+    Clean {
+        /// Only clean the build directory
+        #[structopt(long)]
+        build: bool,
+
+        /// Only clean the dist directory
+        #[structopt(long)]
+        dist: bool,
+
+        /// Only remove the artifacts of the flavor or flavors. May be vanilla, or a
+        /// flavor name
+        #[structopt(short, long)]
+        flavor: Option<Vec<flavor::Flavor>>,
+
+        /// Also remove the artifacts of flavors which are no longer in the manifest
+        #[structopt(long)]
+        stale: bool,
+
+        /// Report what would be removed, and its size, without removing it
+        #[structopt(short = "n", long = "dry-run")]
+        dry_run: bool,
+
+        /// Controls  verbose output to shell
+        #[structopt(short, long)]
+        verbose: bool,
+
+        /// Optionally specify a path to the package root directory
+        #[structopt(short = "r", long = "package-root", parse(from_os_str))]
+        package_root: Option<PathBuf>,
+    },
+    #[structopt(display_order = 14)]
+    /// Summarize the package's environment, manifest and defaults
+    Info {
+        /// Print the summary as json rather than a table
+        #[structopt(long)]
+        json: bool,
+
+        /// Overlay the named profile from the config files on the defaults
+        #[structopt(long)]
+        profile: Option<String>,
+
+        /// Optionally specify a path to the package root directory
+        #[structopt(short = "r", long = "package-root", parse(from_os_str))]
+        package_root: Option<PathBuf>,
+    },
+}
+
+/// The options shared by uninstall and rollback, which mirror those of install
+#[derive(Debug, StructOpt)]
+struct RemoveOpts {
+    /// Print out commands but do not execute them
+    #[structopt(short = "n", long = "dry-run")]
+    dry_run: bool,
+
+    /// Controls verbose output to shell
+    #[structopt(short, long)]
+    verbose: bool,
+
+    /// Confirm removal from all sites without prompting
+    #[structopt(short, long)]
+    yes: bool,
+
+    /// Context may be either shared | user. Defaults to user.
+    #[structopt(short, long)]
+    context: Option<context::Context>,
+
+    /// The current show
+    #[structopt(long)]
+    show: Option<String>,
+
+    /// Permit a show which is not in the list of known shows
+    #[structopt(long = "allow-unknown-show")]
+    allow_unknown_show: bool,
+
+    /// The target level's repository specified as a level-spec, eg <show> | <show>.work
+    #[structopt(short = "L", long)]
+    level: Option<Level>,
+
+    /// The site or sites. Site may be all | local | <site>. This may be
+    /// repeated one or more times
+    #[structopt(short, long)]
+    site: Option<Vec<String>>,
+
+    /// Optionally provide the platform or platforms
+    #[structopt(short = "P", long)]
+    platform: Option<Vec<String>>,
+
+    /// Optionally provide the flavor or flavors. Defaults to all of them
+    #[structopt(short, long)]
+    flavor: Option<Vec<flavor::Flavor>>,
+
+    /// Apply the named profile from the config files
+    #[structopt(long)]
+    profile: Option<String>,
+
+    /// Optionally specify a path to the package root directory
+    #[structopt(short = "r", long = "package-root", parse(from_os_str))]
+    package_root: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+enum ConfigCmd {
+    /// Print the merged configuration values and where each came from
+    Show {
+        /// Optionally specify a path to the package root directory
+        #[structopt(short = "r", long = "package-root", parse(from_os_str))]
+        package_root: Option<PathBuf>,
+    },
+}
+
+// load the layered configuration relative to the package root
+fn load_config(package_root: &Option<PathBuf>) -> Result<Config, AnyError> {
+    Config::load(package_root.as_deref().unwrap_or_else(|| Path::new(".")))
+}
+
+// the configured registry of known shows. An unreadable shows root should not
+// prevent installs, so it only disables validation.
+fn show_registry(config: &Config) -> Option<ShowRegistry> {
+    match config.show_registry() {
+        Ok(registry) => registry,
+        Err(e) => {
+            eprintln!("Warning: show validation disabled. {}", e);
+            None
+        }
+    }
+}
+
+// retrieve the defaults for a subcommand, overlaid with the requested profile if any
+fn section_defaults<'a>(
+    config: &'a Config,
+    section: Section,
+    profile: &'a Option<String>,
+) -> Result<Defaults<'a>, AnyError> {
+    Ok(match profile {
+        Some(profile) => config.profile_defaults(section, profile)?,
+        None => config.defaults(section),
+    })
+}
+
+// when verbose, report the values supplied by the profile which were not overridden
+// on the command line. `overridden` pairs each field with whether it was supplied.
+fn report_profile(defaults: &Defaults, verbose: bool, overridden: &[(&str, bool)]) {
+    if !verbose {
+        return;
+    }
+    for (field, value, origin) in defaults.entries() {
+        let from_cli = overridden.iter().any(|(f, set)| *f == field && *set);
+        if origin.profile.is_some() && !from_cli {
+            println!("Using {} = {} from {}", field, value, origin.describe());
+        }
+    }
+}
+
+impl Opt {
+    // Fill the options which were not supplied on the command line from their
+    // PK_MAKE_* variables. Sites and platforms are filled by sites_or_env and
+    // platforms_or_env instead, once the registries they are checked against have
+    // been loaded.
+    fn with_env(mut self, env: &EnvDefaults) -> Result<Self, PkMakeError> {
+        match &mut self {
+            Opt::Build {
+                clean,
+                rebuild,
+                skip_docs,
+                dry_run,
+                dist_dir,
+                flavor,
+                level,
+                metadata_only,
+                overrides,
+                verbose,
+                define,
+                work,
+                profile,
+                package_root,
+                ..
+            } => {
+                env.fill_flag(clean, "PK_MAKE_CLEAN")?;
+                env.fill_flag(rebuild, "PK_MAKE_REBUILD")?;
+                env.fill_flag(skip_docs, "PK_MAKE_SKIP_DOCS")?;
+                env.fill_flag(dry_run, "PK_MAKE_DRY_RUN")?;
+                env.fill(dist_dir, "PK_MAKE_DIST_DIR")?;
+                env.fill_list(flavor, "PK_MAKE_FLAVORS")?;
+                env.fill(level, "PK_MAKE_LEVEL")?;
+                env.fill_flag(metadata_only, "PK_MAKE_METADATA_ONLY")?;
+                env.fill_list(overrides, "PK_MAKE_OVERRIDES")?;
+                env.fill_flag(verbose, "PK_MAKE_VERBOSE")?;
+                env.fill_list(define, "PK_MAKE_DEFINES")?;
+                env.fill_flag(work, "PK_MAKE_WORK")?;
+                env.fill(profile, "PK_MAKE_PROFILE")?;
+                env.fill(package_root, "PK_MAKE_PACKAGE_ROOT")?;
+            }
+            Opt::Install {
+                clean,
+                rebuild,
+                dry_run,
+                preview,
+                no_build,
+                to_dir,
+                skip_docs,
+                context,
+                show,
+                allow_unknown_show,
+                flavor,
+                build_dir,
+                verbose,
+                dist_dir,
+                level,
+                overrides,
+                define,
+                work,
+                override_policy,
+                vcs,
+                logfile,
+                max_jobs,
+                profile,
+                package_root,
+                ..
+            } => {
+                env.fill_flag(clean, "PK_MAKE_CLEAN")?;
+                env.fill_flag(rebuild, "PK_MAKE_REBUILD")?;
+                env.fill_flag(dry_run, "PK_MAKE_DRY_RUN")?;
+                env.fill_flag(preview, "PK_MAKE_PREVIEW")?;
+                env.fill_flag(no_build, "PK_MAKE_NO_BUILD")?;
+                env.fill(to_dir, "PK_MAKE_TO_DIR")?;
+                env.fill_flag(skip_docs, "PK_MAKE_SKIP_DOCS")?;
+                env.fill(context, "PK_MAKE_CONTEXT")?;
+                env.fill(show, "PK_MAKE_SHOW")?;
+                env.fill_flag(allow_unknown_show, "PK_MAKE_ALLOW_UNKNOWN_SHOW")?;
+                env.fill_list(flavor, "PK_MAKE_FLAVORS")?;
+                env.fill(build_dir, "PK_MAKE_BUILD_DIR")?;
+                env.fill_flag(verbose, "PK_MAKE_VERBOSE")?;
+                env.fill(dist_dir, "PK_MAKE_DIST_DIR")?;
+                env.fill(level, "PK_MAKE_LEVEL")?;
+                env.fill_list(overrides, "PK_MAKE_OVERRIDES")?;
+                env.fill_list(define, "PK_MAKE_DEFINES")?;
+                env.fill_flag(work, "PK_MAKE_WORK")?;
+                env.fill_list(override_policy, "PK_MAKE_OVERRIDE_POLICY")?;
+                env.fill(vcs, "PK_MAKE_VCS")?;
+                env.fill(logfile, "PK_MAKE_LOGFILE")?;
+                env.fill(max_jobs, "PK_MAKE_MAX_JOBS")?;
+                env.fill(profile, "PK_MAKE_PROFILE")?;
+                env.fill(package_root, "PK_MAKE_PACKAGE_ROOT")?;
+            }
+            Opt::Docs {
+                dist_dir,
+                dry_run,
+                verbose,
+                define,
+                flavor,
+                profile,
+                package_root,
+                ..
+            }
+            | Opt::Test {
+                dist_dir,
+                dry_run,
+                verbose,
+                define,
+                flavor,
+                profile,
+                package_root,
+                ..
+            } => {
+                env.fill(dist_dir, "PK_MAKE_DIST_DIR")?;
+                env.fill_flag(dry_run, "PK_MAKE_DRY_RUN")?;
+                env.fill_flag(verbose, "PK_MAKE_VERBOSE")?;
+                env.fill_list(define, "PK_MAKE_DEFINES")?;
+                env.fill_list(flavor, "PK_MAKE_FLAVORS")?;
+                env.fill(profile, "PK_MAKE_PROFILE")?;
+                env.fill(package_root, "PK_MAKE_PACKAGE_ROOT")?;
+            }
+            Opt::Run {
+                dry_run,
+                verbose,
+                flavor,
+                profile,
+                package_root,
+                ..
+            } => {
+                env.fill_flag(dry_run, "PK_MAKE_DRY_RUN")?;
+                env.fill_flag(verbose, "PK_MAKE_VERBOSE")?;
+                env.fill_list(flavor, "PK_MAKE_FLAVORS")?;
+                env.fill(profile, "PK_MAKE_PROFILE")?;
+                env.fill(package_root, "PK_MAKE_PACKAGE_ROOT")?;
+            }
+            Opt::Promote {
+                from,
+                to,
+                dry_run,
+                verbose,
+                dist_dir,
+                allow_unknown_show,
+                override_policy,
+                flavor,
+                max_jobs,
+                package_root,
+                ..
+            } => {
+                env.fill(from, "PK_MAKE_FROM")?;
+                env.fill(to, "PK_MAKE_TO")?;
+                env.fill_flag(dry_run, "PK_MAKE_DRY_RUN")?;
+                env.fill_flag(verbose, "PK_MAKE_VERBOSE")?;
+                env.fill(dist_dir, "PK_MAKE_DIST_DIR")?;
+                env.fill_flag(allow_unknown_show, "PK_MAKE_ALLOW_UNKNOWN_SHOW")?;
+                env.fill_list(override_policy, "PK_MAKE_OVERRIDE_POLICY")?;
+                env.fill_list(flavor, "PK_MAKE_FLAVORS")?;
+                env.fill(max_jobs, "PK_MAKE_MAX_JOBS")?;
+                env.fill(package_root, "PK_MAKE_PACKAGE_ROOT")?;
+            }
+            Opt::Compat { .. } => (),
+            Opt::Config {
+                cmd: ConfigCmd::Show { package_root },
+            } => env.fill(package_root, "PK_MAKE_PACKAGE_ROOT")?,
+            Opt::Uninstall { remove } | Opt::Rollback { remove, .. } => {
+                remove.fill_from(env)?;
+            }
+            Opt::History {
+                package,
+                since,
+                json,
+                package_root,
+                ..
+            } => {
+                env.fill(package, "PK_MAKE_PACKAGE")?;
+                env.fill(since, "PK_MAKE_SINCE")?;
+                env.fill_flag(json, "PK_MAKE_JSON")?;
+                env.fill(package_root, "PK_MAKE_PACKAGE_ROOT")?;
+            }
+            Opt::Pipeline {
+                dry_run,
+                verbose,
+                clean,
+                skip_docs,
+                context,
+                show,
+                allow_unknown_show,
+                level,
+                flavor,
+                define,
+                override_policy,
+                dist_dir,
+                profile,
+                package_root,
+                ..
+            } => {
+                env.fill_flag(dry_run, "PK_MAKE_DRY_RUN")?;
+                env.fill_flag(verbose, "PK_MAKE_VERBOSE")?;
+                env.fill_flag(clean, "PK_MAKE_CLEAN")?;
+                env.fill_flag(skip_docs, "PK_MAKE_SKIP_DOCS")?;
+                env.fill(context, "PK_MAKE_CONTEXT")?;
+                env.fill(show, "PK_MAKE_SHOW")?;
+                env.fill_flag(allow_unknown_show, "PK_MAKE_ALLOW_UNKNOWN_SHOW")?;
+                env.fill(level, "PK_MAKE_LEVEL")?;
+                env.fill_list(flavor, "PK_MAKE_FLAVORS")?;
+                env.fill_list(define, "PK_MAKE_DEFINES")?;
+                env.fill_list(override_policy, "PK_MAKE_OVERRIDE_POLICY")?;
+                env.fill(dist_dir, "PK_MAKE_DIST_DIR")?;
+                env.fill(profile, "PK_MAKE_PROFILE")?;
+                env.fill(package_root, "PK_MAKE_PACKAGE_ROOT")?;
+            }
+            Opt::Clean {
+                build,
+                dist,
+                flavor,
+                stale,
+                dry_run,
+                verbose,
+                package_root,
+            } => {
+                env.fill_flag(build, "PK_MAKE_BUILD")?;
+                env.fill_flag(dist, "PK_MAKE_DIST")?;
+                env.fill_list(flavor, "PK_MAKE_FLAVORS")?;
+                env.fill_flag(stale, "PK_MAKE_STALE")?;
+                env.fill_flag(dry_run, "PK_MAKE_DRY_RUN")?;
+                env.fill_flag(verbose, "PK_MAKE_VERBOSE")?;
+                env.fill(package_root, "PK_MAKE_PACKAGE_ROOT")?;
+            }
+            Opt::Info {
+                json,
+                profile,
+                package_root,
+            } => {
+                env.fill_flag(json, "PK_MAKE_JSON")?;
+                env.fill(profile, "PK_MAKE_PROFILE")?;
+                env.fill(package_root, "PK_MAKE_PACKAGE_ROOT")?;
+            }
+        }
+        Ok(self)
+    }
+}
+
+impl RemoveOpts {
+    // fill the options which were not supplied on the command line from their
+    // PK_MAKE_* variables, as Opt::with_env does
+    fn fill_from(&mut self, env: &EnvDefaults) -> Result<(), PkMakeError> {
+        env.fill_flag(&mut self.dry_run, "PK_MAKE_DRY_RUN")?;
+        env.fill_flag(&mut self.verbose, "PK_MAKE_VERBOSE")?;
+        env.fill_flag(&mut self.yes, "PK_MAKE_YES")?;
+        env.fill(&mut self.context, "PK_MAKE_CONTEXT")?;
+        env.fill(&mut self.show, "PK_MAKE_SHOW")?;
+        env.fill_flag(&mut self.allow_unknown_show, "PK_MAKE_ALLOW_UNKNOWN_SHOW")?;
+        env.fill(&mut self.level, "PK_MAKE_LEVEL")?;
+        env.fill_list(&mut self.flavor, "PK_MAKE_FLAVORS")?;
+        env.fill(&mut self.profile, "PK_MAKE_PROFILE")?;
+        env.fill(&mut self.package_root, "PK_MAKE_PACKAGE_ROOT")
+    }
+}
+
+// the sites supplied on the command line, or else via PK_MAKE_SITES, checked
+// against the registry so that an invalid site names the variable
+fn sites_or_env(
+    site: Option<Vec<String>>,
+    env: &EnvDefaults,
+    registry: &SiteRegistry,
+) -> Result<Option<Vec<String>>, PkMakeError> {
+    match site {
+        Some(site) => Ok(Some(site)),
+        None => env.sites(registry),
+    }
+}
+
+// the platforms supplied on the command line, or else via PK_MAKE_PLATFORMS,
+// checked against the registry so that an invalid platform names the variable
+fn platforms_or_env(
+    platform: Option<Vec<String>>,
+    env: &EnvDefaults,
+    registry: &PlatformRegistry,
+) -> Result<Option<Vec<String>>, PkMakeError> {
+    match platform {
+        Some(platform) => Ok(Some(platform)),
+        None => env.platforms(registry),
+    }
 }
 
 fn main() -> Result<(), AnyError> {
-    let opt = Opt::from_args();
+    let env = EnvDefaults::from_env();
+    execute(Opt::from_args().with_env(&env)?, &env)
+}
+
+// dispatch the parsed command line
+fn execute(opt: Opt, env: &EnvDefaults) -> Result<(), AnyError> {
     match opt {
         Opt::Build {
             clean,
+            rebuild,
             skip_docs,
             dry_run,
             dist_dir,
@@ -255,21 +939,37 @@ fn main() -> Result<(), AnyError> {
             verbose,
             define,
             work,
+            profile,
             package_root,
         } => {
-            let mut build = Build::default()
+            let config = load_config(&package_root)?;
+            let platform = platforms_or_env(platform, env, &config.platform_registry()?)?;
+            let defaults = section_defaults(&config, Section::Build, &profile)?;
+            report_profile(
+                &defaults,
+                verbose,
+                &[
+                    ("with_docs", skip_docs),
+                    ("flavors", flavor.is_some()),
+                    ("platforms", platform.is_some()),
+                    ("defines", define.is_some()),
+                ],
+            );
+            let mut build = Build::with_platform_registry(config.platform_registry()?)
                 .clean(clean)
-                .with_docs(!skip_docs)
+                .rebuild(rebuild)
+                .with_docs(!skip_docs && defaults.with_docs().unwrap_or(true))
                 .dry_run(dry_run)
                 .dist_dir(dist_dir)
-                .flavors(flavor)?
-                .level(level)
+                .flavors(flavor.or(defaults.flavors()?))?
+                .level(level)?
                 .metadata_only(metadata_only)
                 .overrides(overrides)?
-                .platforms(platform)?
+                .platforms(platform.or(defaults.platforms()?))?
                 .verbose(verbose)
-                .defines(define)
+                .defines(define.or_else(|| defaults.defines()))
                 .work(work)
+                .hooks(config.hooks())
                 .package_root(package_root)
                 .build();
             build.doit()
@@ -277,43 +977,87 @@ fn main() -> Result<(), AnyError> {
         Opt::Install {
             skip_docs,
             dry_run,
+            preview,
+            no_build,
+            to_dir,
             context,
             show,
+            allow_unknown_show,
             site,
             platform,
             flavor,
             build_dir,
             verbose,
             clean,
+            rebuild,
             dist_dir,
             level,
             overrides,
             define,
             work,
+            override_policy,
             vcs,
             logfile,
             max_jobs,
+            profile,
             package_root,
         } => {
-            let mut install = Install::default()
+            let config = load_config(&package_root)?;
+            let site = sites_or_env(site, env, &config.site_registry()?)?;
+            let platform = platforms_or_env(platform, env, &config.platform_registry()?)?;
+            let defaults = section_defaults(&config, Section::Install, &profile)?;
+            report_profile(
+                &defaults,
+                verbose,
+                &[
+                    ("with_docs", skip_docs),
+                    ("context", context.is_some() || level.is_some()),
+                    ("sites", site.is_some()),
+                    ("platforms", platform.is_some()),
+                    ("flavors", flavor.is_some()),
+                    ("defines", define.is_some()),
+                    ("max_jobs", max_jobs.is_some()),
+                ],
+            );
+            // a configured context would conflict with an explicit level, and is
+            // irrelevant when staging to a directory
+            let default_context = if level.is_none() && to_dir.is_none() {
+                defaults.context()?
+            } else {
+                None
+            };
+            let mut install = Install::with_platform_registry(config.platform_registry()?)
                 .clean(clean)
+                .rebuild(rebuild)
                 .dry_run(dry_run)
-                .with_docs(!skip_docs)
-                .context(context)?
+                .preview(preview)
+                .no_build(no_build)
+                .to_dir(to_dir)
+                .with_docs(!skip_docs && defaults.with_docs().unwrap_or(true))
+                .context(context.or(default_context))?
+                .show_patterns(config.show_patterns())
+                .show_registry(show_registry(&config))
                 .show(show)
-                .sites(site)?
-                .platforms(platform)?
-                .flavors(flavor)?
+                .allow_unknown_show(allow_unknown_show)
+                .site_registry(config.site_registry()?)
+                .sites(site.or(defaults.sites()?))
+                .platforms(platform.or(defaults.platforms()?))?
+                .flavors(flavor.or(defaults.flavors()?))?
                 .build_dir(build_dir)
                 .verbose(verbose)
                 .dist_dir(dist_dir)
                 .level(level)?
                 .overrides(overrides)?
-                .defines(define)
+                .defines(define.or_else(|| defaults.defines()))
                 .work(work)
                 .vcs(vcs)?
                 .logfile(logfile)
-                .max_jobs(max_jobs)
+                .max_jobs(max_jobs.or_else(|| defaults.max_jobs()))
+                .journal(history_file(None, &config, env))
+                .hooks(config.hooks())
+                .policies(config.policies()?)
+                .override_policies(override_policy)
+                .authorization(config.authorization())
                 .package_root(package_root)
                 .build();
             install.doit()
@@ -325,15 +1069,28 @@ fn main() -> Result<(), AnyError> {
             define,
             flavor,
             platform,
+            profile,
             package_root,
         } => {
-            let mut docs = Docs::default()
+            let config = load_config(&package_root)?;
+            let platform = platforms_or_env(platform, env, &config.platform_registry()?)?;
+            let defaults = section_defaults(&config, Section::Docs, &profile)?;
+            report_profile(
+                &defaults,
+                verbose,
+                &[
+                    ("defines", define.is_some()),
+                    ("flavors", flavor.is_some()),
+                    ("platforms", platform.is_some()),
+                ],
+            );
+            let mut docs = Docs::with_platform_registry(config.platform_registry()?)
                 .dry_run(dry_run)
                 .dist_dir(dist_dir)
-                .defines(define)
+                .defines(define.or_else(|| defaults.defines()))
                 .verbose(verbose)
-                .flavors(flavor)?
-                .platforms(platform)?
+                .flavors(flavor.or(defaults.flavors()?))?
+                .platforms(platform.or(defaults.platforms()?))?
                 .package_root(package_root)
                 .build();
             docs.doit()
@@ -345,15 +1102,28 @@ fn main() -> Result<(), AnyError> {
             platform,
             flavor,
             define,
+            profile,
             package_root,
         } => {
-            let mut test = Test::default()
+            let config = load_config(&package_root)?;
+            let platform = platforms_or_env(platform, env, &config.platform_registry()?)?;
+            let defaults = section_defaults(&config, Section::Test, &profile)?;
+            report_profile(
+                &defaults,
+                verbose,
+                &[
+                    ("platforms", platform.is_some()),
+                    ("flavors", flavor.is_some()),
+                    ("defines", define.is_some()),
+                ],
+            );
+            let mut test = Test::with_platform_registry(config.platform_registry()?)
                 .dry_run(dry_run)
                 .dist_dir(dist_dir)
                 .verbose(verbose)
-                .platforms(platform)?
-                .flavors(flavor)?
-                .defines(define)
+                .platforms(platform.or(defaults.platforms()?))?
+                .flavors(flavor.or(defaults.flavors()?))?
+                .defines(define.or_else(|| defaults.defines()))
                 .package_root(package_root)
                 .build();
             test.doit()
@@ -361,37 +1131,306 @@ fn main() -> Result<(), AnyError> {
         Opt::Run {
             dry_run,
             verbose,
+            profile,
             package_root,
             platform,
             flavor,
             vars,
         } => {
-            let mut run = Run::default()
+            let config = load_config(&package_root)?;
+            let platform = platforms_or_env(platform, env, &config.platform_registry()?)?;
+            let defaults = section_defaults(&config, Section::Run, &profile)?;
+            report_profile(
+                &defaults,
+                verbose,
+                &[("platforms", platform.is_some()), ("flavors", flavor.is_some())],
+            );
+            let mut run = Run::with_platform_registry(config.platform_registry()?)
                 .dry_run(dry_run)
                 .verbose(verbose)
                 .package_root(package_root)
-                .platforms(platform)?
-                .flavors(flavor)?
+                .platforms(platform.or(defaults.platforms()?))?
+                .flavors(flavor.or(defaults.flavors()?))?
                 .vars(vars)
                 .build();
             run.doit()
         }
+        Opt::Promote {
+            from,
+            to,
+            dry_run,
+            verbose,
+            dist_dir,
+            allow_unknown_show,
+            site,
+            platform,
+            override_policy,
+            flavor,
+            max_jobs,
+            package_root,
+        } => {
+            let config = load_config(&package_root)?;
+            let site = sites_or_env(site, env, &config.site_registry()?)?;
+            let platform = platforms_or_env(platform, env, &config.platform_registry()?)?;
+            let mut promote = Promote::with_platform_registry(config.platform_registry()?)
+                .from(from)?
+                .to(to)?
+                .dry_run(dry_run)
+                .verbose(verbose)
+                .dist_dir(dist_dir)
+                .show_registry(show_registry(&config))
+                .allow_unknown_show(allow_unknown_show)
+                .site_registry(config.site_registry()?)
+                .sites(site)
+                .platforms(platform)?
+                .flavors(flavor)?
+                .max_jobs(max_jobs)
+                .journal(history_file(None, &config, env))
+                .hooks(config.hooks())
+                .policies(config.policies()?)
+                .override_policies(override_policy)
+                .authorization(config.authorization())
+                .package_root(package_root)
+                .build();
+            promote.doit()
+        }
+        Opt::Compat { target, vars } => {
+            let make_args = MakeArgs::translate(&target, &vars)?;
+            for var in &make_args.unsupported {
+                eprintln!("Warning: ignoring unsupported Makebridge variable {}", var);
+            }
+            if make_args.is_chatty() {
+                println!("Translated to: {}", make_args.args.join(" "));
+            }
+            execute(Opt::from_iter_safe(&make_args.args)?.with_env(env)?, env)
+        }
+        Opt::Config { cmd } => match cmd {
+            ConfigCmd::Show { package_root } => {
+                let config = load_config(&package_root)?;
+                if config.layers().is_empty() {
+                    println!("No config files found");
+                } else {
+                    config.tabulate();
+                }
+                Ok(())
+            }
+        },
+        Opt::Uninstall { remove } => uninstall(remove, None, env),
+        Opt::Rollback { to, remove } => uninstall(remove, Some(to), env),
+        Opt::History {
+            package,
+            since,
+            json,
+            file,
+            package_root,
+        } => {
+            let config = load_config(&package_root)?;
+            let path = history_file(file, &config, env).ok_or_else(|| {
+                anyhow::anyhow!("Unable to locate the history journal. Use --file to specify it")
+            })?;
+            let records = Journal::new(path).query(package.as_deref(), since.as_deref())?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&records)?);
+            } else if records.is_empty() {
+                println!("No installs recorded");
+            } else {
+                History { records }.tabulate();
+            }
+            Ok(())
+        }
+        Opt::Pipeline {
+            stages,
+            dry_run,
+            verbose,
+            clean,
+            skip_docs,
+            context,
+            show,
+            allow_unknown_show,
+            level,
+            site,
+            platform,
+            flavor,
+            define,
+            override_policy,
+            dist_dir,
+            profile,
+            package_root,
+        } => {
+            let stages = Stage::parse_list(&stages)?;
+            let config = load_config(&package_root)?;
+            let site = sites_or_env(site, env, &config.site_registry()?)?;
+            let platform = platforms_or_env(platform, env, &config.platform_registry()?)?;
+            // each stage applies its own section's defaults to the shared options
+            let build_defaults = section_defaults(&config, Section::Build, &profile)?;
+            let test_defaults = section_defaults(&config, Section::Test, &profile)?;
+            let install_defaults = section_defaults(&config, Section::Install, &profile)?;
+            let build = Build::with_platform_registry(config.platform_registry()?)
+                .clean(clean)
+                .with_docs(!skip_docs && build_defaults.with_docs().unwrap_or(true))
+                .dry_run(dry_run)
+                .dist_dir(dist_dir.clone())
+                .flavors(flavor.clone().or(build_defaults.flavors()?))?
+                .platforms(platform.clone().or(build_defaults.platforms()?))?
+                .verbose(verbose)
+                .defines(define.clone().or_else(|| build_defaults.defines()))
+                .hooks(config.hooks())
+                .package_root(package_root.clone())
+                .build();
+            let test = Test::with_platform_registry(config.platform_registry()?)
+                .dry_run(dry_run)
+                .dist_dir(dist_dir.clone())
+                .verbose(verbose)
+                .platforms(platform.clone().or(test_defaults.platforms()?))?
+                .flavors(flavor.clone().or(test_defaults.flavors()?))?
+                .defines(define.clone().or_else(|| test_defaults.defines()))
+                .package_root(package_root.clone())
+                .build();
+            let default_context = if level.is_none() {
+                install_defaults.context()?
+            } else {
+                None
+            };
+            let install = Install::with_platform_registry(config.platform_registry()?)
+                .clean(clean)
+                .dry_run(dry_run)
+                .with_docs(!skip_docs && install_defaults.with_docs().unwrap_or(true))
+                .context(context.or(default_context))?
+                .show_patterns(config.show_patterns())
+                .show_registry(show_registry(&config))
+                .show(show)
+                .allow_unknown_show(allow_unknown_show)
+                .site_registry(config.site_registry()?)
+                .sites(site.or(install_defaults.sites()?))
+                .platforms(platform.or(install_defaults.platforms()?))?
+                .flavors(flavor.or(install_defaults.flavors()?))?
+                .verbose(verbose)
+                .dist_dir(dist_dir)
+                .level(level)?
+                .defines(define.or_else(|| install_defaults.defines()))
+                .max_jobs(install_defaults.max_jobs())
+                .journal(history_file(None, &config, env))
+                .hooks(config.hooks())
+                .policies(config.policies()?)
+                .override_policies(override_policy)
+                .authorization(config.authorization())
+                .package_root(package_root)
+                .build();
+            let mut pipeline = Pipeline::default()
+                .stages(stages)
+                .build_target(build)
+                .test_target(test)
+                .install_target(install)
+                .verbose(verbose)
+                .build();
+            pipeline.doit()
+        }
+        Opt::Clean {
+            build,
+            dist,
+            flavor,
+            stale,
+            dry_run,
+            verbose,
+            package_root,
+        } => {
+            let config = load_config(&package_root)?;
+            let mut clean = Clean::with_platform_registry(config.platform_registry()?)
+                .build_dir(build)
+                .dist_dir(dist)
+                .flavors(flavor)?
+                .stale(stale)
+                .dry_run(dry_run)
+                .verbose(verbose)
+                .package_root(package_root)
+                .build();
+            clean.doit()
+        }
+        Opt::Info {
+            json,
+            profile,
+            package_root,
+        } => {
+            let config = load_config(&package_root)?;
+            let build_env = BuildEnv::with_registries(
+                package_root.as_deref().unwrap_or_else(|| Path::new(".")),
+                &config.platform_registry()?,
+                &config.show_patterns(),
+            )?;
+            let info = PackageInfo::new(&build_env, &config, profile.as_deref(), env)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&info)?);
+            } else {
+                info.tabulate();
+            }
+            Ok(())
+        }
     }
 }
 
-/*
- * The following is a list of flags from Makebridge
- flag translation
- CONTEXT => --context
- LEVEL => --level
- SHOW => --show
- WITH_DOCS => --with-docs
- DRY_RUN => -n --dry-run
- FLAVOUR => --flavor --flavour
- SITES --sites (local|all|name)
- BUILD_DIR --build-dir
- PLATFORMS => --platforms
- VERBOSe => --verbose
- VCS => --vcs
- pk-make
-*/
+// the history journal: supplied explicitly, or via PK_MAKE_HISTORY_FILE, or
+// configured, or the per user default
+fn history_file(file: Option<PathBuf>, config: &Config, env: &EnvDefaults) -> Option<PathBuf> {
+    file.or_else(|| env.path("PK_MAKE_HISTORY_FILE"))
+        .or_else(|| config.history_file().map(Path::to_path_buf))
+        .or_else(Journal::default_path)
+}
+
+// uninstall, or roll back to an earlier version, from the levels, sites, platforms
+// and flavors that install would install to, applying the install defaults. A
+// rollback consults the history journal that install records to.
+fn uninstall(remove: RemoveOpts, to: Option<String>, env: &EnvDefaults) -> Result<(), AnyError> {
+    let RemoveOpts {
+        dry_run,
+        verbose,
+        yes,
+        context,
+        show,
+        allow_unknown_show,
+        level,
+        site,
+        platform,
+        flavor,
+        profile,
+        package_root,
+    } = remove;
+    let config = load_config(&package_root)?;
+    let site = sites_or_env(site, env, &config.site_registry()?)?;
+    let platform = platforms_or_env(platform, env, &config.platform_registry()?)?;
+    let defaults = section_defaults(&config, Section::Install, &profile)?;
+    report_profile(
+        &defaults,
+        verbose,
+        &[
+            ("context", context.is_some() || level.is_some()),
+            ("sites", site.is_some()),
+            ("platforms", platform.is_some()),
+            ("flavors", flavor.is_some()),
+        ],
+    );
+    // a configured context would conflict with an explicit level
+    let default_context = if level.is_none() {
+        defaults.context()?
+    } else {
+        None
+    };
+    let install = Install::with_platform_registry(config.platform_registry()?)
+        .dry_run(dry_run)
+        .verbose(verbose)
+        .context(context.or(default_context))?
+        .show_patterns(config.show_patterns())
+        .show_registry(show_registry(&config))
+        .show(show)
+        .allow_unknown_show(allow_unknown_show)
+        .level(level)?
+        .site_registry(config.site_registry()?)
+        .sites(site.or(defaults.sites()?))
+        .platforms(platform.or(defaults.platforms()?))?
+        .flavors(flavor.or(defaults.flavors()?))?
+        .authorization(config.authorization())
+        .journal(history_file(None, &config, env))
+        .package_root(package_root)
+        .build();
+    let mut uninstall = Uninstall::default().install(install).to(to).yes(yes).build();
+    uninstall.doit()
+}
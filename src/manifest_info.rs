@@ -5,6 +5,7 @@
 
 // Internal crate imports
 use crate::Flavor;
+use crate::Platform;
 
 // External ctate imports
 use serde::Deserialize;
@@ -19,6 +20,10 @@ pub struct ManifestInfo {
     pub name: String,
     pub version: String,
     pub flavors: Vec<Flavor>,
+    /// The operating systems (or platforms) the package supports. Empty if unrestricted.
+    pub os: Vec<String>,
+    pub description: Option<String>,
+    pub url: Option<String>,
 }
 
 impl ManifestInfo {
@@ -34,6 +39,22 @@ impl ManifestInfo {
     pub fn version(&self) -> &str {
         self.version.as_str()
     }
+    /// The id of the package built for a flavor, ie `name-version` for the vanilla
+    /// flavor, and `name-version_flavor` otherwise. Dist artifacts are named by id.
+    pub fn package_id(&self, flavor: &Flavor) -> String {
+        match flavor {
+            Flavor::Vanilla => format!("{}-{}", self.name, self.version),
+            _ => format!("{}-{}_{}", self.name, self.version, flavor.as_str()),
+        }
+    }
+    /// Whether the package supports the platform, per the manifest's os list. Entries
+    /// may name an os family (eg linux) or a platform.
+    pub fn supports(&self, platform: &Platform) -> bool {
+        self.os.is_empty()
+            || self.os.iter().any(|os| {
+                os.eq_ignore_ascii_case(platform.os().as_str()) || platform.matches(os)
+            })
+    }
     //
     // THIS IS AN ALTERNATE VERSION WHICH RELIES ON PK MANIFEST INSTEAD OF 
     // SERDE. 
@@ -86,6 +107,12 @@ pub struct Manifest {
     version: String,
     #[serde(alias = "Flavours")]
     flavours: Option<Vec<Flavour>>,
+    #[serde(alias = "Os")]
+    os: Option<Vec<String>>,
+    #[serde(alias = "Description")]
+    description: Option<String>,
+    #[serde(alias = "Url", alias = "URL")]
+    url: Option<String>,
 }
 
 impl Manifest {
@@ -113,6 +140,9 @@ impl Manifest {
             name: self.name.clone(),
             version: self.version,
             flavors,
+            os: self.os.unwrap_or_default(),
+            description: self.description,
+            url: self.url,
         })
     }
 }
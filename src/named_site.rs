@@ -1,37 +1,92 @@
 //! NamedSite
-//! Enumerates our locations and provides a simple api for conversion from / to &str
+//! Models our locations, which are loaded into a SiteRegistry, and provides a simple
+//! api for conversion from / to &str
+//!
+//! The registry starts out with our built in sites, and may be extended or modified via
+//! the `[[sites]]` tables in the config files:
+//!
+//! ```toml
+//! [[sites]]
+//! id = "london"
+//! name = "London"
+//! aliases = ["ldn"]
+//! groups = ["eu"]
+//!
+//! [site_groups]
+//! na = ["playa", "portland", "vancouver", "montreal"]
+//! ```
 
 // Internal crate imports
 use crate::PkMakeError;
 
 // External crate imports
+use serde::Deserialize;
+use std::cmp::Ordering;
 use std::convert::TryFrom;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::str::FromStr;
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
-/// List of Valid Sites, along with a placeholder for unknown sites. This is used
-/// by non-fallible constructor methods.
-pub enum NamedSite {
-    Hyderabad,
-    Playa,
-    Portland,
-    Montreal,
-    Vancouver,
+/// Site names which have special meaning, and may not be used as a site id
+const RESERVED: [&str; 2] = ["local", "all"];
+
+/// A single site entry from the SiteRegistry. Sites are identified by their id;
+/// two NamedSites with the same id are considered equal.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NamedSite {
+    id: String,
+    name: String,
+    #[serde(default)]
+    aliases: Vec<String>,
+    #[serde(default)]
+    groups: Vec<String>,
 }
 
 impl NamedSite {
-    /// Return a string literal representation of the current variant of the NamedSite instance
-    pub fn as_str(&self) -> &str {
-        match self {
-            Self::Hyderabad => "hyderabad",
-            Self::Playa => "playa",
-            Self::Portland => "portland",
-            Self::Montreal => "montreal",
-            Self::Vancouver => "vancouver",
+    /// New up a NamedSite given an id, a display name, a list of aliases and a list of groups
+    pub fn new<I, N>(id: I, name: N, aliases: Vec<String>, groups: Vec<String>) -> Self
+    where
+        I: Into<String>,
+        N: Into<String>,
+    {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            aliases,
+            groups,
         }
     }
 
+    /// Return the id of the site
+    pub fn as_str(&self) -> &str {
+        self.id.as_str()
+    }
+
+    /// Return the display name of the site
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// Return the alternate names which the site may be referred to by
+    pub fn aliases(&self) -> &[String] {
+        &self.aliases
+    }
+
+    /// Return the groups which the site belongs to
+    pub fn groups(&self) -> &[String] {
+        &self.groups
+    }
+
+    /// Test whether the input refers to this site via its id, name, or one of its aliases.
+    /// The comparison is case insensitive.
+    pub fn matches(&self, input: &str) -> bool {
+        let input = input.to_lowercase();
+        self.id.to_lowercase() == input
+            || self.name.to_lowercase() == input
+            || self.aliases.iter().any(|a| a.to_lowercase() == input)
+    }
+
     /// Overrides the auto generated trait impl of from to provide a
     /// fallible version
     pub fn from<I>(input: I) -> Result<Self, PkMakeError>
@@ -42,30 +97,45 @@ impl NamedSite {
     }
 }
 
+impl PartialEq for NamedSite {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for NamedSite {}
+
+impl Hash for NamedSite {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl PartialOrd for NamedSite {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for NamedSite {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.id.cmp(&other.id)
+    }
+}
+
 impl fmt::Display for NamedSite {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::Hyderabad => write!(f, "Hyderabad"),
-            Self::Playa => write!(f, "Playa Vista"),
-            Self::Portland => write!(f, "Portland"),
-            Self::Montreal => write!(f, "Montreal"),
-            Self::Vancouver => write!(f, "Vancouver"),
-        }
+        write!(f, "{}", self.name)
     }
 }
 
+/// Look up a site in the builtin SiteRegistry. Sites added by the config are
+/// looked up via SiteRegistry::parse.
 impl FromStr for NamedSite {
     type Err = PkMakeError;
 
     fn from_str(input: &str) -> Result<Self, Self::Err> {
-        match input.to_lowercase().as_str() {
-            "hyderabad" => Ok(Self::Hyderabad),
-            "playa" | "playa vista" | "playavista" => Ok(Self::Playa),
-            "portland" => Ok(Self::Portland),
-            "montreal" => Ok(Self::Montreal),
-            "vancouver" => Ok(Self::Vancouver),
-            _ => Err(PkMakeError::InvalidSite(input.to_string())),
-        }
+        SiteRegistry::builtin().parse(input)
     }
 }
 
@@ -77,6 +147,109 @@ impl TryFrom<&str> for NamedSite {
     }
 }
 
+/// The list of known sites.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SiteRegistry {
+    sites: Vec<NamedSite>,
+}
+
+impl SiteRegistry {
+    /// The registry of sites which are known without any configuration
+    pub fn builtin() -> Self {
+        let site = |id: &str, name: &str, aliases: &[&str]| {
+            NamedSite::new(
+                id,
+                name,
+                aliases.iter().map(|a| a.to_string()).collect(),
+                Vec::new(),
+            )
+        };
+        Self {
+            sites: vec![
+                site("hyderabad", "Hyderabad", &[]),
+                site("playa", "Playa Vista", &["playa vista", "playavista"]),
+                site("portland", "Portland", &[]),
+                site("montreal", "Montreal", &[]),
+                site("vancouver", "Vancouver", &[]),
+            ],
+        }
+    }
+
+    /// Add sites to the registry. A site whose id is already registered replaces the
+    /// existing entry.
+    pub fn extend(&mut self, sites: &[NamedSite]) -> Result<(), PkMakeError> {
+        for site in sites {
+            if site.id.is_empty()
+                || RESERVED.contains(&site.id.to_lowercase().as_str())
+                || !site.id.chars().all(|c| c.is_alphanumeric() || c == '_')
+            {
+                return Err(PkMakeError::InvalidSite(site.id.clone()));
+            }
+            match self.sites.iter_mut().find(|s| *s == site) {
+                Some(existing) => *existing = site.clone(),
+                None => self.sites.push(site.clone()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a named group of sites to the registry. Each member must already be registered.
+    pub fn add_group(&mut self, group: &str, members: &[String]) -> Result<(), PkMakeError> {
+        if group.is_empty() || !group.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(PkMakeError::InvalidSiteGroup(group.to_string()));
+        }
+        for member in members {
+            let site = self
+                .sites
+                .iter_mut()
+                .find(|s| s.matches(member))
+                .ok_or_else(|| PkMakeError::InvalidSite(member.to_string()))?;
+            if !site.groups.iter().any(|g| g == group) {
+                site.groups.push(group.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Retrieve the sites belonging to a group, or None if the group has no members.
+    pub fn group(&self, group: &str) -> Option<Vec<&NamedSite>> {
+        let members = self
+            .sites
+            .iter()
+            .filter(|s| s.groups.iter().any(|g| g.eq_ignore_ascii_case(group)))
+            .collect::<Vec<_>>();
+        if members.is_empty() {
+            None
+        } else {
+            Some(members)
+        }
+    }
+
+    /// Retrieve a site by id, name or alias.
+    pub fn get(&self, input: &str) -> Option<&NamedSite> {
+        self.sites.iter().find(|s| s.matches(input))
+    }
+
+    /// Convert a site id, name or alias into a NamedSite, erroring if it is not
+    /// registered.
+    pub fn parse(&self, input: &str) -> Result<NamedSite, PkMakeError> {
+        self.get(input)
+            .cloned()
+            .ok_or_else(|| PkMakeError::InvalidSite(input.to_string()))
+    }
+
+    /// Retrieve all of the registered sites
+    pub fn sites(&self) -> &[NamedSite] {
+        &self.sites
+    }
+}
+
+impl Default for SiteRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
 #[cfg(test)]
 #[path = "./unit_tests/named_site_test.rs"]
 mod named_site_test;
@@ -1,48 +1,137 @@
 //!
 //! Platform models the valid platforms that a user may request be built
 //!
+//! Platforms are looked up in a PlatformRegistry, which starts out with our
+//! builtin platforms and may be extended via `[[platforms]]` tables in the config
+//! files:
+//!
+//! ```toml
+//! [[platforms]]
+//! name = "rocky9_64"
+//! aliases = ["rocky9"]
+//! os = "linux"
+//! os_release = ["rocky-9"]
+//! ```
+//!
+//! The `os_release` entries are used to identify the host platform from
+//! `/etc/os-release` when `DD_OS` is not set. Each is either `<ID>-<major VERSION_ID>`
+//! or simply `<ID>`.
+//!
 
 // Internal crate imports
 use crate::PkMakeError;
 
 
 // External crate imports
+use serde::Deserialize;
 use std::convert::TryFrom;
+use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::str::FromStr;
 
-
- /// The set of platforms that are active.
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
-pub enum Platform {
-    /// As of this writing, we sstil hvae some legacy windows 
-    Win7_64,
-    /// This is our current windows platform
-    Win10_64,
-    /// We have osx defined in our platforms config. However, we 
-    /// currently do not have osx build machines. 
-    Osx10_64,
-    /// Our former mainstay, and still actively built
-    Cent6_64,
-    /// Our current primary platform
-    Cent7_64,
-    /// The next primary linux platform; not currently used, but should be comming up.
-    Cent8_64,
+/// The operating system family a platform belongs to
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OsFamily {
+    Linux,
+    Windows,
+    Osx,
 }
 
-impl Platform {
-    /// Return a string literal representing the enum.
-    // we could use an external dependency to generate this via macro...
+impl OsFamily {
+    /// Return a string literal representing the OsFamily
     pub fn as_str(&self) -> &str {
         match self {
-            Self::Win7_64 => "win7_64",
-            Self::Win10_64 => "win10_64",
-            Self::Osx10_64 => "osx10_64",
-            Self::Cent6_64 => "cent6_64",
-            Self::Cent7_64 => "cent7_64",
-            Self::Cent8_64 => "cent8_64",
+            Self::Linux => "linux",
+            Self::Windows => "windows",
+            Self::Osx => "osx",
+        }
+    }
+}
+
+impl fmt::Display for OsFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A platform from the PlatformRegistry. Platforms are identified by name; two
+/// Platforms with the same name are considered equal.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Platform {
+    name: String,
+    #[serde(default)]
+    aliases: Vec<String>,
+    os: OsFamily,
+    /// Whether we have build machines for the platform
+    #[serde(default = "default_buildable")]
+    buildable: bool,
+    /// os-release identifiers which map to this platform
+    #[serde(default)]
+    os_release: Vec<String>,
+}
+
+// serde default for Platform::buildable
+fn default_buildable() -> bool {
+    true
+}
+
+impl Platform {
+    /// New up a Platform given a name, a list of aliases, the os family, and
+    /// whether it is buildable.
+    pub fn new<I>(name: I, aliases: Vec<String>, os: OsFamily, buildable: bool) -> Self
+    where
+        I: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            aliases,
+            os,
+            buildable,
+            os_release: Vec::new(),
         }
     }
 
+    /// Set the os-release identifiers (eg `centos-7`) which map to the platform,
+    /// per the builder pattern.
+    pub fn with_os_release(mut self, os_release: Vec<String>) -> Self {
+        self.os_release = os_release;
+        self
+    }
+
+    /// Return the name of the platform
+    pub fn as_str(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// Return the alternate names which the platform may be referred to by
+    pub fn aliases(&self) -> &[String] {
+        &self.aliases
+    }
+
+    /// Return the operating system family of the platform
+    pub fn os(&self) -> OsFamily {
+        self.os
+    }
+
+    /// Return the os-release identifiers which map to the platform
+    pub fn os_release(&self) -> &[String] {
+        &self.os_release
+    }
+
+    /// Whether we have build machines for the platform at all
+    pub fn is_buildable(&self) -> bool {
+        self.buildable
+    }
+
+    /// Test whether the input refers to this platform via its name or one of its
+    /// aliases. The comparison is case insensitive.
+    pub fn matches(&self, input: &str) -> bool {
+        let input = input.to_lowercase();
+        self.name.to_lowercase() == input || self.aliases.iter().any(|a| a.to_lowercase() == input)
+    }
+
     /// Overrides the auto generated trait impl of from to provide a
     /// fallible version
     pub fn from<I>(input: I) -> Result<Self, PkMakeError>
@@ -52,21 +141,35 @@ impl Platform {
         Self::try_from(input.as_ref())
     }
 }
-/// Convert a str to a Platform. It is case insensitive (at the small cost of a string allocation)
-/// and accepts names which drop the _64, assuming that we are 64 bit.
+
+impl PartialEq for Platform {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Eq for Platform {}
+
+impl Hash for Platform {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// Convert a str to a Platform by looking it up in the builtin PlatformRegistry. It
+/// is case insensitive (at the small cost of a string allocation). Platforms added
+/// by the config are looked up via PlatformRegistry::parse.
 impl FromStr for Platform {
     type Err = PkMakeError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
-            "win7_64" | "win7" => Ok(Self::Win7_64),
-            "win10_64" | "win10" => Ok(Self::Win10_64),
-            "osx10_64" | "osx10" => Ok(Self::Osx10_64),
-            "cent6_64" | "cent6" => Ok(Self::Cent6_64),
-            "cent7_64" | "cent7" => Ok(Self::Cent7_64),
-            "cent8_64" | "cent8" => Ok(Self::Cent8_64),
-            _ => Err(PkMakeError::InvalidPlatform(s.to_string())),
-        }
+        PlatformRegistry::builtin().parse(s)
     }
 }
 
@@ -79,6 +182,137 @@ impl TryFrom<&str> for Platform {
         Self::from_str(input).map_err(|_e| PkMakeError::InvalidPlatform(input.to_string()))
     }
 }
+
+/// Fallible conversion from a String. Platforms are accepted as Strings from the command
+/// line, since the PlatformRegistry is not known until the config has been loaded.
+impl TryFrom<String> for Platform {
+    type Error = PkMakeError;
+
+    fn try_from(input: String) -> Result<Self, Self::Error> {
+        Self::try_from(input.as_str())
+    }
+}
+
+/// The list of known platforms.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PlatformRegistry {
+    platforms: Vec<Platform>,
+}
+
+impl PlatformRegistry {
+    /// The registry of platforms which are known without any configuration. Each
+    /// platform may be referred to without the trailing _64, as we assume 64 bit.
+    pub fn builtin() -> Self {
+        let platform = |name: &str, alias: &str, os: OsFamily, buildable: bool| {
+            Platform::new(name, vec![alias.to_string()], os, buildable)
+        };
+        let os_release = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect();
+        Self {
+            platforms: vec![
+                // As of this writing, we still have some legacy windows
+                platform("win7_64", "win7", OsFamily::Windows, true),
+                // This is our current windows platform
+                platform("win10_64", "win10", OsFamily::Windows, true),
+                // We have osx defined in our platforms config. However, we
+                // currently do not have osx build machines.
+                platform("osx10_64", "osx10", OsFamily::Osx, false),
+                // Our former mainstay, and still actively built
+                platform("cent6_64", "cent6", OsFamily::Linux, true)
+                    .with_os_release(os_release(&["centos-6", "rhel-6"])),
+                // Our current primary platform
+                platform("cent7_64", "cent7", OsFamily::Linux, true)
+                    .with_os_release(os_release(&["centos-7", "rhel-7"])),
+                // The next primary linux platform; not currently used, but should be
+                // coming up.
+                platform("cent8_64", "cent8", OsFamily::Linux, false).with_os_release(
+                    os_release(&["centos-8", "rhel-8", "rocky-8", "almalinux-8"]),
+                ),
+            ],
+        }
+    }
+
+    /// Add platforms to the registry. A platform whose name is already registered
+    /// replaces the existing entry.
+    pub fn extend(&mut self, platforms: &[Platform]) -> Result<(), PkMakeError> {
+        for platform in platforms {
+            if platform.name.is_empty()
+                || !platform.name.chars().all(|c| c.is_alphanumeric() || c == '_')
+            {
+                return Err(PkMakeError::InvalidPlatform(platform.name.clone()));
+            }
+            match self.platforms.iter_mut().find(|p| *p == platform) {
+                Some(existing) => *existing = platform.clone(),
+                None => self.platforms.push(platform.clone()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Retrieve a platform by name or alias
+    pub fn get(&self, input: &str) -> Option<&Platform> {
+        self.platforms.iter().find(|p| p.matches(input))
+    }
+
+    /// Convert a platform name or alias into a Platform, erroring if it is not
+    /// registered.
+    pub fn parse(&self, input: &str) -> Result<Platform, PkMakeError> {
+        self.get(input)
+            .cloned()
+            .ok_or_else(|| PkMakeError::InvalidPlatform(input.to_string()))
+    }
+
+    /// Identify the platform corresponding to the contents of an os-release file. The
+    /// `<ID>-<major VERSION_ID>` identifier is preferred over a bare `<ID>`.
+    pub fn match_os_release(&self, contents: &str) -> Option<&Platform> {
+        let mut id = None;
+        let mut version_id = None;
+        for line in contents.lines() {
+            let mut pieces = line.splitn(2, '=');
+            let key = pieces.next().unwrap_or("").trim();
+            let value = pieces.next().unwrap_or("").trim().trim_matches('"');
+            match key {
+                "ID" => id = Some(value.to_lowercase()),
+                "VERSION_ID" => version_id = Some(value),
+                _ => (),
+            }
+        }
+        let id = id?;
+        let mut candidates = Vec::new();
+        if let Some(major) = version_id.and_then(|v| v.split('.').next()) {
+            candidates.push(format!("{}-{}", id, major));
+        }
+        candidates.push(id);
+        candidates.iter().find_map(|candidate| {
+            self.platforms
+                .iter()
+                .find(|p| p.os_release.iter().any(|r| r.eq_ignore_ascii_case(candidate)))
+        })
+    }
+
+    /// Retrieve all of the registered platforms
+    pub fn platforms(&self) -> &[Platform] {
+        &self.platforms
+    }
+}
+
+/// Verify that we have build machines for each of the supplied platforms, erroring
+/// with the first for which we do not.
+pub fn verify_buildable<'a, I>(platforms: I) -> Result<(), PkMakeError>
+where
+    I: IntoIterator<Item = &'a Platform>,
+{
+    match platforms.into_iter().find(|p| !p.is_buildable()) {
+        Some(platform) => Err(PkMakeError::UnbuildablePlatform(platform.as_str().to_string())),
+        None => Ok(()),
+    }
+}
+
+impl Default for PlatformRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
 #[cfg(test)]
 #[path = "./unit_tests/platform_test.rs"]
 mod platform_test;
@@ -0,0 +1,250 @@
+//! Policy
+//!
+//! Rules, configured in the `[[policies]]` array of any config layer, which an
+//! install must satisfy before any commands are built. Each rule applies when all of
+//! its `when` conditions match the install, and then enforces its `require`ments:
+//!
+//! ```toml
+//! [[policies]]
+//! id = "shared-cent7"
+//! description = "Shared installs must include cent7_64"
+//! when = { context = "shared" }
+//! require = { platforms = ["cent7_64"] }
+//! overridable = true
+//!
+//! [[policies]]
+//! id = "all-sites-clean"
+//! when = { sites = ["all"] }
+//! require = { clean_vcs = true }
+//!
+//! [[policies]]
+//! id = "facility-tested"
+//! when = { context = "facility" }
+//! require = { tests_pass = true }
+//!
+//! [[policies]]
+//! id = "no-work-at-show"
+//! when = { context = "shared", work = true }
+//! require = { deny = true }
+//! ```
+//!
+//! Conditions: `context` (facility | shared | user), `sites` (matches if any of the
+//! install's sites is listed, where a request for every site, or for all but some,
+//! counts as `all`) and `work` (the `--work` flag).
+//!
+//! Requirements: `platforms` (each must be installed), `clean_vcs` (no uncommitted
+//! changes), `tests_pass` (the test target succeeds first, which is only checked
+//! when the install is executed) and `deny` (the install is not permitted at all).
+//!
+//! Every violation is reported together, by rule id. A violated rule may be waived
+//! with `--override-policy <id>`, but only if it is `overridable`. Ids are unique
+//! across the layers: a layer may add rules, but may not redefine a rule from
+//! another layer (eg the package config may not relax a system rule by making it
+//! overridable, or by emptying its requirements).
+
+// Internal crate imports
+use crate::context::Context;
+use crate::site::Site;
+use crate::PkMakeError;
+
+// External crate imports
+use anyhow::Error as AnyError;
+use serde::Deserialize;
+
+/// A single policy rule
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    pub id: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub when: PolicyCondition,
+    #[serde(default)]
+    pub require: PolicyRequirement,
+    /// Whether the rule may be waived via `--override-policy`
+    #[serde(default)]
+    pub overridable: bool,
+}
+
+/// The conditions under which a policy applies. Every condition which is set must
+/// match.
+#[derive(Debug, PartialEq, Eq, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PolicyCondition {
+    pub context: Option<String>,
+    pub sites: Vec<String>,
+    pub work: Option<bool>,
+}
+
+/// What a policy requires of the installs it applies to
+#[derive(Debug, PartialEq, Eq, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PolicyRequirement {
+    pub platforms: Vec<String>,
+    pub clean_vcs: bool,
+    pub tests_pass: bool,
+    pub deny: bool,
+}
+
+/// The resolved install which policies are evaluated against
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PolicySubject {
+    pub context: Context,
+    pub sites: Vec<Site>,
+    pub platforms: Vec<String>,
+    pub work: bool,
+}
+
+/// The checks which policies may require that are expensive, or have side
+/// effects, and are therefore only performed when a matching policy requires them.
+pub trait PolicyProbe {
+    /// Whether the package has no uncommitted changes
+    fn vcs_clean(&mut self) -> Result<bool, AnyError>;
+    /// Whether the package's tests pass
+    fn tests_pass(&mut self) -> Result<bool, AnyError>;
+}
+
+/// A policy which an install does not satisfy
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Violation {
+    pub id: String,
+    pub reason: String,
+    pub overridable: bool,
+}
+
+impl Policy {
+    /// Whether the policy applies to the subject
+    pub fn applies_to(&self, subject: &PolicySubject) -> Result<bool, PkMakeError> {
+        let when = &self.when;
+        if let Some(ref context) = when.context {
+            if Context::from(context)? != subject.context {
+                return Ok(false);
+            }
+        }
+        if !when.sites.is_empty()
+            && !subject
+                .sites
+                .iter()
+                .any(|s| when.sites.iter().any(|w| s.matches(w)))
+        {
+            return Ok(false);
+        }
+        if let Some(work) = when.work {
+            if work != subject.work {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// The reasons the subject fails the policy's requirements, if it applies
+    pub fn check(
+        &self,
+        subject: &PolicySubject,
+        probe: &mut dyn PolicyProbe,
+    ) -> Result<Vec<String>, AnyError> {
+        if !self.applies_to(subject)? {
+            return Ok(Vec::new());
+        }
+        let require = &self.require;
+        let mut reasons = Vec::new();
+        if require.deny {
+            reasons.push(
+                self.description
+                    .clone()
+                    .unwrap_or_else(|| "not permitted".to_string()),
+            );
+        }
+        let missing = require
+            .platforms
+            .iter()
+            .filter(|p| !subject.platforms.contains(p))
+            .map(|p| p.as_str())
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            reasons.push(format!("missing required platforms: {}", missing.join(", ")));
+        }
+        if require.clean_vcs && !probe.vcs_clean()? {
+            reasons.push("the package has uncommitted changes".to_string());
+        }
+        if require.tests_pass && !probe.tests_pass()? {
+            reasons.push("the package's tests did not pass".to_string());
+        }
+        Ok(reasons)
+    }
+}
+
+/// Evaluate every policy against the subject, waiving violations of the overridable
+/// policies named in overrides. All remaining violations are reported together.
+pub fn enforce(
+    policies: &[Policy],
+    subject: &PolicySubject,
+    probe: &mut dyn PolicyProbe,
+    overrides: &[String],
+) -> Result<Vec<Violation>, AnyError> {
+    for id in overrides {
+        if !policies.iter().any(|p| &p.id == id) {
+            return Err(PkMakeError::UnknownPolicy(id.clone()).into());
+        }
+    }
+    let mut waived = Vec::new();
+    let mut violations = Vec::new();
+    for policy in policies {
+        for reason in policy.check(subject, probe)? {
+            let violation = Violation {
+                id: policy.id.clone(),
+                reason,
+                overridable: policy.overridable,
+            };
+            if policy.overridable && overrides.contains(&policy.id) {
+                waived.push(violation);
+            } else {
+                violations.push(violation);
+            }
+        }
+    }
+    if violations.is_empty() {
+        return Ok(waived);
+    }
+    let report = violations
+        .iter()
+        .map(|v| {
+            let note = match (v.overridable, overrides.contains(&v.id)) {
+                (false, true) => " (this policy may not be overridden)",
+                (true, false) => " (may be overridden with --override-policy)",
+                _ => "",
+            };
+            format!("[{}] {}{}", v.id, v.reason, note)
+        })
+        .collect::<Vec<_>>()
+        .join("\n  ");
+    Err(PkMakeError::PolicyViolations(report).into())
+}
+
+/// Merge the policies of config layers, listed in order of increasing precedence
+/// along with a description of each layer. A policy whose id is already defined,
+/// by the same or another layer, is an error.
+pub fn merge<'a, I>(layers: I) -> Result<Vec<Policy>, PkMakeError>
+where
+    I: IntoIterator<Item = (String, &'a [Policy])>,
+{
+    let mut merged: Vec<(Policy, String)> = Vec::new();
+    for (layer, policies) in layers {
+        for policy in policies {
+            if let Some((_, original)) = merged.iter().find(|(p, _)| p.id == policy.id) {
+                return Err(PkMakeError::PolicyRedefined {
+                    id: policy.id.clone(),
+                    layer,
+                    original: original.clone(),
+                });
+            }
+            merged.push((policy.clone(), layer.clone()));
+        }
+    }
+    Ok(merged.into_iter().map(|(policy, _)| policy).collect())
+}
+
+#[cfg(test)]
+#[path = "./unit_tests/policy_test.rs"]
+mod policy_test;
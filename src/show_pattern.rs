@@ -0,0 +1,138 @@
+//! ShowPattern
+//!
+//! Path patterns used to infer the show from the current working directory when
+//! DD_SHOW is not set. A pattern is an absolute path made up of:
+//!
+//! - `{show}` - matches a single component, which is taken to be the show
+//! - `*` - matches any single component
+//! - `...` - as the final component, matches any number of trailing components
+//! - anything else - matches the component literally
+//!
+//! The builtin pattern is `/jobs/{show}/...`. Additional patterns may be supplied
+//! via `show_patterns` in the config files, and are tried before the builtin:
+//!
+//! ```toml
+//! show_patterns = ["/mnt/projects/*/{show}/..."]
+//! ```
+
+// Internal crate imports
+use crate::PkMakeError;
+
+// External crate imports
+use serde::Deserialize;
+use std::convert::TryFrom;
+use std::fmt;
+use std::path::{Component, Path};
+use std::str::FromStr;
+
+/// The pattern used when none have been configured
+const BUILTIN: &str = "/jobs/{show}/...";
+const SHOW: &str = "{show}";
+const ANY: &str = "*";
+const REST: &str = "...";
+
+/// A validated pattern identifying the show component of a path
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ShowPattern {
+    pattern: String,
+}
+
+impl ShowPattern {
+    /// Return the pattern as supplied
+    pub fn as_str(&self) -> &str {
+        self.pattern.as_str()
+    }
+
+    /// Extract the show from a path, if the path matches the pattern
+    pub fn show_from(&self, path: &Path) -> Option<String> {
+        let mut components = path.components().filter_map(|c| match c {
+            Component::Normal(c) => c.to_str(),
+            _ => None,
+        });
+        let mut show = None;
+        for piece in self.pieces() {
+            if piece == REST {
+                return show;
+            }
+            let component = components.next()?;
+            match piece {
+                SHOW => show = Some(component.to_string()),
+                ANY => (),
+                literal if literal == component => (),
+                _ => return None,
+            }
+        }
+        if components.next().is_some() {
+            None
+        } else {
+            show
+        }
+    }
+
+    /// Overrides the auto generated trait impl of from to provide a
+    /// fallible version
+    pub fn from<I>(input: I) -> Result<Self, PkMakeError>
+    where
+        I: AsRef<str>,
+    {
+        Self::try_from(input.as_ref())
+    }
+
+    /// The builtin patterns
+    pub fn builtin() -> Vec<ShowPattern> {
+        vec![Self {
+            pattern: BUILTIN.to_string(),
+        }]
+    }
+
+    // the components of the pattern below the root
+    fn pieces(&self) -> impl Iterator<Item = &str> {
+        self.pattern.split('/').filter(|p| !p.is_empty())
+    }
+}
+
+impl fmt::Display for ShowPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.pattern)
+    }
+}
+
+/// Patterns must be absolute, contain `{show}` exactly once, and may only use `...`
+/// as the final component.
+impl FromStr for ShowPattern {
+    type Err = PkMakeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pattern = Self {
+            pattern: s.to_string(),
+        };
+        let pieces = pattern.pieces().collect::<Vec<_>>();
+        let shows = pieces.iter().filter(|p| **p == SHOW).count();
+        let rest_ok = !pieces.iter().rev().skip(1).any(|p| *p == REST);
+        if !s.starts_with('/') || shows != 1 || !rest_ok {
+            return Err(PkMakeError::InvalidShowPattern(s.to_string()));
+        }
+        Ok(pattern)
+    }
+}
+
+impl TryFrom<&str> for ShowPattern {
+    type Error = PkMakeError;
+
+    fn try_from(input: &str) -> Result<Self, Self::Error> {
+        Self::from_str(input)
+    }
+}
+
+impl TryFrom<String> for ShowPattern {
+    type Error = PkMakeError;
+
+    fn try_from(input: String) -> Result<Self, Self::Error> {
+        Self::from_str(&input)
+    }
+}
+
+#[cfg(test)]
+#[path = "./unit_tests/show_pattern_test.rs"]
+mod show_pattern_test;
@@ -0,0 +1,128 @@
+//! ShowRegistry
+//!
+//! The list of known shows, used to catch typos in `--show` and `DD_SHOW` before
+//! anything is installed. Shows may be listed explicitly in the config files, or
+//! discovered by scanning the directories under a shows root:
+//!
+//! ```toml
+//! shows = ["dev01", "dev02"]
+//! shows_root = "/jobs"
+//! ```
+//!
+//! When neither is configured there is no registry, and shows are not validated.
+
+// Internal crate imports
+use crate::PkMakeError;
+
+// External crate imports
+use anyhow::anyhow;
+use anyhow::Error as AnyError;
+use std::path::Path;
+
+/// The maximum edit distance for a known show to be suggested
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+/// The list of known shows
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct ShowRegistry {
+    shows: Vec<String>,
+}
+
+impl ShowRegistry {
+    /// New up a ShowRegistry from a list of shows
+    pub fn new<I, S>(shows: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut registry = Self::default();
+        registry.extend(shows);
+        registry
+    }
+
+    /// Add shows to the registry, ignoring duplicates
+    pub fn extend<I, S>(&mut self, shows: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        for show in shows {
+            let show = show.into();
+            if !self.contains(&show) {
+                self.shows.push(show);
+            }
+        }
+    }
+
+    /// Add each of the (non hidden) directories under root to the registry
+    pub fn scan(&mut self, root: &Path) -> Result<(), AnyError> {
+        let entries = std::fs::read_dir(root)
+            .map_err(|e| anyhow!("Unable to scan shows root '{}': {}", root.display(), e))?;
+        let mut shows = Vec::new();
+        for entry in entries.filter_map(Result::ok) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.starts_with('.') && entry.path().is_dir() {
+                shows.push(name);
+            }
+        }
+        shows.sort();
+        self.extend(shows);
+        Ok(())
+    }
+
+    /// Whether the show is known. The comparison is case insensitive.
+    pub fn contains(&self, show: &str) -> bool {
+        self.shows.iter().any(|s| s.eq_ignore_ascii_case(show))
+    }
+
+    /// Verify that the show is known, suggesting close matches if it is not
+    pub fn verify(&self, show: &str) -> Result<(), PkMakeError> {
+        if self.contains(show) {
+            return Ok(());
+        }
+        Err(PkMakeError::UnknownShow {
+            show: show.to_string(),
+            suggestions: match self.suggestions(show).as_slice() {
+                [] => String::new(),
+                suggestions => format!(" Did you mean {}?", suggestions.join(", ")),
+            },
+        })
+    }
+
+    /// Known shows within a small edit distance of the supplied show, closest first
+    pub fn suggestions(&self, show: &str) -> Vec<&str> {
+        let show = show.to_lowercase();
+        let mut candidates = self
+            .shows
+            .iter()
+            .map(|s| (edit_distance(&show, &s.to_lowercase()), s.as_str()))
+            .filter(|(distance, _)| *distance <= MAX_SUGGESTION_DISTANCE)
+            .collect::<Vec<_>>();
+        candidates.sort();
+        candidates.into_iter().map(|(_, s)| s).collect()
+    }
+
+    /// Retrieve all of the known shows
+    pub fn shows(&self) -> &[String] {
+        &self.shows
+    }
+}
+
+// levenshtein distance between two strs
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == *cb { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+#[path = "./unit_tests/show_registry_test.rs"]
+mod show_registry_test;
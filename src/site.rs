@@ -1,20 +1,31 @@
-//! Site models the valid state values that a user may provide to the 
+//! Site models the valid state values that a user may provide to the
 //! command
+//!
+//! In addition to `local`, `all`, and the sites in the SiteRegistry, a user
+//! may request a group of sites via `@<group>`, and exclude a site or group
+//! via `!<site>` or `!@<group>`. Groups and exclusions are expanded into
+//! concrete sites via `Site::expand`.
 
 // Internal crate imports
-use crate::named_site::NamedSite;
+use crate::named_site::{NamedSite, SiteRegistry};
 use crate::PkMakeError;
 
 // External crate imports
+use indexmap::IndexSet as HashSet;
 use std::convert::TryFrom;
+use std::fmt;
 use std::str::FromStr;
 
 /// Valid Site input variants supplied by the user.
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
 pub enum Site {
     Local,
     All,
     Named(NamedSite),
+    /// A group of sites from the SiteRegistry, requested as `@<group>`
+    Group(String),
+    /// A site or group to remove from the request, requested as `!<site>`
+    Exclude(Box<Site>),
 }
 
 impl Site {
@@ -24,6 +35,8 @@ impl Site {
             Self::Local => "local",
             Self::All => "all",
             Self::Named(named_site) => named_site.as_str(),
+            Self::Group(group) => group.as_str(),
+            Self::Exclude(site) => site.as_str(),
         }
     }
 
@@ -35,28 +48,165 @@ impl Site {
     {
         Self::try_from(input.as_ref())
     }
-}
-
-/// Falible conversion from a string
-impl FromStr for Site {
-    type Err = PkMakeError;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+    /// Convert a str into a Site, looking up named sites and groups in the supplied
+    /// registry.
+    pub fn parse(s: &str, registry: &SiteRegistry) -> Result<Self, PkMakeError> {
+        if let Some(excluded) = s.strip_prefix('!') {
+            return match Site::parse(excluded, registry)? {
+                site @ Site::Named(_) | site @ Site::Group(_) => {
+                    Ok(Site::Exclude(Box::new(site)))
+                }
+                _ => Err(PkMakeError::InvalidSite(s.to_string())),
+            };
+        }
+        if let Some(group) = s.strip_prefix('@') {
+            return match registry.group(group) {
+                Some(_) => Ok(Site::Group(group.to_lowercase())),
+                None => Err(PkMakeError::InvalidSiteGroup(group.to_string())),
+            };
+        }
         match s.to_lowercase().as_str() {
             "local" => Ok(Site::Local),
             "all" => Ok(Site::All),
-            _ => {
-               
-                match NamedSite::from_str(s) {
-                    Ok(site) => Ok(Site::Named(site)),
-                    Err(_) => Err(PkMakeError::InvalidSite(s.to_string())),
+            _ => match registry.parse(s) {
+                Ok(site) => Ok(Site::Named(site)),
+                Err(_) => Err(PkMakeError::InvalidSite(s.to_string())),
+            },
+        }
+    }
+
+    /// Test whether the input, as it would appear in a config file, refers to this
+    /// Site. Named sites match via their id, name or aliases, and groups as
+    /// `@<group>`. The comparison is case insensitive.
+    pub fn matches(&self, input: &str) -> bool {
+        match self {
+            Self::Named(named_site) => named_site.matches(input),
+            Self::Exclude(_) => false,
+            site => site.to_string().eq_ignore_ascii_case(input),
+        }
+    }
+
+    /// Expand groups and exclusions into concrete sites, using the supplied registry.
+    /// A request for `all` is left as is unless something is excluded from it. A
+    /// request consisting solely of exclusions is applied to all of the sites.
+    pub fn expand<I>(sites: I, registry: &SiteRegistry) -> Result<HashSet<Site>, PkMakeError>
+    where
+        I: IntoIterator<Item = Site>,
+    {
+        let mut requested = HashSet::new();
+        let mut excluded = HashSet::new();
+        for site in sites {
+            match site {
+                Site::Exclude(site) => excluded.insert(*site),
+                site => requested.insert(site),
+            };
+        }
+        if excluded.is_empty() && !requested.iter().any(|s| matches!(s, Site::Group(_))) {
+            return Ok(requested);
+        }
+        if requested.is_empty() {
+            requested.insert(Site::All);
+        }
+        let excluded = Self::concrete(excluded, registry)?;
+        Ok(Self::concrete(requested, registry)?
+            .into_iter()
+            .filter(|s| !excluded.contains(s))
+            .collect())
+    }
+
+    /// The sites which rules are matched against: the expanded sites, along with
+    /// the groups and `all` as requested. A request of exclusions alone counts as
+    /// `all`, as does an expansion which covers every site in the registry, so that
+    /// a rule for `all` cannot be dodged by excluding a site.
+    pub fn as_requested<I>(
+        requested: I,
+        expanded: &HashSet<Site>,
+        registry: &SiteRegistry,
+    ) -> Vec<Site>
+    where
+        I: IntoIterator<Item = Site>,
+    {
+        let mut result = expanded.iter().cloned().collect::<Vec<_>>();
+        let mut only_exclusions = true;
+        let mut any_exclusions = false;
+        for site in requested {
+            match site {
+                Site::Exclude(_) => any_exclusions = true,
+                site => {
+                    only_exclusions = false;
+                    if !result.contains(&site) {
+                        result.push(site);
+                    }
+                }
+            }
+        }
+        let covers_registry = !registry.sites().is_empty()
+            && registry
+                .sites()
+                .iter()
+                .all(|named| expanded.contains(&Site::Named(named.clone())));
+        let all = (any_exclusions && only_exclusions) || covers_registry;
+        if all && !result.contains(&Site::All) {
+            result.push(Site::All);
+        }
+        result
+    }
+
+    // replace All and groups with the sites they refer to
+    fn concrete(
+        sites: HashSet<Site>,
+        registry: &SiteRegistry,
+    ) -> Result<HashSet<Site>, PkMakeError> {
+        let mut result = HashSet::new();
+        for site in sites {
+            match site {
+                Site::All => {
+                    for named in registry.sites() {
+                        result.insert(Site::Named(named.clone()));
+                    }
+                }
+                Site::Group(group) => {
+                    let members = registry
+                        .group(&group)
+                        .ok_or_else(|| PkMakeError::InvalidSiteGroup(group.clone()))?;
+                    for named in members {
+                        result.insert(Site::Named(named.clone()));
+                    }
+                }
+                Site::Exclude(site) => {
+                    return Err(PkMakeError::InvalidSite(format!("!{}", site)))
+                }
+                site => {
+                    result.insert(site);
                 }
             }
         }
+        Ok(result)
+    }
+}
+
+/// Display the Site as the user would request it
+impl fmt::Display for Site {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Group(group) => write!(f, "@{}", group),
+            Self::Exclude(site) => write!(f, "!{}", site),
+            _ => write!(f, "{}", self.as_str()),
+        }
     }
 }
 
-/// Implementation of TryFrom provides fallible conversion from a &str. 
+/// Falible conversion from a string, against the builtin SiteRegistry
+impl FromStr for Site {
+    type Err = PkMakeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Site::parse(s, &SiteRegistry::builtin())
+    }
+}
+
+/// Implementation of TryFrom provides fallible conversion from a &str.
 impl TryFrom<&str> for Site {
     type Error = PkMakeError;
 
@@ -65,6 +215,17 @@ impl TryFrom<&str> for Site {
     }
 }
 
+/// Implementation of TryFrom provides fallible conversion from a String. Sites are
+/// accepted as Strings from the command line, since the SiteRegistry is not known until
+/// the config has been loaded.
+impl TryFrom<String> for Site {
+    type Error = PkMakeError;
+
+    fn try_from(input: String) -> Result<Self, Self::Error> {
+        Self::from_str(&input)
+    }
+}
+
 #[cfg(test)]
 #[path = "./unit_tests/site_test.rs"]
 mod site_test;
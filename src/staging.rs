@@ -0,0 +1,129 @@
+//! Staging
+//!
+//! Copy dist artifacts into a scratch directory, laid out as they would be when
+//! installed, without calling `pk install`. Each artifact is staged under
+//! `<root>/<name>/<version>[_flavor]/<platform>`, and recorded in an index file
+//! at the root, so that the integration tests of downstream packages can locate
+//! what was staged:
+//!
+//! ```toml
+//! [[staged]]
+//! name = "foo"
+//! version = "1.0.0_bar"
+//! platform = "cent7_64"
+//! source = "/dd/dept/foo/private/dist/foo-1.0.0_bar"
+//! destination = "/scratch/foo/1.0.0_bar/cent7_64"
+//! ```
+//!
+//! Staging the same destination again replaces both the staged files and the
+//! index entry.
+
+// External crate imports
+use anyhow::anyhow;
+use anyhow::Error as AnyError;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// The name of the index file, within the staging root
+pub const INDEX: &str = "pk-make-index.toml";
+
+/// A single staged artifact
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct StagedPackage {
+    pub name: String,
+    /// The version, suffixed with `_flavor` for flavors other than vanilla
+    pub version: String,
+    pub platform: String,
+    pub source: PathBuf,
+    pub destination: PathBuf,
+}
+
+impl StagedPackage {
+    /// Copy the source artifact to the destination, replacing anything previously
+    /// staged there
+    pub fn stage(&self) -> Result<(), AnyError> {
+        if self.destination.exists() {
+            std::fs::remove_dir_all(&self.destination)?;
+        }
+        copy_dir(&self.source, &self.destination).map_err(|e| {
+            anyhow!(
+                "Unable to stage '{}' to '{}': {}",
+                self.source.display(),
+                self.destination.display(),
+                e
+            )
+        })
+    }
+}
+
+/// The index of the artifacts staged under a root directory
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+pub struct StagingIndex {
+    #[serde(default)]
+    pub staged: Vec<StagedPackage>,
+}
+
+impl StagingIndex {
+    /// Load the index from the staging root. A missing index is empty.
+    pub fn load(root: &Path) -> Result<Self, AnyError> {
+        let path = root.join(INDEX);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(&path)?;
+        toml::from_str(&contents)
+            .map_err(|e| anyhow!("Unable to read staging index '{}': {}", path.display(), e))
+    }
+
+    /// Add a staged package, replacing any entry with the same destination
+    pub fn add(&mut self, package: StagedPackage) {
+        self.staged.retain(|p| p.destination != package.destination);
+        self.staged.push(package);
+    }
+
+    /// Write the index to the staging root
+    pub fn save(&self, root: &Path) -> Result<(), AnyError> {
+        std::fs::create_dir_all(root)?;
+        std::fs::write(root.join(INDEX), toml::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+// recursively copy the directory src to dst, recreating symlinks rather than
+// following them
+fn copy_dir(src: &Path, dst: &Path) -> Result<(), std::io::Error> {
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let target = dst.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else if file_type.is_symlink() {
+            copy_symlink(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+// recreate the symlink src at dst, pointing at the same (possibly relative) target
+#[cfg(unix)]
+fn copy_symlink(src: &Path, dst: &Path) -> Result<(), std::io::Error> {
+    std::os::unix::fs::symlink(std::fs::read_link(src)?, dst)
+}
+
+// without unix symlinks, copy whatever the link resolves to instead
+#[cfg(not(unix))]
+fn copy_symlink(src: &Path, dst: &Path) -> Result<(), std::io::Error> {
+    if src.is_dir() {
+        copy_dir(src, dst)
+    } else {
+        std::fs::copy(src, dst).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+#[path = "./unit_tests/staging_test.rs"]
+mod staging_test;
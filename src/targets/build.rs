@@ -4,12 +4,15 @@
 //! by the user to trigger a build.
 
 // Internal crate imports
+use crate::build_cache::{BuildInputs, CachePlan};
 use crate::build_env::BuildEnv;
 use crate::flavor::Flavor;
+use crate::hooks::{HookContext, HookPoint, Hooks};
+use crate::level::Level;
 use crate::OverridePair;
-use crate::platform::Platform;
+use crate::platform::{verify_buildable, Platform, PlatformRegistry};
 use crate::traits::{Doit, Tabulate};
-use crate::utils::exec_cmd;
+use crate::utils::{exec_cmd, ExitStatus};
 
 // External crate imports
 use anyhow::anyhow;
@@ -26,11 +29,12 @@ use std::convert::TryInto;
 #[derive(Debug, PartialEq, Eq)]
 pub struct Build {
     pub clean: bool,
+    pub rebuild: bool,
     pub with_docs: bool,
     pub dry_run: bool,
     pub dist_dir: Option<String>,
     pub flavors: Option<HashSet<Flavor>>,
-    pub level: Option<String>,
+    pub level: Option<Level>,
     pub metadata_only: bool,
     pub overrides: Option<Vec<OverridePair>>,
     pub platforms: Option<HashSet<Platform>>,
@@ -38,6 +42,8 @@ pub struct Build {
     pub defines: Option<Vec<String>>,
     pub work: bool,
     pub package_root: Option<std::path::PathBuf>,
+    pub hooks: Hooks,
+    pub platform_registry: PlatformRegistry,
 }
 
 /// We provide a means to generate a set of subshell commands to trigger
@@ -48,48 +54,122 @@ impl Doit for Build {
     /// Generate the subshell pk struct commands from current state, and
     /// execute them in a subshell.
     fn doit(&mut self) -> Result<(), Self::Err> {
+        self.execute().map(|_| ())
+    }
+
+    /// Construct the commands to be executed in a subshell as a vector of strings.
+    /// Flavors whose cache key is unchanged are not built, so the vector is empty
+    /// when every flavor is up to date.
+    fn build_cmd(&mut self) -> Result<Vec<String>, Self::Err> {
+        let build_env = BuildEnv::with_platforms(self.get_package_root(), &self.platform_registry)?;
+        let plan = self.cache_plan(&build_env)?;
+        self.build_cmd_with(&build_env, plan.as_ref())
+    }
+}
+
+//
+// Execution
+//
+impl Build {
+    /// Execute the build as doit does, returning the exit status of the pk
+    /// commands, or None if they did not run (ie a dry run, or every flavor is up
+    /// to date).
+    pub fn execute(&mut self) -> Result<Option<ExitStatus>, AnyError> {
         if self.verbose {
             self.tabulate();
         }
-        let cmd = self.build_cmd()?;
+        let build_env = BuildEnv::with_platforms(self.get_package_root(), &self.platform_registry)?;
+        let plan = self.cache_plan(&build_env)?;
+        let cmd = self.build_cmd_with(&build_env, plan.as_ref())?;
+        if cmd.is_empty() {
+            println!("Every flavor is up to date. Use --rebuild to force a build");
+            return Ok(None);
+        }
+        let context = if self.hooks.is_empty() {
+            HookContext::default()
+        } else {
+            HookContext::new(
+                &build_env,
+                self.dist_dir.as_deref(),
+                self.flavors.as_ref(),
+                self.level.as_ref(),
+            )?
+        };
         if self.dry_run {
+            self.hooks.run(HookPoint::PreBuild, &context, true, self.verbose)?;
             for c in &cmd {
                 println!("{}", c);
             }
+            self.hooks.run(HookPoint::PostBuild, &context, true, self.verbose)?;
+            Ok(None)
         } else {
             if self.verbose {
                 for c in &cmd {
                     println!("{}", c);
                 }
             }
-            let cmd = cmd.join(" ; ");
-
-            let exit_status = exec_cmd(cmd.as_str(), self.get_package_root())?;
-            println!("\nExit Status: {:?}", exit_status);
+            let result = self.run_build(&cmd, plan, &context);
+            let error = match result {
+                Ok(ref status) if !status.success() => Some(format!("{:?}", status)),
+                Ok(_) => None,
+                Err(ref e) => Some(e.to_string()),
+            };
+            if let Some(error) = error {
+                self.hooks.run_on_failure(&context, &error, self.verbose);
+            }
+            result.map(Some)
         }
-        Ok(())
     }
 
-    /// Construct the commands to be executed in a subshell as a vector of strings.
-    fn build_cmd(&mut self) -> Result<Vec<String>, Self::Err> {
-        let package_root = self
-            .package_root
-            .as_deref()
-            .unwrap_or_else(|| std::path::Path::new("."));
+    // Run the pre-build hooks, the pk commands, and, if they succeed, the
+    // post-build hooks, recording the cache keys of the flavors built
+    fn run_build(
+        &self,
+        cmd: &[String],
+        mut plan: Option<CachePlan>,
+        context: &HookContext,
+    ) -> Result<ExitStatus, AnyError> {
+        self.hooks.run(HookPoint::PreBuild, context, false, self.verbose)?;
+        if let Some(ref mut plan) = plan {
+            plan.invalidate()?;
+        }
+        let exit_status = exec_cmd(cmd.join(" ; ").as_str(), self.get_package_root())?;
+        println!("\nExit Status: {:?}", exit_status);
+        if exit_status.success() {
+            if let Some(ref mut plan) = plan {
+                plan.record()?;
+            }
+            self.hooks.run(HookPoint::PostBuild, context, false, self.verbose)?;
+        }
+        Ok(exit_status)
+    }
+}
 
-        let build_env = BuildEnv::new(package_root)?;
+//
+// Private methods - helpers to construct pk command flags
+//
+impl Build {
+    // Construct the commands given the cache plan
+    fn build_cmd_with(
+        &mut self,
+        build_env: &BuildEnv,
+        plan: Option<&CachePlan>,
+    ) -> Result<Vec<String>, AnyError> {
+        let flavor_str = match plan {
+            Some(plan) if plan.is_current() => return Ok(Vec::new()),
+            Some(plan) => plan.flavor_str().unwrap_or_else(|| self.get_flavor_str()),
+            None => self.get_flavor_str(),
+        };
 
         let clean_str = if self.clean { " --clean" } else { "" };
 
         let defines_str = self.get_defines_str();
 
         // if the use supplied the dist_dir, great. Otherwise, grab it from the env
-        let dist_dir_str = self.get_dist_dir_str(&build_env)?;
+        let dist_dir_str = self.get_dist_dir_str(build_env)?;
 
         let docs_str = self.get_docs_str();
 
-        let flavor_str = self.get_flavor_str();
-
         let level_str = self.get_level_str();
 
         let metadata_only_str = if self.metadata_only {
@@ -120,12 +200,33 @@ impl Doit for Build {
         )];
         Ok(result)
     }
-}
 
-//
-// Private methods - helpers to construct pk command flags
-//
-impl Build {
+    // Plan the build with the cache, refusing platforms which we cannot build.
+    // Metadata only builds are not cached. Every flavor is built when clean or
+    // rebuild is requested.
+    fn cache_plan(&self, build_env: &BuildEnv) -> Result<Option<CachePlan>, AnyError> {
+        if let Some(ref platforms) = self.platforms {
+            verify_buildable(platforms)?;
+        }
+        if self.metadata_only {
+            return Ok(None);
+        }
+        let inputs = BuildInputs {
+            dist_dir: self.dist_dir.as_deref(),
+            flavors: self.flavors.as_ref(),
+            platforms: self.platforms.as_ref(),
+            overrides: self.overrides.as_deref(),
+            defines: self.defines.as_deref(),
+            with_docs: self.with_docs,
+            work: self.work,
+        };
+        let plan = CachePlan::new(build_env, &inputs, self.clean || self.rebuild)?;
+        if self.dry_run || self.verbose {
+            plan.report();
+        }
+        Ok(Some(plan))
+    }
+
     fn get_defines_str(&self) -> String {
         // NB: The -D flag works differently in pk build in that it
         // follows posix convention for multiple values; it supports
@@ -250,6 +351,7 @@ impl Default for Build {
     fn default() -> Self {
         Self {
             clean: false,
+            rebuild: false,
             with_docs: true,
             dry_run: false,
             dist_dir: None,
@@ -262,6 +364,8 @@ impl Default for Build {
             defines: None,
             work: false,
             package_root: None,
+            hooks: Hooks::default(),
+            platform_registry: PlatformRegistry::builtin(),
         }
     }
 }
@@ -275,6 +379,18 @@ impl Build {
         self.clean = value;
         self
     }
+    /// Set the rebuild value and return a mutable reference to self per the builder
+    /// pattern. When set, every flavor is built, regardless of its cache key.
+    pub fn rebuild(&mut self, value: bool) -> &mut Self {
+        self.rebuild = value;
+        self
+    }
+    /// Set the hooks to run around the build and return a mutable reference to self
+    /// per the builder pattern.
+    pub fn hooks(&mut self, value: Hooks) -> &mut Self {
+        self.hooks = value;
+        self
+    }
     /// Set the with_docs value and return a mutable reference to self per the
     /// builder pattern.
     pub fn with_docs(&mut self, value: bool) -> &mut Self {
@@ -334,15 +450,18 @@ impl Build {
     }
     /// Set the level value and return a mutable reference to
     /// self, per the builder pattern.
-    pub fn level<I>(&mut self, input: Option<I>) -> &mut Self
+    pub fn level<I>(&mut self, input: Option<I>) -> Result<&mut Self, AnyError>
     where
-        I: Into<String>,
+        I: TryInto<Level>,
+        I::Error: std::fmt::Display,
     {
         match input {
             None => self.level = None,
-            Some(level) => self.level = Some(level.into()),
+            Some(level) => {
+                self.level = Some(level.try_into().map_err(|e| anyhow!("{}", e))?)
+            }
         }
-        self
+        Ok(self)
     }
 
     /// Set the metadata_only value and return a mutable reference to self
@@ -403,15 +522,17 @@ impl Build {
     /// ```
     pub fn platforms<I>(&mut self, value: Option<Vec<I>>) -> Result<&mut Self, AnyError>
     where
-        I: TryInto<Platform>,
+        I: AsRef<str>,
     {
         match value {
             None => self.platforms = None,
             Some(plats) => {
-                let plats: Result<Vec<_>, _> =
-                    plats.into_iter().map(|i_val| i_val.try_into()).collect();
+                let plats: Result<Vec<_>, _> = plats
+                    .iter()
+                    .map(|i_val| self.platform_registry.parse(i_val.as_ref()))
+                    .collect();
                 match plats {
-                    Err(_) => return Err(anyhow!("failed to convert to platform")),
+                    Err(e) => return Err(anyhow!("failed to convert to platform: {}", e)),
                     Ok(val) => match self.platforms {
                         Some(ref mut platforms) => {
                             for v in val {
@@ -454,6 +575,15 @@ impl Build {
         self
     }
 
+    /// New up a Build whose platforms, including the host platform, are looked up
+    /// in the supplied registry, rather than the builtin one.
+    pub fn with_platform_registry(platform_registry: PlatformRegistry) -> Self {
+        Self {
+            platform_registry,
+            ..Self::default()
+        }
+    }
+
     /// Terminate a chain of calls with a build to return an owned instance.
     ///
     /// # Example
@@ -478,6 +608,12 @@ impl Tabulate for Build {
         let mut table = Table::new();
         table.add_row(row!["Field", "Value"]);
         table.add_row(row!["clean", self.clean]);
+        table.add_row(row!["rebuild", self.rebuild]);
+        for point in &[HookPoint::PreBuild, HookPoint::PostBuild, HookPoint::OnFailure] {
+            for cmd in self.hooks.get(*point) {
+                table.add_row(row![format!("hooks.{}", point), cmd]);
+            }
+        }
         table.add_row(row!["with_docs", self.with_docs]);
         table.add_row(row!["dry_run", self.dry_run]);
         table.add_row(row!["dist_dir", self.dist_dir.as_deref().unwrap_or("None")]);
@@ -488,7 +624,10 @@ impl Tabulate for Build {
                 .map(|v| v.iter().map(|x| x.as_str()).collect::<Vec<_>>().join("\n"))
                 .unwrap_or_else(|| "None".to_string())
         ]);
-        table.add_row(row!["level", self.level.as_deref().unwrap_or("None")]);
+        table.add_row(row![
+            "level",
+            self.level.as_ref().map(|l| l.to_string()).unwrap_or_else(|| "None".to_string())
+        ]);
         table.add_row(row!["metadata_only", self.metadata_only]);
         table.add_row(row![
             "overrides",
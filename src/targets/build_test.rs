@@ -11,6 +11,7 @@ pub fn default_produces_instance_with_expected_state() {
     let result = Build::default();
     let expected = Build {
         clean: false,
+        rebuild: false,
         with_docs: true,
         dry_run: false,
         dist_dir: None,
@@ -23,6 +24,8 @@ pub fn default_produces_instance_with_expected_state() {
         defines: None,
         work: false,
         package_root: None,
+        hooks: Hooks::default(),
+        platform_registry: PlatformRegistry::builtin(),
     };
     assert_eq!(result, expected);
 }
@@ -34,6 +37,7 @@ pub fn can_set_with_docs() {
     result.with_docs(false);
     let expected = Build {
         clean: false,
+        rebuild: false,
         with_docs: false, // set by with_docs above
         dry_run: false,
         dist_dir: None,
@@ -46,6 +50,8 @@ pub fn can_set_with_docs() {
         defines: None,
         work: false,
         package_root: None,
+        hooks: Hooks::default(),
+        platform_registry: PlatformRegistry::builtin(),
     };
     assert_eq!(result, expected);
 }
@@ -56,6 +62,7 @@ pub fn dry_run_given_bool_sets_state() {
     result.dry_run(true);
     let expected = Build {
         clean: false,
+        rebuild: false,
         with_docs: true, // set by with_docs above
         dry_run: true,
         dist_dir: None,
@@ -68,6 +75,8 @@ pub fn dry_run_given_bool_sets_state() {
         defines: None,
         work: false,
         package_root: None,
+        hooks: Hooks::default(),
+        platform_registry: PlatformRegistry::builtin(),
     };
     assert_eq!(result, expected);
 }
@@ -78,6 +87,7 @@ fn dist_dir_given_some_str_updates_state() {
     result.dist_dir(Some("foo/bar"));
     let expected = Build {
         clean: false,
+        rebuild: false,
         with_docs: true, // set by with_docs above
         dry_run: false,
         dist_dir: Some("foo/bar".to_string()),
@@ -90,6 +100,8 @@ fn dist_dir_given_some_str_updates_state() {
         defines: None,
         work: false,
         package_root: None,
+        hooks: Hooks::default(),
+        platform_registry: PlatformRegistry::builtin(),
     };
     assert_eq!(result, expected);
     // now test it with a String
@@ -104,6 +116,7 @@ fn dist_dir_given_some_string_updates_state() {
     result.dist_dir(Some("foo/bar".to_string()));
     let expected = Build {
         clean: false,
+        rebuild: false,
         with_docs: true, // set by with_docs above
         dry_run: false,
         dist_dir: Some("foo/bar".to_string()),
@@ -116,6 +129,8 @@ fn dist_dir_given_some_string_updates_state() {
         defines: None,
         work: false,
         package_root: None,
+        hooks: Hooks::default(),
+        platform_registry: PlatformRegistry::builtin(),
     };
     assert_eq!(result, expected);
     // now test it with a String
@@ -139,6 +154,7 @@ fn flavors_given_some_vec_flavor_updates_state() {
     flavs.insert(Flavor::Named("foo".to_string()));
     let expected = Build {
         clean: false,
+        rebuild: false,
         with_docs: true, // set by with_docs above
         dry_run: false,
         dist_dir: None,
@@ -151,6 +167,8 @@ fn flavors_given_some_vec_flavor_updates_state() {
         defines: None,
         work: false,
         package_root: None,
+        hooks: Hooks::default(),
+        platform_registry: PlatformRegistry::builtin(),
     };
     assert_eq!(result, expected);
 }
@@ -167,6 +185,7 @@ fn flavors_given_none_sets_state_to_none() {
     result.flavors(None::<Vec<Flavor>>).unwrap();
     let expected = Build {
         clean: false,
+        rebuild: false,
         with_docs: true, // set by with_docs above
         dry_run: false,
         dist_dir: None,
@@ -179,6 +198,8 @@ fn flavors_given_none_sets_state_to_none() {
         defines: None,
         work: false,
         package_root: None,
+        hooks: Hooks::default(),
+        platform_registry: PlatformRegistry::builtin(),
     };
     assert_eq!(result, expected);
 }
@@ -189,11 +210,12 @@ fn platforms_given_some_vec_str_updates_state() {
     result.platforms(Some(vec!["cent7", "cent6"])).unwrap();
 
     let mut pforms = HashSet::new();
-    pforms.insert(Platform::Cent7_64);
-    pforms.insert(Platform::Cent6_64);
+    pforms.insert(Platform::from("cent7_64").unwrap());
+    pforms.insert(Platform::from("cent6_64").unwrap());
 
     let expected = Build {
         clean: false,
+        rebuild: false,
         with_docs: true, // set by with_docs above
         dry_run: false,
         dist_dir: None,
@@ -206,37 +228,36 @@ fn platforms_given_some_vec_str_updates_state() {
         defines: None,
         work: false,
         package_root: None,
+        hooks: Hooks::default(),
+        platform_registry: PlatformRegistry::builtin(),
     };
     assert_eq!(result, expected);
 }
 
 #[test]
-fn platforms_given_some_vec_platform_updates_state() {
-    let mut result = Build::default();
-    result
-        .platforms(Some(vec![Platform::Cent6_64, Platform::Cent7_64]))
+fn platforms_given_registry_accepts_configured_platforms() {
+    let mut registry = PlatformRegistry::builtin();
+    registry
+        .extend(&[Platform::new(
+            "rocky9_64",
+            vec!["rocky9".into()],
+            crate::platform::OsFamily::Linux,
+            true,
+        )])
         .unwrap();
-
-    let mut pforms = HashSet::new();
-    pforms.insert(Platform::Cent7_64);
-    pforms.insert(Platform::Cent6_64);
-
-    let expected = Build {
-        clean: false,
-        with_docs: true, // set by with_docs above
-        dry_run: false,
-        dist_dir: None,
-        flavors: None,
-        level: None,
-        metadata_only: false,
-        overrides: None,
-        platforms: Some(pforms),
-        verbose: false,
-        defines: None,
-        work: false,
-        package_root: None,
-    };
-    assert_eq!(result, expected);
+    let result = Build::with_platform_registry(registry)
+        .platforms(Some(vec!["rocky9", "cent7"]))
+        .unwrap()
+        .build();
+    let platforms = result
+        .platforms
+        .unwrap()
+        .iter()
+        .map(|p| p.as_str().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(platforms, vec!["rocky9_64", "cent7_64"]);
+    // the builtin registry knows nothing of rocky9
+    assert!(Build::default().platforms(Some(vec!["rocky9"])).is_err());
 }
 
 #[test]
@@ -245,6 +266,7 @@ fn verbose_given_bool_updates_state() {
     result.verbose(true);
     let expected = Build {
         clean: false,
+        rebuild: false,
         with_docs: true,
         dry_run: false,
         dist_dir: None,
@@ -257,6 +279,8 @@ fn verbose_given_bool_updates_state() {
         defines: None,
         work: false,
         package_root: None,
+        hooks: Hooks::default(),
+        platform_registry: PlatformRegistry::builtin(),
     };
     assert_eq!(result, expected);
 }
@@ -267,6 +291,7 @@ fn defines_given_vec_str_updates_state() {
     result.defines(Some(vec!["foo=bar", "ba=ba_blacksheep"]));
     let expected = Build {
         clean: false,
+        rebuild: false,
         with_docs: true,
         dry_run: false,
         dist_dir: None,
@@ -279,6 +304,8 @@ fn defines_given_vec_str_updates_state() {
         defines: Some(vec!["foo=bar".to_string(), "ba=ba_blacksheep".to_string()]),
         work: false,
         package_root: None,
+        hooks: Hooks::default(),
+        platform_registry: PlatformRegistry::builtin(),
     };
     assert_eq!(result, expected);
 }
@@ -292,6 +319,7 @@ fn defines_given_vec_string_updates_state() {
     ]));
     let expected = Build {
         clean: false,
+        rebuild: false,
         with_docs: true,
         dry_run: false,
         dist_dir: None,
@@ -304,6 +332,8 @@ fn defines_given_vec_string_updates_state() {
         defines: Some(vec!["foo=bar".to_string(), "ba=ba_blacksheep".to_string()]),
         work: false,
         package_root: None,
+        hooks: Hooks::default(),
+        platform_registry: PlatformRegistry::builtin(),
     };
     assert_eq!(result, expected);
 }
@@ -322,6 +352,7 @@ fn build_given_mut_ref_to_self_produces_owned_instance() {
     flavs.insert(Flavor::Vanilla);
     let expected = Build {
         clean: false,
+        rebuild: false,
         with_docs: false, // set by with_docs above
         dry_run: true,
         dist_dir: Some("foo/bar".to_string()),
@@ -334,6 +365,8 @@ fn build_given_mut_ref_to_self_produces_owned_instance() {
         defines: None,
         work: false,
         package_root: None,
+        hooks: Hooks::default(),
+        platform_registry: PlatformRegistry::builtin(),
     };
     assert_eq!(result, expected);
 }
@@ -464,6 +497,39 @@ fn build_cmd_given_clean_platforms() {
     assert_eq!(result.unwrap(), expected);
 }
 
+#[test]
+#[serial]
+fn build_cmd_given_platform_from_other_os_passes_it_through() {
+    setup_manifest_dir(false);
+    env::set_var("DD_SHOW", "DEV01");
+    env::set_var("DD_OS", "cent7_64");
+    let result = Build::default()
+        .platforms(Some(vec!["cent7", "win10"]))
+        .unwrap()
+        .build_cmd();
+    let expected = vec![
+        "pk audit && pk build --with-docs --platform=cent7_64,win10_64".to_string(),
+    ];
+    assert_eq!(result.unwrap(), expected);
+}
+
+#[test]
+#[serial]
+fn build_cmd_given_unbuildable_platform_fails() {
+    setup_manifest_dir(false);
+    env::set_var("DD_SHOW", "DEV01");
+    env::set_var("DD_OS", "cent7_64");
+    // we have no osx build machines
+    let result = Build::default()
+        .platforms(Some(vec!["cent7", "osx10"]))
+        .unwrap()
+        .build_cmd();
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Platform 'osx10_64' is not buildable. We have no build machines for it"
+    );
+}
+
 
 #[test]
 #[serial]
@@ -479,6 +545,7 @@ fn build_cmd_given_clean_distdir_flavors_platforms_showlevel() {
         .platforms(Some(vec!["cent6","cent7"]))
         .unwrap()
         .level(Some("DEV01"))
+        .unwrap()
         .build_cmd();
     let expected = vec![
         "pk audit && pk build --clean --dist-dir=./foo/bar --with-docs --flavour=^,foo --platform=cent6_64,cent7_64 --level=DEV01".to_string(),
@@ -495,6 +562,7 @@ fn build_cmd_given_showlevel() {
     env::set_var("DD_OS", "cent7_64");
     let result = Build::default()
         .level(Some("DEV01"))
+        .unwrap()
         .build_cmd();
     let expected = vec![
         "pk audit && pk build --with-docs --level=DEV01".to_string(),
@@ -516,6 +584,7 @@ fn build_cmd_given_clean_distdir_flavors_platforms_worklevel() {
         .platforms(Some(vec!["cent6","cent7"]))
         .unwrap()
         .level(Some("DEV01.work"))
+        .unwrap()
         .build_cmd();
     let expected = vec![
         "pk audit && pk build --clean --dist-dir=./foo/bar --with-docs --flavour=^,foo --platform=cent6_64,cent7_64 --level=DEV01.work".to_string(),
@@ -531,6 +600,7 @@ fn build_cmd_given_worklevel() {
     env::set_var("DD_OS", "cent7_64");
     let result = Build::default()
         .level(Some("DEV01.work"))
+        .unwrap()
         .build_cmd();
     let expected = vec![
         "pk audit && pk build --with-docs --level=DEV01.work".to_string(),
@@ -553,6 +623,7 @@ fn build_cmd_given_clean_distdir_flavors_platforms_showlevel_metadataonly() {
         .platforms(Some(vec!["cent6","cent7"]))
         .unwrap()
         .level(Some("DEV01"))
+        .unwrap()
         .metadata_only(true)
         .build_cmd();
     // NOTICE that we do not add --with docs. there is no reason to build docs
@@ -592,6 +663,7 @@ fn build_cmd_given_clean_distdir_flavors_platforms_worklevel_overrides() {
         .platforms(Some(vec!["cent6","cent7"]))
         .unwrap()
         .level(Some("DEV01.work"))
+        .unwrap()
         .overrides(Some(vec!["make=2.0.0","bs=2.1.0"]))
         .unwrap()
         .build_cmd();
@@ -634,6 +706,7 @@ fn build_cmd_given_clean_distdir_flavors_platforms_worklevel_overrides_defines()
         .platforms(Some(vec!["cent6","cent7"]))
         .unwrap()
         .level(Some("DEV01.work"))
+        .unwrap()
         .overrides(Some(vec!["make=2.0.0","bs=2.1.0"]))
         .unwrap()
         .defines(Some(vec!["foo=bar", "la=deda"]))
@@ -676,6 +749,7 @@ fn build_cmd_given_clean_distdir_flavors_platforms_worklevel_overrides_defines_v
         .platforms(Some(vec!["cent6","cent7"]))
         .unwrap()
         .level(Some("DEV01.work"))
+        .unwrap()
         .overrides(Some(vec!["make=2.0.0","bs=2.1.0"]))
         .unwrap()
         .defines(Some(vec!["foo=bar", "la=deda"]))
@@ -716,6 +790,7 @@ fn build_cmd_given_clean_distdir_flavors_platforms_worklevel_overrides_defines_v
         .platforms(Some(vec!["cent6","cent7"]))
         .unwrap()
         .level(Some("DEV01.work"))
+        .unwrap()
         .overrides(Some(vec!["make=2.0.0","bs=2.1.0"]))
         .unwrap()
         .defines(Some(vec!["foo=bar", "la=deda"]))
@@ -741,4 +816,67 @@ fn build_cmd_given_work() {
         "pk audit && pk build --with-docs --work".to_string(),
     ];
     assert_eq!(result.unwrap(), expected);
-}
\ No newline at end of file
+}
+// malformed levels are rejected by the builder, before pk is invoked
+#[test]
+fn level_given_malformed_spec_fails() {
+    assert!(Build::default().level(Some("DEV01.work.rd")).is_err());
+    assert!(Build::default().level(Some("DEV01.work")).is_ok());
+}
+
+// flavors whose cache key is unchanged are not rebuilt, unless rebuild is requested
+#[test]
+#[serial]
+fn build_cmd_skips_flavors_with_unchanged_cache_key() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = std::fs::canonicalize(dir.path()).unwrap();
+    std::fs::write(
+        root.join("manifest.yaml"),
+        "name: foo\nversion: 1.0.0\nflavours:\n  - name: ^\n  - name: bar\n",
+    )
+    .unwrap();
+    env::set_current_dir(&root).unwrap();
+    env::set_var("DD_OS", "cent7_64");
+    let dist = root.join("private").join("dist");
+
+    let mut build = Build::default().package_root(Some(&root)).build();
+    assert_eq!(build.build_cmd().unwrap(), vec!["pk audit && pk build --with-docs".to_string()]);
+
+    // simulate a successful build of both flavors
+    std::fs::create_dir_all(dist.join("foo-1.0.0")).unwrap();
+    std::fs::create_dir_all(dist.join("foo-1.0.0_bar")).unwrap();
+    let build_env = BuildEnv::new(&root).unwrap();
+    build.cache_plan(&build_env).unwrap().unwrap().record().unwrap();
+    assert!(build.build_cmd().unwrap().is_empty());
+
+    // only the flavor whose key has gone missing is built
+    std::fs::remove_file(dist.join("foo-1.0.0_bar.cachekey")).unwrap();
+    assert_eq!(
+        build.build_cmd().unwrap(),
+        vec!["pk audit && pk build --with-docs --flavour=bar".to_string()]
+    );
+
+    // changing an input invalidates every flavor
+    build.cache_plan(&build_env).unwrap().unwrap().record().unwrap();
+    build.defines(Some(vec!["FOO=1"]));
+    assert_eq!(build.build_cmd().unwrap().len(), 1);
+    build.defines(None::<Vec<String>>);
+    assert!(build.build_cmd().unwrap().is_empty());
+
+    // a --work build resolves dependencies differently, so is not satisfied by a
+    // build without it
+    build.work(true);
+    assert_eq!(build.build_cmd().unwrap().len(), 1);
+    build.work(false);
+
+    // list inputs are compared item by item, rather than joined
+    build.defines(Some(vec!["A=1,B=2"]));
+    build.cache_plan(&build_env).unwrap().unwrap().record().unwrap();
+    assert!(build.build_cmd().unwrap().is_empty());
+    build.defines(Some(vec!["A=1", "B=2"]));
+    assert_eq!(build.build_cmd().unwrap().len(), 1);
+    build.defines(None::<Vec<String>>);
+
+    build.rebuild(true);
+    assert_eq!(build.build_cmd().unwrap(), vec!["pk audit && pk build --with-docs".to_string()]);
+}
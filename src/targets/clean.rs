@@ -0,0 +1,340 @@
+//! Clean
+//!
+//! Remove the output of `pk build` from the package's private directory. By default
+//! both the build and dist directories are removed. `--build` or `--dist` restricts
+//! the clean to one of them, and `--flavor` to the artifacts of the named flavors
+//! (along with their build cache keys). `--stale` removes the artifacts of flavors
+//! which are no longer in the manifest.
+//!
+//! Nothing outside the private directory is ever removed; a path which resolves
+//! elsewhere (eg via a symlink) is refused. A dry run reports what would be removed,
+//! along with its size.
+
+// Internal crate imports
+use crate::build_cache::{key_file, KEY_EXTENSION};
+use crate::traits::{Doit, Tabulate};
+use crate::BuildEnv;
+use crate::Flavor;
+use crate::ManifestInfo;
+use crate::PkMakeError;
+use crate::PlatformRegistry;
+
+// External crate imports
+use anyhow::anyhow;
+use anyhow::Error as AnyError;
+use indexmap::IndexSet as HashSet;
+use prettytable::{row, Table};
+use std::convert::TryInto;
+use std::path::{Path, PathBuf};
+
+/// Models a request to remove build output
+#[derive(Debug, PartialEq, Eq, Default)]
+pub struct Clean {
+    pub build: bool,
+    pub dist: bool,
+    pub flavors: Option<HashSet<Flavor>>,
+    pub stale: bool,
+    pub dry_run: bool,
+    pub verbose: bool,
+    pub package_root: Option<PathBuf>,
+    pub platform_registry: PlatformRegistry,
+}
+
+impl Doit for Clean {
+    type Err = AnyError;
+
+    /// Remove each path in the plan, or report them when dry_run
+    fn doit(&mut self) -> Result<(), Self::Err> {
+        if self.verbose {
+            self.tabulate();
+        }
+        let plan = self.plan()?;
+        if plan.is_empty() {
+            println!("Nothing to clean");
+            return Ok(());
+        }
+        let mut total = 0;
+        for path in plan {
+            let size = disk_usage(&path)?;
+            total += size;
+            if self.dry_run {
+                println!("Would remove {} ({})", path.display(), human_size(size));
+            } else {
+                remove(&path)?;
+                if self.verbose {
+                    println!("Removed {} ({})", path.display(), human_size(size));
+                }
+            }
+        }
+        let action = if self.dry_run { "Would free" } else { "Freed" };
+        println!("{} {}", action, human_size(total));
+        Ok(())
+    }
+
+    fn build_cmd(&mut self) -> Result<Vec<String>, Self::Err> {
+        Ok(self
+            .plan()?
+            .iter()
+            .map(|p| format!("rm -rf {}", p.display()))
+            .collect())
+    }
+}
+
+//
+// Public methods
+//
+impl Clean {
+    /// The existing paths which would be removed. Every path is verified to lie
+    /// within the private directory.
+    pub fn plan(&self) -> Result<Vec<PathBuf>, AnyError> {
+        let build_env = BuildEnv::with_platforms(self.get_package_root(), &self.platform_registry)?;
+        let dirs = self.get_dirs(&build_env);
+        let mut plan = Vec::new();
+        if self.flavors.is_none() && !self.stale {
+            plan.extend(dirs);
+        } else {
+            let manifest_info = ManifestInfo::from_path(&build_env.manifest)?;
+            if let Some(ref flavors) = self.flavors {
+                for dir in &dirs {
+                    for flavor in flavors {
+                        let artifact = dir.join(manifest_info.package_id(flavor));
+                        plan.push(key_file(&artifact));
+                        plan.push(artifact);
+                    }
+                }
+            }
+            if self.stale {
+                for dir in &dirs {
+                    for path in stale_artifacts(dir, &manifest_info)? {
+                        if !plan.contains(&path) {
+                            plan.push(path);
+                        }
+                    }
+                }
+            }
+        }
+        // symlink_metadata so that dangling links are still removed
+        plan.retain(|p| p.symlink_metadata().is_ok());
+        for path in &plan {
+            verify_within(&build_env.private_dir, path)?;
+        }
+        Ok(plan)
+    }
+
+    /// Restrict the clean to the build directory. May be combined with dist.
+    pub fn build_dir(&mut self, input: bool) -> &mut Self {
+        self.build = input;
+        self
+    }
+
+    /// Restrict the clean to the dist directory. May be combined with build.
+    pub fn dist_dir(&mut self, input: bool) -> &mut Self {
+        self.dist = input;
+        self
+    }
+
+    /// Restrict the clean to the artifacts of the given flavors. This may be called
+    /// multiple times to accumulate flavors.
+    pub fn flavors<I>(&mut self, value: Option<Vec<I>>) -> Result<&mut Self, AnyError>
+    where
+        I: TryInto<Flavor> + std::fmt::Debug + Clone,
+    {
+        if let Some(flavors) = value {
+            let flavors: Result<Vec<_>, _> =
+                flavors.into_iter().map(|i_val| i_val.try_into()).collect();
+            let flavors = flavors.map_err(|_| anyhow!("failed to convert one or more flavors"))?;
+            self.flavors
+                .get_or_insert_with(HashSet::new)
+                .extend(flavors);
+        }
+        Ok(self)
+    }
+
+    /// Also remove the artifacts of flavors which are no longer in the manifest
+    pub fn stale(&mut self, input: bool) -> &mut Self {
+        self.stale = input;
+        self
+    }
+
+    /// Report what would be removed, and its size, without removing anything
+    pub fn dry_run(&mut self, input: bool) -> &mut Self {
+        self.dry_run = input;
+        self
+    }
+
+    /// Set verbose
+    pub fn verbose(&mut self, input: bool) -> &mut Self {
+        self.verbose = input;
+        self
+    }
+
+    /// Update the package root, which is where we look for the manifest and private
+    /// directory. By default, we look in the current working directory.
+    pub fn package_root<I>(&mut self, input: Option<I>) -> &mut Self
+    where
+        I: Into<PathBuf>,
+    {
+        self.package_root = input.map(|p| p.into());
+        self
+    }
+
+    /// New up a Clean whose host platform is looked up in the supplied registry,
+    /// rather than the builtin one.
+    pub fn with_platform_registry(platform_registry: PlatformRegistry) -> Self {
+        Self {
+            platform_registry,
+            ..Self::default()
+        }
+    }
+
+    /// Terminate a chain of calls with a build to return an owned instance.
+    pub fn build(&mut self) -> Self {
+        let mut default = Self::default();
+        std::mem::swap(self, &mut default);
+        default
+    }
+}
+
+//
+// Private methods
+//
+impl Clean {
+    // the directories being cleaned. Neither flag selects both.
+    fn get_dirs(&self, build_env: &BuildEnv) -> Vec<PathBuf> {
+        let both = !self.build && !self.dist;
+        let mut dirs = Vec::new();
+        if self.build || both {
+            dirs.push(build_env.build_dir.clone());
+        }
+        if self.dist || both {
+            dirs.push(build_env.dist_dir.clone());
+        }
+        dirs
+    }
+
+    // retreive the package root directory
+    fn get_package_root(&self) -> &Path {
+        self.package_root
+            .as_deref()
+            .unwrap_or_else(|| Path::new("."))
+    }
+}
+
+// The artifacts (and cache keys) in dir which belong to this version of the package,
+// but to a flavor no longer in the manifest.
+fn stale_artifacts(dir: &Path, manifest_info: &ManifestInfo) -> Result<Vec<PathBuf>, AnyError> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let prefix = format!("{}-{}", manifest_info.name(), manifest_info.version());
+    let current = manifest_info
+        .flavors
+        .iter()
+        .map(|f| manifest_info.package_id(f))
+        .collect::<Vec<_>>();
+    let key_suffix = format!(".{}", KEY_EXTENSION);
+    let mut stale = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        let id = name.strip_suffix(&key_suffix).unwrap_or(&name);
+        let ours = id == prefix || id.starts_with(&format!("{}_", prefix));
+        if ours && !current.iter().any(|c| c == id) {
+            stale.push(path);
+        }
+    }
+    stale.sort();
+    Ok(stale)
+}
+
+// Refuse to remove anything which does not resolve to a path within private_dir
+fn verify_within(private_dir: &Path, path: &Path) -> Result<(), PkMakeError> {
+    let refuse = || PkMakeError::UnsafeClean(path.display().to_string());
+    let private_dir = std::fs::canonicalize(private_dir).map_err(|_| refuse())?;
+    // resolve the parent, so that a symlink is judged by where it lives
+    let parent = path
+        .parent()
+        .and_then(|p| std::fs::canonicalize(p).ok())
+        .ok_or_else(refuse)?;
+    let resolved = match path.file_name() {
+        Some(name) => parent.join(name),
+        None => return Err(refuse()),
+    };
+    if resolved == private_dir || !resolved.starts_with(&private_dir) {
+        return Err(refuse());
+    }
+    Ok(())
+}
+
+// The number of bytes used by path, without following symlinks
+fn disk_usage(path: &Path) -> Result<u64, AnyError> {
+    let metadata = path.symlink_metadata()?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+    let mut total = 0;
+    for entry in std::fs::read_dir(path)? {
+        total += disk_usage(&entry?.path())?;
+    }
+    Ok(total)
+}
+
+// Remove a file, symlink or directory tree
+fn remove(path: &Path) -> Result<(), AnyError> {
+    let metadata = path.symlink_metadata()?;
+    if metadata.is_dir() {
+        std::fs::remove_dir_all(path)?;
+    } else {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+// A size in bytes, in the largest binary unit under 1024
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+//
+// Tabulate implementation
+//
+impl Tabulate for Clean {
+    fn create_table(&self) -> Table {
+        let mut table = Table::new();
+        table.add_row(row!["Field", "Value"]);
+        table.add_row(row!["build", self.build]);
+        table.add_row(row!["dist", self.dist]);
+        table.add_row(row![
+            "flavors",
+            self.flavors
+                .as_ref()
+                .map(|v| v.iter().map(|x| x.as_str()).collect::<Vec<_>>().join("\n"))
+                .unwrap_or_else(|| "None".to_string())
+        ]);
+        table.add_row(row!["stale", self.stale]);
+        table.add_row(row!["dry_run", self.dry_run]);
+        table.add_row(row!["verbose", self.verbose]);
+        table
+    }
+}
+
+//
+// Import Tests
+//
+#[cfg(test)]
+#[path = "./clean_test.rs"]
+mod clean_test;
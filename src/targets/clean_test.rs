@@ -0,0 +1,193 @@
+use super::*;
+use serial_test::serial;
+use std::env;
+
+// a package with vanilla and bar flavors, with build output for them and for a
+// flavor, baz, which has since been removed from the manifest
+fn setup_package() -> (tempfile::TempDir, PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let root = std::fs::canonicalize(dir.path()).unwrap();
+    std::fs::write(
+        root.join("manifest.yaml"),
+        "name: foo\nversion: 1.0.0\nflavours:\n  - name: ^\n  - name: bar\n",
+    )
+    .unwrap();
+    env::set_var("DD_OS", "cent7_64");
+    for dir in &["build", "dist"] {
+        for id in &["foo-1.0.0", "foo-1.0.0_bar", "foo-1.0.0_baz", "foo-0.9.0_baz"] {
+            let artifact = root.join("private").join(dir).join(id);
+            std::fs::create_dir_all(&artifact).unwrap();
+            std::fs::write(artifact.join("payload"), "0123456789").unwrap();
+        }
+    }
+    let dist = root.join("private").join("dist");
+    std::fs::write(dist.join("foo-1.0.0_bar.cachekey"), "abc\n").unwrap();
+    std::fs::write(dist.join("foo-1.0.0_baz.cachekey"), "abc\n").unwrap();
+    (dir, root)
+}
+
+#[test]
+fn can_modify_and_build() {
+    let result = Clean::default()
+        .build_dir(true)
+        .flavors(Some(vec!["^", "bar"]))
+        .unwrap()
+        .stale(true)
+        .dry_run(true)
+        .verbose(true)
+        .package_root(Some("./foo"))
+        .build();
+    let mut flavors = HashSet::new();
+    flavors.insert(Flavor::Vanilla);
+    flavors.insert(Flavor::Named("bar".to_string()));
+    let expected = Clean {
+        build: true,
+        dist: false,
+        flavors: Some(flavors),
+        stale: true,
+        dry_run: true,
+        verbose: true,
+        package_root: Some(PathBuf::from("./foo")),
+        platform_registry: PlatformRegistry::builtin(),
+    };
+    assert_eq!(result, expected);
+}
+
+#[test]
+#[serial]
+fn plan_given_no_options_is_build_and_dist_dirs() {
+    let (_dir, root) = setup_package();
+    let private = root.join("private");
+    let plan = Clean::default().package_root(Some(&root)).build().plan().unwrap();
+    assert_eq!(plan, vec![private.join("build"), private.join("dist")]);
+
+    let plan = Clean::default()
+        .dist_dir(true)
+        .package_root(Some(&root))
+        .build()
+        .plan()
+        .unwrap();
+    assert_eq!(plan, vec![private.join("dist")]);
+}
+
+#[test]
+#[serial]
+fn plan_given_flavors_is_their_artifacts_and_cache_keys() {
+    let (_dir, root) = setup_package();
+    let private = root.join("private");
+    let plan = Clean::default()
+        .flavors(Some(vec!["bar"]))
+        .unwrap()
+        .package_root(Some(&root))
+        .build()
+        .plan()
+        .unwrap();
+    assert_eq!(
+        plan,
+        vec![
+            private.join("build").join("foo-1.0.0_bar"),
+            private.join("dist").join("foo-1.0.0_bar.cachekey"),
+            private.join("dist").join("foo-1.0.0_bar"),
+        ]
+    );
+}
+
+#[test]
+#[serial]
+fn plan_given_stale_is_flavors_missing_from_the_manifest() {
+    let (_dir, root) = setup_package();
+    let dist = root.join("private").join("dist");
+    let plan = Clean::default()
+        .dist_dir(true)
+        .stale(true)
+        .package_root(Some(&root))
+        .build()
+        .plan()
+        .unwrap();
+    assert_eq!(
+        plan,
+        vec![dist.join("foo-1.0.0_baz"), dist.join("foo-1.0.0_baz.cachekey")]
+    );
+}
+
+#[test]
+#[serial]
+fn plan_given_symlink_out_of_private_dir_errors() {
+    let (_dir, root) = setup_package();
+    let outside = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(outside.path().join("foo-1.0.0_bar")).unwrap();
+    let private = root.join("private");
+    std::fs::remove_dir_all(private.join("build")).unwrap();
+    std::os::unix::fs::symlink(outside.path(), private.join("build")).unwrap();
+    let result = Clean::default()
+        .build_dir(true)
+        .flavors(Some(vec!["bar"]))
+        .unwrap()
+        .package_root(Some(&root))
+        .build()
+        .plan();
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("Refusing to clean"), "{}", err);
+    assert!(outside.path().join("foo-1.0.0_bar").is_dir());
+}
+
+#[test]
+#[serial]
+fn doit_given_dry_run_removes_nothing() {
+    let (_dir, root) = setup_package();
+    Clean::default()
+        .dry_run(true)
+        .package_root(Some(&root))
+        .build()
+        .doit()
+        .unwrap();
+    assert!(root.join("private").join("dist").join("foo-1.0.0").is_dir());
+}
+
+#[test]
+#[serial]
+fn doit_removes_the_plan() {
+    let (_dir, root) = setup_package();
+    let dist = root.join("private").join("dist");
+    Clean::default()
+        .flavors(Some(vec!["^"]))
+        .unwrap()
+        .stale(true)
+        .package_root(Some(&root))
+        .build()
+        .doit()
+        .unwrap();
+    assert!(!dist.join("foo-1.0.0").exists());
+    assert!(!dist.join("foo-1.0.0_baz").exists());
+    assert!(!dist.join("foo-1.0.0_baz.cachekey").exists());
+    assert!(dist.join("foo-1.0.0_bar").is_dir());
+    assert!(dist.join("foo-0.9.0_baz").is_dir());
+    assert!(root.join("manifest.yaml").is_file());
+}
+
+#[test]
+fn verify_within_given_private_dir_itself_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let private = dir.path().join("private");
+    std::fs::create_dir_all(private.join("dist")).unwrap();
+    assert!(verify_within(&private, &private.join("dist")).is_ok());
+    assert!(verify_within(&private, &private).is_err());
+    assert!(verify_within(&private, dir.path()).is_err());
+}
+
+#[test]
+fn disk_usage_sums_files() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("a")).unwrap();
+    std::fs::write(dir.path().join("a").join("b"), "12345").unwrap();
+    std::fs::write(dir.path().join("c"), "123").unwrap();
+    assert_eq!(disk_usage(dir.path()).unwrap(), 8);
+}
+
+#[test]
+fn human_size_uses_binary_units() {
+    assert_eq!(human_size(0), "0 B");
+    assert_eq!(human_size(1023), "1023 B");
+    assert_eq!(human_size(1536), "1.5 KiB");
+    assert_eq!(human_size(5 * 1024 * 1024 * 1024), "5.0 GiB");
+}
@@ -6,6 +6,7 @@
 use crate::BuildEnv;
 use crate::Flavor;
 use crate::Platform;
+use crate::PlatformRegistry;
 use crate::traits::Doit;
 use crate::traits::Tabulate;
 use crate::utils::exec_cmd;
@@ -31,6 +32,7 @@ pub struct Docs {
     pub platforms: Option<HashSet<Platform>>,
     pub flavors: Option<HashSet<Flavor>>,
     pub package_root: Option<PathBuf>,
+    pub platform_registry: PlatformRegistry,
 }
 /// Implement the trait responsible for calculating the appropriate pk make commands
 /// and executing them in a subshell.
@@ -63,7 +65,7 @@ impl Doit for Docs {
     /// Generate a vector of strings representing one or more commands to be 
     /// executed in a subshell. 
     fn build_cmd(&mut self) -> Result<Vec<String>, Self::Err> {
-        let build_env = BuildEnv::new(self.get_package_root())?;
+        let build_env = BuildEnv::with_platforms(self.get_package_root(), &self.platform_registry)?;
 
         let dist_dir_str = self.dist_dir_str(&build_env)?;
         let defines_str = self.get_defines_str();
@@ -171,6 +173,7 @@ impl std::default::Default for Docs {
             platforms: None,
             flavors: None,
             package_root: None,
+            platform_registry: PlatformRegistry::builtin(),
         }
     }
 }
@@ -255,34 +258,15 @@ impl Docs {
     /// ```
     pub fn platform<I>(&mut self, value: Option<I>) -> Result<&mut Self, AnyError>
     where
-        I: TryInto<Platform> + std::fmt::Debug + Clone,
+        I: AsRef<str>,
     {
         match value {
-            Some(val) => match self.platforms {
-                Some(ref mut platforms) => {
-                    let val_cpy = val.clone();
-                    //platforms.insert(val.into());
-                    match val.try_into() {
-                        Ok(v) => platforms.insert(v),
-                        Err(_) => {
-                            return Err(anyhow!("Error converting {:?} into Platform", val_cpy))
-                        }
-                    };
-                }
-                None => {
-                    let val_cpy = val.clone();
-                    let mut hset = HashSet::new();
-                    //hset.insert(val.into());
-                    //
-                    match val.try_into() {
-                        Ok(v) => hset.insert(v),
-                        Err(_) => {
-                            return Err(anyhow!("Error converting {:?} into Platform", val_cpy))
-                        }
-                    };
-                    self.platforms = Some(hset);
-                }
-            },
+            Some(val) => {
+                let platform = self.platform_registry.parse(val.as_ref())?;
+                self.platforms
+                    .get_or_insert_with(HashSet::new)
+                    .insert(platform);
+            }
             None => self.platforms = None,
         }
         Ok(self)
@@ -303,15 +287,17 @@ impl Docs {
     /// ```
     pub fn platforms<I>(&mut self, value: Option<Vec<I>>) -> Result<&mut Self, AnyError>
     where
-        I: TryInto<Platform>,
+        I: AsRef<str>,
     {
         match value {
             None => self.platforms = None,
             Some(plats) => {
-                let plats: Result<Vec<_>, _> =
-                    plats.into_iter().map(|i_val| i_val.try_into()).collect();
+                let plats: Result<Vec<_>, _> = plats
+                    .iter()
+                    .map(|i_val| self.platform_registry.parse(i_val.as_ref()))
+                    .collect();
                 match plats {
-                    Err(_) => return Err(anyhow!("failed to convert to platform")),
+                    Err(e) => return Err(anyhow!("failed to convert to platform: {}", e)),
                     Ok(val) => match self.platforms {
                         Some(ref mut platforms) => {
                             for v in val {
@@ -433,6 +419,15 @@ impl Docs {
         Ok(self)
     }
 
+    /// New up a Docs whose platforms, including the host platform, are looked up
+    /// in the supplied registry, rather than the builtin one.
+    pub fn with_platform_registry(platform_registry: PlatformRegistry) -> Self {
+        Self {
+            platform_registry,
+            ..Self::default()
+        }
+    }
+
     /// Terminate a chain of calls with a build to return an owned instance.
     ///
     /// # Example
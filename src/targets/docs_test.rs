@@ -15,6 +15,7 @@ fn can_build_default() {
         flavors: None,
         platforms: None,
         package_root: None,
+        platform_registry: PlatformRegistry::builtin(),
     };
     assert_eq!(result, expected);
 }
@@ -35,8 +36,8 @@ fn can_update_build() -> Result<(), Box<dyn std::error::Error>> {
         flavor_hs.insert(Flavor::Vanilla);
 
         let mut platform_hs = HashSet::new();
-        platform_hs.insert(Platform::Cent6_64);
-        platform_hs.insert(Platform::Cent7_64);
+        platform_hs.insert(Platform::from("cent6_64").unwrap());
+        platform_hs.insert(Platform::from("cent7_64").unwrap());
          
     let expected = Docs {
         dist_dir: Some("foo/bar".to_string()),
@@ -46,6 +47,7 @@ fn can_update_build() -> Result<(), Box<dyn std::error::Error>> {
         flavors: Some(flavor_hs),
         platforms: Some(platform_hs),
         package_root: Some(std::path::PathBuf::from("./foo/bar")),
+        platform_registry: PlatformRegistry::builtin(),
     };
     assert_eq!(result, expected);
     Ok(())
@@ -5,15 +5,28 @@
 //! responsible for execution.
 
 // Internal crate imports
+use crate::authorization::{self, AuthRule, AuthSubject, UserGroups};
+use crate::build_cache::{BuildInputs, CachePlan};
 use crate::BuildEnv;
 use crate::context::Context;
+use crate::dist::verify_artifacts;
 use crate::flavor::Flavor;
+use crate::history::{self, HistoryRecord, Journal};
+use crate::hooks::{HookContext, HookPoint, Hooks};
+use crate::level::Level;
 use crate::ManifestInfo;
 use crate::OverridePair;
-use crate::platform::Platform;
+use crate::platform::{verify_buildable, Platform, PlatformRegistry};
+use crate::policy::{self, Policy, PolicyProbe, PolicyRequirement, PolicySubject, Violation};
+use crate::named_site::SiteRegistry;
+use crate::show_pattern::ShowPattern;
+use crate::show_registry::ShowRegistry;
 use crate::site::Site;
+use crate::staging::{StagedPackage, StagingIndex};
+use crate::targets::Test;
 use crate::traits::{Doit, Tabulate};
 use crate::utils::exec_cmd;
+use crate::PkMakeError;
 use crate::Vcs;
 
 // External crate imports
@@ -26,6 +39,8 @@ use indexmap::IndexSet as HashSet;
 use prettytable::{row, Table};
 use std::convert::TryInto;
 use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+use subprocess::ExitStatus;
 
 const DEFAULT_CONTEXT: Context = Context::User;
 
@@ -35,22 +50,42 @@ enum PkPhase {
     Build,
     Install
 }
+
+// The commands of an install: the build step, if any, and the install steps which
+// follow it once the build has succeeded. Only pk installs (and staging) consume
+// the dist artifacts; a facility install tags the vcs instead.
+#[derive(Debug, PartialEq, Eq, Default)]
+struct InstallSteps {
+    build: Vec<String>,
+    install: Vec<String>,
+    uses_dist: bool,
+    // the cache keys to record once the build step succeeds
+    cache: Option<CachePlan>,
+    // whether a policy requires the tests to pass before anything is run
+    tests_required: bool,
+}
+
 #[derive(Debug, PartialEq, Eq)]
 /// Models user install request state, and implements traits necessary to execute 
 /// the request
 pub struct Install {
     pub clean: bool,
+    pub rebuild: bool,
     pub dry_run: bool,
+    pub preview: bool,
+    pub no_build: bool,
     pub with_docs: bool,
     pub build_dir: Option<String>,
     pub context: Option<Context>,
     pub show: Option<String>,
+    pub allow_unknown_show: bool,
     pub sites: Option<HashSet<Site>>,
+    pub requested_sites: Vec<String>,
     pub platforms: Option<HashSet<Platform>>,
     pub flavors: Option<HashSet<Flavor>>,
     pub verbose: bool,
     pub dist_dir: Option<String>,
-    pub level: Option<String>,
+    pub level: Option<Level>,
     pub overrides: Option<Vec<OverridePair>>,
     pub defines: Option<Vec<String>>,
     pub work: bool,
@@ -58,6 +93,19 @@ pub struct Install {
     pub logfile: Option<PathBuf>,
     pub max_jobs: Option<u8>,
     pub package_root: Option<PathBuf>,
+    pub to_dir: Option<PathBuf>,
+    pub journal: Option<PathBuf>,
+    pub hooks: Hooks,
+    pub policies: Vec<Policy>,
+    pub override_policies: Vec<String>,
+    pub authorization: Vec<AuthRule>,
+    /// The user and groups authorization rules are checked against. None looks
+    /// them up via `id`.
+    pub user_groups: Option<UserGroups>,
+    pub site_registry: SiteRegistry,
+    pub platform_registry: PlatformRegistry,
+    pub show_patterns: Vec<ShowPattern>,
+    pub show_registry: Option<ShowRegistry>,
 }
 
 //
@@ -68,45 +116,50 @@ impl Doit for Install {
     type Err = AnyError;
     /// doit executes the install target command
     fn doit(&mut self) -> Result<(), Self::Err> {
-        if self.verbose {
-            //println!("{:#?}", self);
-            self.tabulate();
-        }
-        let cmd = self.build_cmd()?;
-        if self.dry_run {
-            for c in cmd {
-                println!("{}", c);
-            }
-        } else {
-            if self.verbose {
-                for c in &cmd {
-                    println!("{}", c);
-                }
-            }
-            let cmd = cmd.join(" ; ");
-
-            let exit_status = exec_cmd(cmd.as_str(), self.get_package_root())?;
-            println!("\nExit Status: {:?}", exit_status);
-        }
-        Ok(())
+        self.execute().map(|_| ())
     }
     /// construct the command which will be executed
     fn build_cmd(&mut self) -> Result<Vec<String>, Self::Err> {
-        let build_env = BuildEnv::new(self.get_package_root())?;
+        let steps = self.steps()?;
+        Ok(steps.build.into_iter().chain(steps.install).collect())
+    }
+}
+
+//
+// Private Helper Methods for Install::build_cmd(...)
+//
+impl Install {
+    // construct the build and install steps which build_cmd joins
+    fn steps(&mut self) -> Result<InstallSteps, AnyError> {
+        self.resolve_sites()?;
+        let build_env = self.get_build_env()?;
         // if build_env.vcs.is_none() {
         //     return Err(anyhow!("Unable to identify vcs at package root: {:?}", self.get_package_root()));
         // }
-        self.reconcile_context_and_level(&build_env)?;
+        // staging to a directory does not involve a level. The tests which a policy
+        // requires are run by run, rather than as a side effect of building commands
+        let tests_required = if self.to_dir.is_some() {
+            if self.level.is_some() || self.context.is_some() || self.show.is_some() {
+                return Err(anyhow!(
+                    "--to-dir stages the dist without installing to a level. It may not be combined with a level, context or show"
+                ));
+            }
+            false
+        } else {
+            self.reconcile_context_and_level(&build_env)?;
+            self.authorize()?;
+            self.enforce_policies(&build_env)?
+        };
         // bail out early if we are installing to facility, as we are simply calling
-        if self.get_context() == &Context::Facility {
-            return match build_env.vcs {
-                Some(Vcs::Git) => return Ok(vec!["git-tag create --protect".into()]),
-                Some(Vcs::Svn) => return Ok(vec!["svn-tag create".into()]),
+        if self.to_dir.is_none() && self.get_context() == &Context::Facility {
+            let steps = match build_env.vcs {
+                Some(Vcs::Git) => Ok(tag_step("git-tag create --protect")),
+                Some(Vcs::Svn) => Ok(tag_step("svn-tag create")),
                 Some(Vcs::Both) => {
                     if let Some(ref vcs) = self.vcs {
                         match vcs {
-                            Vcs::Git => Ok(vec!["git-tag create --protect".into()]),
-                            Vcs::Svn => Ok(vec!["svn-tag create".into()]),
+                            Vcs::Git => Ok(tag_step("git-tag create --protect")),
+                            Vcs::Svn => Ok(tag_step("svn-tag create")),
                             _ => Err(anyhow!(
                                 "Vcs system provided by user unrecognized: '{}'",
                                 vcs
@@ -120,6 +173,10 @@ impl Doit for Install {
                 }
                 _ => Err(anyhow!("Unrecognized vcs for context: {} and build_env.vcs: {:?}",self.get_context().as_str(), build_env.vcs)),
             };
+            return steps.map(|steps| InstallSteps {
+                tests_required,
+                ..steps
+            });
         }
 
         let clean_str = if self.clean { " --clean" } else { "" };
@@ -144,28 +201,47 @@ impl Doit for Install {
         let build_dir_str = self.get_build_dir_str()?;
         let verbose_str = if self.verbose {" --verbose"} else {""};
         // we have to build an install command for every target
-        let mut result = vec![format!(
-            "pk audit && pk build{}{}{}{}{}{}{}{}{}{}",
-            clean_str,
-            dist_dir_str,
-            docs_str,
-            flavor_str,
-            overrides_str,
-            platform_str,
-            defines_str,
-            work_str,
-            build_dir_str,
-            verbose_str,
-        )];
-        self.update_results_with_install(&mut result, &build_env)?;
-        Ok(result)
+        let mut steps = InstallSteps {
+            uses_dist: true,
+            tests_required,
+            ..InstallSteps::default()
+        };
+        if !self.skip_build() {
+            if let Some(ref platforms) = self.platforms {
+                verify_buildable(platforms)?;
+            }
+            let plan = self.cache_plan(&build_env)?;
+            if plan.is_current() {
+                if self.dry_run || self.verbose {
+                    println!(
+                        "Skipping pk build: every flavor's cache key is unchanged. Use --rebuild to force a build"
+                    );
+                }
+            } else {
+                let flavor_str = plan.flavor_str().unwrap_or(flavor_str);
+                steps.build.push(format!(
+                    "pk audit && pk build{}{}{}{}{}{}{}{}{}{}",
+                    clean_str,
+                    dist_dir_str,
+                    docs_str,
+                    flavor_str,
+                    overrides_str,
+                    platform_str,
+                    defines_str,
+                    work_str,
+                    build_dir_str,
+                    verbose_str,
+                ));
+                steps.cache = Some(plan);
+            }
+        }
+        // when staging, the dist is copied by doit instead of installed
+        if self.to_dir.is_none() {
+            self.update_results_with_install(&mut steps.install, &build_env)?;
+        }
+        Ok(steps)
     }
-}
 
-//
-// Private Helper Methods for Install::build_cmd(...)
-//
-impl Install {
     // context/show and level are both responsible for setting execution level. Context and
     // show are vestiges of the build system everyone is familiar with. Level is the pk native
     // replacement for them. This function reconciles one with the other, and errors if they
@@ -187,9 +263,12 @@ impl Install {
         // At this point, if the level has been set, we can be certain that the show and context have not been set.
         // We check to see if the level is "faciilty" and update the context if it is so. Then we return early.
         if let Some(level) = self.level.as_ref() {
-            if level.to_lowercase().as_str() == "facility" {
+            if level.is_facility() {
                 self.context = Some(Context::Facility);
             }
+            if let Some(show) = level.show() {
+                self.verify_show(show)?;
+            }
             return Ok(());
         }
         // At this point, we know that level has not been set. We need to update the level, based on the context
@@ -200,10 +279,11 @@ impl Install {
             Some(context) => context,
             None => &Context::User,
         };
-        // At this point, there is nothing more to be done if the context is Facility, since
-        // this fact is used elsewhere to short circuit additional work based on the level.
-        // So, we can simply return early.
+        // At this point, if the context is Facility, the level is too, regardless of the
+        // show. It is recorded so that the journal and hooks report the facility level
+        // just as they do when it is requested via --level. We can then return early.
         if context == &Context::Facility {
+            self.level = Some(Level::Facility);
             return Ok(());
         }
         // At this point, we know that level has not been set, and the context is NOT Facility. Now
@@ -215,30 +295,149 @@ impl Install {
         let show = if let Some(show) = self.show.as_ref() {
             show
         } else {
-            match build_env.dd_show {
-                Some(ref show) => show,
-                None => {
+            match (&build_env.dd_show, &build_env.dd_show_source) {
+                (Some(show), Some(source)) => {
+                    if self.verbose {
+                        println!("Using show '{}' from {}", show, source);
+                    }
+                    show
+                }
+                _ => {
                     return Err(anyhow!(
-                        "Show has not been set explicitly, and DD_SHOW is not set"
+                        "Show has not been set explicitly, DD_SHOW is not set, and no show pattern matches the current directory"
                     ))
                 }
             }
         };
-        // If the show is facility, again we special case it, and set the context to facility
-        if show.to_lowercase().as_str() == "facility" {
+        // lastly, we update the level depending upon the context. If the show is facility,
+        // again we special case it, and set the context to facility
+        let level = Level::from_context(context, show)?;
+        if level.is_facility() {
             self.context = Some(Context::Facility);
+        } else {
+            self.verify_show(show)?;
+        }
+        self.level = Some(level);
+        Ok(())
+    }
+
+    // The context implied by the reconciled level
+    fn resolved_context(&self) -> Context {
+        match self.level {
+            Some(ref level) => level.context(),
+            None => self.get_context().clone(),
+        }
+    }
+
+    // The sites which authorization rules and policies are matched against, which
+    // default to the local site. These retain `all` and any groups as requested.
+    // See Site::as_requested.
+    pub(crate) fn sites_as_requested(&self) -> Vec<Site> {
+        match self.sites {
+            Some(ref sites) => Site::as_requested(
+                self.requested_sites
+                    .iter()
+                    .filter_map(|s| Site::parse(s, &self.site_registry).ok()),
+                sites,
+                &self.site_registry,
+            ),
+            None => vec![Site::Local],
+        }
+    }
+
+    // Verify that the current user belongs to the groups which the authorization
+    // rules require for the reconciled level and sites
+    fn authorize(&self) -> Result<(), AnyError> {
+        if self.authorization.is_empty() {
             return Ok(());
         }
-        // lastly, we update the level depending upon the context. We know that
-        // we are at a show at this point...
-        if context == &Context::Shared {
-            self.level = Some(show.clone());
-        } else {
-            self.level = Some(format!("{}.work", show));
+        let subject = AuthSubject {
+            context: self.resolved_context(),
+            level: self.level.clone(),
+            sites: self.sites_as_requested(),
+        };
+        let user = match self.user_groups {
+            Some(ref user) => user.clone(),
+            None => UserGroups::current()?,
+        };
+        authorization::authorize(&self.authorization, &subject, &user)?;
+        Ok(())
+    }
+
+    // Check the resolved install against the configured policies, before any
+    // commands are built. Violations which have been overridden are reported as
+    // warnings. Running the tests is deferred to enforce_tests, so a tests_pass
+    // requirement holds here. Returns whether one applies.
+    fn enforce_policies(&self, build_env: &BuildEnv) -> Result<bool, AnyError> {
+        if self.policies.is_empty() && self.override_policies.is_empty() {
+            return Ok(false);
         }
+        let mut probe = InstallProbe::new(self, build_env, true);
+        let waived = policy::enforce(
+            &self.policies,
+            &self.policy_subject(build_env),
+            &mut probe,
+            &self.override_policies,
+        )?;
+        warn_waived(&waived);
+        Ok(probe.tests_deferred)
+    }
+
+    // Run the tests which enforce_policies deferred, and enforce the tests_pass
+    // requirement of the policies which apply. Their other requirements have
+    // already been enforced.
+    fn enforce_tests(&self, build_env: &BuildEnv) -> Result<(), AnyError> {
+        let policies = self
+            .policies
+            .iter()
+            .filter(|p| p.require.tests_pass)
+            .map(|p| Policy {
+                require: PolicyRequirement {
+                    tests_pass: true,
+                    ..PolicyRequirement::default()
+                },
+                ..p.clone()
+            })
+            .collect::<Vec<_>>();
+        let overrides = self
+            .override_policies
+            .iter()
+            .filter(|id| policies.iter().any(|p| &p.id == *id))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut probe = InstallProbe::new(self, build_env, false);
+        let waived = policy::enforce(
+            &policies,
+            &self.policy_subject(build_env),
+            &mut probe,
+            &overrides,
+        )?;
+        warn_waived(&waived);
         Ok(())
     }
 
+    // The resolved install, as policies see it
+    fn policy_subject(&self, build_env: &BuildEnv) -> PolicySubject {
+        PolicySubject {
+            context: self.resolved_context(),
+            sites: self.sites_as_requested(),
+            platforms: match self.platforms {
+                Some(ref platforms) => platforms.iter().map(|p| p.as_str().to_string()).collect(),
+                None => vec![build_env.dd_os.as_str().to_string()],
+            },
+            work: self.work,
+        }
+    }
+
+    // verify the show against the ShowRegistry, if there is one, unless unknown
+    // shows have been explicitly allowed.
+    fn verify_show(&self, show: &str) -> Result<(), PkMakeError> {
+        match self.show_registry {
+            Some(ref registry) if !self.allow_unknown_show => registry.verify(show),
+            _ => Ok(()),
+        }
+    }
+
     // build up the string representing the define flag invocation.
     fn get_defines_str(&self) -> String {
         // NB: The --define flag works differently in pk build in that it
@@ -416,6 +615,319 @@ impl Install {
         //&std::path::Path::new(".")
     }
 
+    // resolve the BuildEnv of the package root, via the configured registries
+    fn get_build_env(&self) -> Result<BuildEnv, AnyError> {
+        BuildEnv::with_registries(
+            self.get_package_root(),
+            &self.platform_registry,
+            &self.show_patterns,
+        )
+    }
+
+    /// Execute the install as doit does, returning the exit status of the first pk
+    /// command which failed, or of the last which ran, if any.
+    pub fn execute(&mut self) -> Result<Option<ExitStatus>, AnyError> {
+        self.resolve_sites()?;
+        if self.verbose {
+            //println!("{:#?}", self);
+            self.tabulate();
+        }
+        if (self.dry_run || self.verbose) && self.sites_expanded() {
+            println!(
+                "Expanded sites {} to {}",
+                self.requested_sites.join(" "),
+                self.get_site_str().trim_start().trim_start_matches("--site=")
+            );
+        }
+        if self.preview {
+            let preview = self.resolve_preview()?;
+            preview.tabulate();
+            let unsupported = preview.unsupported().count();
+            if unsupported > 0 {
+                eprintln!(
+                    "Warning: {} of {} destinations use a platform which the manifest's os list ({}) does not support",
+                    unsupported,
+                    preview.entries.len(),
+                    preview.os.join(", ")
+                );
+            }
+            return Ok(None);
+        }
+        let started = SystemTime::now();
+        let clock = Instant::now();
+        let result = self.run();
+        // dry runs neither trigger the on-failure hooks nor are recorded in the
+        // history journal
+        if !self.dry_run {
+            self.run_failure_hooks(&result);
+            self.record_history(started, clock.elapsed(), &result);
+        }
+        result
+    }
+
+    // Build (unless skipped), verify the dist artifacts, then install or stage them.
+    // Returns the exit status of the first pk command which failed, or of the last
+    // which ran, if any.
+    fn run(&mut self) -> Result<Option<ExitStatus>, AnyError> {
+        let InstallSteps {
+            build,
+            install,
+            uses_dist,
+            mut cache,
+            tests_required,
+        } = self.steps()?;
+        if tests_required {
+            self.enforce_tests(&self.get_build_env()?)?;
+        }
+        let installing = !install.is_empty() || self.to_dir.is_some();
+        let context = self.hook_context()?;
+        let mut status = None;
+        if self.dry_run {
+            if !build.is_empty() {
+                self.hooks.run(HookPoint::PreBuild, &context, true, self.verbose)?;
+                for c in &build {
+                    println!("{}", c);
+                }
+                self.hooks.run(HookPoint::PostBuild, &context, true, self.verbose)?;
+            }
+            if installing {
+                self.hooks.run(HookPoint::PreInstall, &context, true, self.verbose)?;
+            }
+            for c in &install {
+                println!("{}", c);
+            }
+            if self.to_dir.is_some() {
+                for package in self.staging_plan()? {
+                    println!(
+                        "stage {} to {}",
+                        package.source.display(),
+                        package.destination.display()
+                    );
+                }
+            }
+            if installing {
+                self.hooks.run(HookPoint::PostInstall, &context, true, self.verbose)?;
+            }
+            // without a build step, the artifacts should already be in place
+            if build.is_empty() && uses_dist {
+                if let Err(e) = self.verify_artifacts() {
+                    eprintln!("Warning: {}", e);
+                }
+            }
+        } else {
+            if self.verbose {
+                for c in build.iter().chain(install.iter()) {
+                    println!("{}", c);
+                }
+            }
+            if !build.is_empty() {
+                self.hooks.run(HookPoint::PreBuild, &context, false, self.verbose)?;
+                if let Some(ref mut plan) = cache {
+                    plan.invalidate()?;
+                }
+                let exit_status = exec_cmd(build.join(" ; ").as_str(), self.get_package_root())?;
+                println!("\nExit Status: {:?}", exit_status);
+                // an earlier build's artifacts must not be installed in place of
+                // the failed one
+                if !exit_status.success() {
+                    return Ok(Some(exit_status));
+                }
+                if let Some(ref mut plan) = cache {
+                    plan.record()?;
+                }
+                self.hooks.run(HookPoint::PostBuild, &context, false, self.verbose)?;
+                status = Some(exit_status);
+            }
+            // the dist artifacts are verified between the build step (if any) and
+            // the install steps
+            if uses_dist {
+                self.verify_artifacts()?;
+            }
+            if installing {
+                self.hooks.run(HookPoint::PreInstall, &context, false, self.verbose)?;
+            }
+            let mut installed = true;
+            if !install.is_empty() {
+                let exit_status =
+                    exec_cmd(install.join(" ; ").as_str(), self.get_package_root())?;
+                println!("\nExit Status: {:?}", exit_status);
+                installed = exit_status.success();
+                status = Some(exit_status);
+            }
+            if self.to_dir.is_some() {
+                self.stage()?;
+            }
+            if installing && installed {
+                self.hooks.run(HookPoint::PostInstall, &context, false, self.verbose)?;
+            }
+        }
+        Ok(status)
+    }
+
+    // The context supplied to the hooks. Only resolved when hooks are configured.
+    fn hook_context(&self) -> Result<HookContext, AnyError> {
+        if self.hooks.is_empty() {
+            return Ok(HookContext::default());
+        }
+        let build_env = self.get_build_env()?;
+        HookContext::new(
+            &build_env,
+            self.dist_dir.as_deref(),
+            self.flavors.as_ref(),
+            self.level.as_ref(),
+        )
+    }
+
+    // Run the on-failure hooks if the install failed, or any pk command exited
+    // unsuccessfully
+    fn run_failure_hooks(&self, result: &Result<Option<ExitStatus>, AnyError>) {
+        if self.hooks.on_failure.is_empty() {
+            return;
+        }
+        let error = match result {
+            Ok(Some(status)) if !status.success() => format!("{:?}", status),
+            Ok(_) => return,
+            Err(e) => e.to_string(),
+        };
+        let context = self.hook_context().unwrap_or_else(|_| HookContext {
+            package_root: self.get_package_root().to_path_buf(),
+            ..HookContext::default()
+        });
+        self.hooks.run_on_failure(&context, &error, self.verbose);
+    }
+
+    // Append a record of this install to the history journal. Failing to write the
+    // journal does not fail the install.
+    fn record_history(
+        &self,
+        started: SystemTime,
+        elapsed: Duration,
+        result: &Result<Option<ExitStatus>, AnyError>,
+    ) {
+        let journal = match self.journal {
+            Some(ref path) => Journal::new(path),
+            None => return,
+        };
+        let mut record = self.history_record();
+        record.timestamp = history::timestamp(started);
+        record.duration_ms = elapsed.as_millis() as u64;
+        match result {
+            Ok(Some(ExitStatus::Exited(code))) => record.exit_status = Some(*code),
+            Ok(Some(status)) => record.error = Some(format!("{:?}", status)),
+            Ok(None) => record.exit_status = Some(0),
+            Err(e) => record.error = Some(e.to_string()),
+        }
+        if let Err(e) = journal.append(&record) {
+            eprintln!(
+                "Warning: Unable to record install in '{}': {}",
+                journal.path().display(),
+                e
+            );
+        }
+    }
+
+    // The package, destinations and revision being installed, for the history
+    // journal. Anything which cannot be resolved is left empty.
+    fn history_record(&self) -> HistoryRecord {
+        let mut record = HistoryRecord {
+            user: std::env::var("USER").unwrap_or_default(),
+            level: self.level.as_ref().map(|l| l.to_string()),
+            sites: match self.sites {
+                Some(ref sites) => sites.iter().map(|s| s.as_str().to_string()).collect(),
+                None if self.to_dir.is_some() => Vec::new(),
+                None => vec![Site::Local.as_str().to_string()],
+            },
+            ..HistoryRecord::default()
+        };
+        if let Some(ref flavors) = self.flavors {
+            record.flavors = flavors.iter().map(|f| f.as_str().to_string()).collect();
+        }
+        if let Some(ref platforms) = self.platforms {
+            record.platforms = platforms.iter().map(|p| p.as_str().to_string()).collect();
+        }
+        let build_env = match self.get_build_env() {
+            Ok(build_env) => build_env,
+            Err(_) => return record,
+        };
+        if record.platforms.is_empty() {
+            record.platforms = vec![build_env.dd_os.as_str().to_string()];
+        }
+        record.revision = build_env
+            .vcs
+            .as_ref()
+            .and_then(|vcs| vcs.revision(&build_env.package_root));
+        if let Ok(manifest_info) = ManifestInfo::from_path(build_env.manifest.as_ref()) {
+            record.package = manifest_info.name().to_string();
+            record.version = manifest_info.version().to_string();
+            if record.flavors.is_empty() {
+                record.flavors = manifest_info
+                    .flavors
+                    .iter()
+                    .map(|f| f.as_str().to_string())
+                    .collect();
+            }
+        }
+        record
+    }
+
+    // The package (ie name-version), and the dist artifact expected for each of the
+    // flavors being installed
+    fn get_artifacts(&self, build_env: &BuildEnv) -> Result<(String, Vec<PathBuf>), AnyError> {
+        let manifest_info = ManifestInfo::from_path(build_env.manifest.as_ref())?;
+        // pk install runs from the package root, so relative dist dirs are too
+        let dist_dir = self
+            .dist_dir
+            .as_ref()
+            .map(|d| build_env.package_root.join(d))
+            .unwrap_or_else(|| build_env.dist_dir.clone());
+        let artifacts = match self.flavors {
+            Some(ref flavors) => flavors.iter().collect::<Vec<_>>(),
+            None => manifest_info.flavors.iter().collect::<Vec<_>>(),
+        }
+        .into_iter()
+        .map(|f| dist_dir.join(manifest_info.package_id(f)))
+        .collect::<Vec<_>>();
+        let package = format!("{}-{}", manifest_info.name(), manifest_info.version());
+        Ok((package, artifacts))
+    }
+
+    // Verify that the dist artifact of every flavor being installed exists and is
+    // not empty, reporting all of those which are missing together.
+    fn verify_artifacts(&self) -> Result<(), AnyError> {
+        let build_env = self.get_build_env()?;
+        let (package, artifacts) = self.get_artifacts(&build_env)?;
+        verify_artifacts(&package, &artifacts)?;
+        Ok(())
+    }
+
+    // Plan the build step with the build cache, which pk-make build shares. Every
+    // flavor is built when clean or rebuild is requested.
+    fn cache_plan(&self, build_env: &BuildEnv) -> Result<CachePlan, AnyError> {
+        let inputs = BuildInputs {
+            dist_dir: self.dist_dir.as_deref(),
+            flavors: self.flavors.as_ref(),
+            platforms: self.platforms.as_ref(),
+            overrides: self.overrides.as_deref(),
+            defines: self.defines.as_deref(),
+            with_docs: self.with_docs,
+            work: self.work,
+        };
+        let plan = CachePlan::new(build_env, &inputs, self.clean || self.rebuild)?;
+        if self.dry_run || self.verbose {
+            plan.report();
+        }
+        Ok(plan)
+    }
+
+    // Whether the pk build step is skipped, because --no-build was requested. The
+    // reason is reported when verbose or dry_run.
+    fn skip_build(&self) -> bool {
+        if self.no_build && (self.dry_run || self.verbose) {
+            println!("Skipping pk build: --no-build was requested");
+        }
+        self.no_build
+    }
+
     // used to update the results with the installation call
     fn update_results_with_install(
         &mut self,
@@ -500,6 +1012,148 @@ impl Install {
         }
         Ok(())
     }
+    /// Generate the pk uninstall commands which remove the manifest's version of the
+    /// package from the level, sites, platforms and flavors that build_cmd installs
+    /// to. Facility installs may not be removed.
+    pub fn uninstall_cmd(&mut self) -> Result<Vec<String>, AnyError> {
+        self.resolve_sites()?;
+        let build_env = self.get_build_env()?;
+        self.reconcile_context_and_level(&build_env)?;
+        if self.get_context() == &Context::Facility {
+            return Err(anyhow!("Refusing to uninstall from the facility level"));
+        }
+        self.authorize()?;
+        let manifest_info = ManifestInfo::from_path(build_env.manifest.as_ref())?;
+        let flavors = match self.flavors {
+            Some(ref flavors) => flavors.iter().collect::<Vec<_>>(),
+            None => manifest_info.flavors.iter().collect::<Vec<_>>(),
+        };
+        let level_str = self.get_level_str();
+        let site_str = self.get_site_str();
+        let platform_str = self.get_platform_str(PkPhase::Install, &build_env);
+        let verbose_str = if self.verbose { " --verbose" } else { "" };
+        Ok(flavors
+            .into_iter()
+            .map(|flavor| {
+                format!(
+                    "pk uninstall{}{}{}{} {}",
+                    level_str,
+                    site_str,
+                    platform_str,
+                    verbose_str,
+                    manifest_info.package_id(flavor)
+                )
+            })
+            .collect())
+    }
+
+    /// The artifacts which `--to-dir` stages: one per flavor and platform, under
+    /// `<to_dir>/<name>/<version>[_flavor]/<platform>`. Platforms default to the
+    /// host platform.
+    pub fn staging_plan(&self) -> Result<Vec<StagedPackage>, AnyError> {
+        let to_dir = self
+            .to_dir
+            .as_ref()
+            .ok_or_else(|| anyhow!("The directory to stage to has not been set"))?;
+        let build_env = self.get_build_env()?;
+        let manifest_info = ManifestInfo::from_path(build_env.manifest.as_ref())?;
+        let (_, artifacts) = self.get_artifacts(&build_env)?;
+        let flavors = match self.flavors {
+            Some(ref flavors) => flavors.iter().collect::<Vec<_>>(),
+            None => manifest_info.flavors.iter().collect::<Vec<_>>(),
+        };
+        let platforms = match self.platforms {
+            Some(ref platforms) => platforms.iter().map(|p| p.as_str()).collect::<Vec<_>>(),
+            None => vec![build_env.dd_os.as_str()],
+        };
+        let mut staged = Vec::new();
+        for (flavor, artifact) in flavors.into_iter().zip(artifacts) {
+            let version = match flavor {
+                Flavor::Vanilla => manifest_info.version().to_string(),
+                _ => format!("{}_{}", manifest_info.version(), flavor.as_str()),
+            };
+            for platform in &platforms {
+                staged.push(StagedPackage {
+                    name: manifest_info.name().to_string(),
+                    version: version.clone(),
+                    platform: platform.to_string(),
+                    source: artifact.clone(),
+                    destination: to_dir
+                        .join(manifest_info.name())
+                        .join(&version)
+                        .join(platform),
+                });
+            }
+        }
+        Ok(staged)
+    }
+
+    // Copy each flavor's dist into the to_dir, and record it in the staging index
+    fn stage(&self) -> Result<(), AnyError> {
+        let to_dir = self
+            .to_dir
+            .as_ref()
+            .ok_or_else(|| anyhow!("The directory to stage to has not been set"))?;
+        let mut index = StagingIndex::load(to_dir)?;
+        for package in self.staging_plan()? {
+            if self.verbose {
+                println!(
+                    "Staging {} to {}",
+                    package.source.display(),
+                    package.destination.display()
+                );
+            }
+            package.stage()?;
+            index.add(package);
+        }
+        index.save(to_dir)
+    }
+
+    /// Resolve the level, sites, platforms and flavors as build_cmd does, and report
+    /// where each package would be installed, without building or installing anything.
+    pub fn resolve_preview(&mut self) -> Result<InstallPreview, AnyError> {
+        self.resolve_sites()?;
+        let build_env = self.get_build_env()?;
+        self.reconcile_context_and_level(&build_env)?;
+        let manifest_info = ManifestInfo::from_path(build_env.manifest.as_ref())?;
+        let level = self
+            .level
+            .as_ref()
+            .map(|l| l.to_string())
+            .unwrap_or_else(|| Level::Facility.to_string());
+        let sites = match self.sites {
+            Some(ref sites) => sites.iter().map(|s| s.as_str().to_string()).collect(),
+            None => vec![Site::Local.as_str().to_string()],
+        };
+        let platforms = match self.platforms {
+            Some(ref platforms) => platforms.iter().cloned().collect(),
+            None => vec![build_env.dd_os.clone()],
+        };
+        let flavors = match self.flavors {
+            Some(ref flavors) => flavors.iter().cloned().collect::<Vec<_>>(),
+            None => manifest_info.flavors.clone(),
+        };
+        let mut entries = Vec::new();
+        for site in &sites {
+            for platform in &platforms {
+                for flavor in &flavors {
+                    entries.push(PreviewEntry {
+                        site: site.clone(),
+                        platform: platform.clone(),
+                        flavor: flavor.clone(),
+                        package_id: manifest_info.package_id(flavor),
+                        level: level.clone(),
+                        supported: manifest_info.supports(platform),
+                    });
+                }
+            }
+        }
+        Ok(InstallPreview {
+            entries,
+            os: manifest_info.os,
+        })
+    }
+
     /// Retrieve a reference to the context
     pub fn get_context(&self) -> &Context {
         match self.context {
@@ -509,6 +1163,24 @@ impl Install {
     }
 }
 
+// Report the policy violations which --override-policy waived
+fn warn_waived(waived: &[Violation]) {
+    for violation in waived {
+        eprintln!(
+            "Warning: overriding policy [{}] {}",
+            violation.id, violation.reason
+        );
+    }
+}
+
+// the steps of a facility install, which tags the vcs rather than running pk
+fn tag_step(cmd: &str) -> InstallSteps {
+    InstallSteps {
+        install: vec![cmd.to_string()],
+        ..InstallSteps::default()
+    }
+}
+
 //
 // Implement Default trait for Install.
 //
@@ -516,12 +1188,17 @@ impl Default for Install {
     fn default() -> Self {
         Self {
             clean: false,
+            rebuild: false,
             dry_run: false,
+            preview: false,
+            no_build: false,
             with_docs: true,
             build_dir: None,
             context: None,
             show: None,
+            allow_unknown_show: false,
             sites: None,
+            requested_sites: Vec::new(),
             platforms: None,
             flavors: None,
             verbose: false,
@@ -534,6 +1211,17 @@ impl Default for Install {
             logfile: None,
             max_jobs: None,
             package_root: None,
+            to_dir: None,
+            journal: None,
+            hooks: Hooks::default(),
+            policies: Vec::new(),
+            override_policies: Vec::new(),
+            authorization: Vec::new(),
+            user_groups: None,
+            site_registry: SiteRegistry::builtin(),
+            platform_registry: PlatformRegistry::builtin(),
+            show_patterns: ShowPattern::builtin(),
+            show_registry: None,
         }
     }
 }
@@ -631,92 +1319,198 @@ impl Install {
         self
     }
 
+    /// Set the preview field. When set, doit reports where each package would be
+    /// installed instead of executing the install.
+    pub fn preview(&mut self, value: bool) -> &mut Self {
+        self.preview = value;
+        self
+    }
+
+    /// Set the no_build field. When set, the pk build step is skipped and the
+    /// existing dist artifacts are installed.
+    pub fn no_build(&mut self, value: bool) -> &mut Self {
+        self.no_build = value;
+        self
+    }
+
+    /// Set the directory to stage the dist to, instead of installing it with pk
+    /// install, and return a mutable reference to self, per the builder pattern.
+    pub fn to_dir<I>(&mut self, input: Option<I>) -> &mut Self
+    where
+        I: Into<PathBuf>,
+    {
+        self.to_dir = input.map(|dir| dir.into());
+        self
+    }
+
+    /// Set the history journal which each install (other than a dry run) is
+    /// recorded in, and return a mutable reference to self, per the builder pattern.
+    pub fn journal<I>(&mut self, input: Option<I>) -> &mut Self
+    where
+        I: Into<PathBuf>,
+    {
+        self.journal = input.map(|path| path.into());
+        self
+    }
+
+    /// Set the hooks to run around the build and install phases, and return a
+    /// mutable reference to self, per the builder pattern.
+    pub fn hooks(&mut self, input: Hooks) -> &mut Self {
+        self.hooks = input;
+        self
+    }
+
+    /// Set the policies which the install must satisfy, and return a mutable
+    /// reference to self, per the builder pattern.
+    pub fn policies(&mut self, input: Vec<Policy>) -> &mut Self {
+        self.policies = input;
+        self
+    }
+
+    /// Set the ids of the policies to override, and return a mutable reference to
+    /// self, per the builder pattern. Only overridable policies may be overridden.
+    pub fn override_policies<I>(&mut self, input: Option<Vec<I>>) -> &mut Self
+    where
+        I: Into<String>,
+    {
+        self.override_policies = input
+            .map(|ids| ids.into_iter().map(|id| id.into()).collect())
+            .unwrap_or_default();
+        self
+    }
+
+    /// Set the rules restricting which Unix groups may install to each level and
+    /// site, and return a mutable reference to self, per the builder pattern.
+    pub fn authorization(&mut self, input: Vec<AuthRule>) -> &mut Self {
+        self.authorization = input;
+        self
+    }
+
+    /// Set the user and groups which the authorization rules are checked against,
+    /// and return a mutable reference to self, per the builder pattern. None, the
+    /// default, looks up the current user via `id`.
+    pub fn user_groups(&mut self, input: Option<UserGroups>) -> &mut Self {
+        self.user_groups = input;
+        self
+    }
+
+    /// Set the registry which sites are looked up in, and groups expanded against,
+    /// and return a mutable reference to self, per the builder pattern.
+    pub fn site_registry(&mut self, input: SiteRegistry) -> &mut Self {
+        self.site_registry = input;
+        self
+    }
+
+    /// New up an Install whose platforms, including the host platform, are looked up
+    /// in the supplied registry, rather than the builtin one.
+    pub fn with_platform_registry(platform_registry: PlatformRegistry) -> Self {
+        Self {
+            platform_registry,
+            ..Self::default()
+        }
+    }
+
+    /// Set the patterns which the show is inferred from the working directory by,
+    /// and return a mutable reference to self, per the builder pattern.
+    pub fn show_patterns(&mut self, input: Vec<ShowPattern>) -> &mut Self {
+        self.show_patterns = input;
+        self
+    }
+
+    /// Set the registry of known shows, which the show is verified against, and
+    /// return a mutable reference to self, per the builder pattern. None disables
+    /// show validation.
+    pub fn show_registry(&mut self, input: Option<ShowRegistry>) -> &mut Self {
+        self.show_registry = input;
+        self
+    }
+
+    /// Permit installing to a show which is not in the ShowRegistry, and return a
+    /// mutable reference to self, per the builder pattern.
+    pub fn allow_unknown_show(&mut self, value: bool) -> &mut Self {
+        self.allow_unknown_show = value;
+        self
+    }
+
     /// Add a site to the list of sites maintianed by the Install struct. This
-    /// method may be called multiple times. The method is fallible, and must
-    /// be unwrapped.
+    /// method may be called multiple times. Sites are looked up in the site registry,
+    /// and groups and exclusions expanded, once the install is carried out.
     ///
     /// # Example
     /// ```
-    /// # fn main() -> Result<(),Box<dyn std::error::Error>> {
     /// # use pk_make::Install;
     /// let install = Install::default()
-    ///                 .site(Some("vancouver"))?
-    ///                 .site(Some("playa"))?
+    ///                 .site(Some("vancouver"))
+    ///                 .site(Some("playa"))
     ///                 .build();
-    /// # Ok(())
-    /// # }
     /// ```
-    pub fn site<I>(&mut self, value: Option<I>) -> Result<&mut Self, AnyError>
+    pub fn site<I>(&mut self, value: Option<I>) -> &mut Self
     where
-        I: std::convert::TryInto<Site> + std::fmt::Debug + Clone,
+        I: AsRef<str>,
     {
         match value {
-            Some(val) => match self.sites {
-                Some(ref mut sites) => {
-                    let val_cpy = val.clone();
-                    match val.try_into() {
-                        Ok(v) => sites.insert(v),
-                        Err(_) => return Err(anyhow!("Error converting {:?} into Site", val_cpy)),
-                    };
-                }
-                None => {
-                    let mut hset = HashSet::new();
-                    let val_cpy = val.clone();
-                    match val.try_into() {
-                        Ok(v) => hset.insert(v),
-                        Err(_) => return Err(anyhow!("Error converting {:?} into Site", val_cpy)),
-                    };
-                    //hset.insert(val.into());
-                    self.sites = Some(hset);
-                }
-            },
-            None => self.sites = None,
+            Some(val) => self.requested_sites.push(val.as_ref().to_string()),
+            None => self.clear_sites(),
         }
-        Ok(self)
+        self
     }
 
-    /// Add a vec of sites to the list of sites maintianed by the Install struct. This is a
-    /// fallible setter, and must be unwrapped or '?'ed
+    /// Add a vec of sites to the list of sites maintianed by the Install struct. As
+    /// with site, the sites are looked up once the install is carried out.
     ///
     /// # Example
     /// ```
-    /// # fn main() -> Result<(),Box<dyn std::error::Error>> {
     /// # use pk_make::Install;
     /// let install = Install::default()
-    ///                 .sites(Some(vec!["vancouver","portland"]))?
+    ///                 .sites(Some(vec!["vancouver","portland"]))
     ///                 .build();
-    /// # Ok(())
-    /// # }
     /// ```
-    pub fn sites<I>(&mut self, value: Option<Vec<I>>) -> Result<&mut Self, AnyError>
+    pub fn sites<I>(&mut self, value: Option<Vec<I>>) -> &mut Self
     where
-        I: TryInto<Site>,
+        I: AsRef<str>,
     {
         match value {
-            None => self.sites = None,
-            Some(sites) => {
-                let sites: Result<Vec<_>, _> =
-                    sites.into_iter().map(|i_val| i_val.try_into()).collect();
-                match sites {
-                    Err(_) => return Err(anyhow!("failed to convert one or more overrides")),
-                    Ok(val) => match self.sites {
-                        Some(ref mut sites) => {
-                            for v in val {
-                                sites.insert(v);
-                            }
-                        }
-                        None => {
-                            let mut hset = HashSet::new();
-                            for v in val {
-                                hset.insert(v);
-                            }
-                            self.sites = Some(hset);
-                        }
-                    },
-                }
-            }
+            None => self.clear_sites(),
+            Some(sites) => self
+                .requested_sites
+                .extend(sites.iter().map(|s| s.as_ref().to_string())),
         }
-        Ok(self)
+        self
+    }
+
+    // expand the requested sites, if any, into sites, against the SiteRegistry. This
+    // happens once the install is carried out, so that the registry may be set at any
+    // point in the chain of calls. An expansion which leaves no sites is an error.
+    pub(crate) fn resolve_sites(&mut self) -> Result<(), AnyError> {
+        if self.requested_sites.is_empty() {
+            return Ok(());
+        }
+        let parsed = self
+            .requested_sites
+            .iter()
+            .map(|s| Site::parse(s, &self.site_registry))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| anyhow!("failed to convert one or more sites: {}", e))?;
+        let expanded = Site::expand(parsed, &self.site_registry)?;
+        if expanded.is_empty() {
+            return Err(PkMakeError::NoSites(self.requested_sites.join(" ")).into());
+        }
+        self.sites = Some(expanded);
+        Ok(())
+    }
+
+    // forget the requested sites, so that the default applies
+    fn clear_sites(&mut self) {
+        self.sites = None;
+        self.requested_sites.clear();
+    }
+
+    // whether any of the requested sites is a group or exclusion, and so was expanded
+    // into concrete sites
+    fn sites_expanded(&self) -> bool {
+        self.requested_sites
+            .iter()
+            .any(|s| s.starts_with('@') || s.starts_with('!'))
     }
 
     /// Add a platform to the list of platforms on the Install struct. This may be called
@@ -735,34 +1529,15 @@ impl Install {
     /// ```
     pub fn platform<I>(&mut self, value: Option<I>) -> Result<&mut Self, AnyError>
     where
-        I: TryInto<Platform> + std::fmt::Debug + Clone,
+        I: AsRef<str>,
     {
         match value {
-            Some(val) => match self.platforms {
-                Some(ref mut platforms) => {
-                    let val_cpy = val.clone();
-                    //platforms.insert(val.into());
-                    match val.try_into() {
-                        Ok(v) => platforms.insert(v),
-                        Err(_) => {
-                            return Err(anyhow!("Error converting {:?} into Platform", val_cpy))
-                        }
-                    };
-                }
-                None => {
-                    let val_cpy = val.clone();
-                    let mut hset = HashSet::new();
-                    //hset.insert(val.into());
-                    //
-                    match val.try_into() {
-                        Ok(v) => hset.insert(v),
-                        Err(_) => {
-                            return Err(anyhow!("Error converting {:?} into Platform", val_cpy))
-                        }
-                    };
-                    self.platforms = Some(hset);
-                }
-            },
+            Some(val) => {
+                let platform = self.platform_registry.parse(val.as_ref())?;
+                self.platforms
+                    .get_or_insert_with(HashSet::new)
+                    .insert(platform);
+            }
             None => self.platforms = None,
         }
         Ok(self)
@@ -782,15 +1557,17 @@ impl Install {
     /// ```
     pub fn platforms<I>(&mut self, value: Option<Vec<I>>) -> Result<&mut Self, AnyError>
     where
-        I: TryInto<Platform>,
+        I: AsRef<str>,
     {
         match value {
             None => self.platforms = None,
             Some(plats) => {
-                let plats: Result<Vec<_>, _> =
-                    plats.into_iter().map(|i_val| i_val.try_into()).collect();
+                let plats: Result<Vec<_>, _> = plats
+                    .iter()
+                    .map(|i_val| self.platform_registry.parse(i_val.as_ref()))
+                    .collect();
                 match plats {
-                    Err(_) => return Err(anyhow!("failed to convert to platform")),
+                    Err(e) => return Err(anyhow!("failed to convert to platform: {}", e)),
                     Ok(val) => match self.platforms {
                         Some(ref mut platforms) => {
                             for v in val {
@@ -924,6 +1701,13 @@ impl Install {
         self
     }
 
+    /// Set the rebuild value and return a mutable reference to self per the builder
+    /// pattern. When set, every flavor is built, regardless of its cache key.
+    pub fn rebuild(&mut self, value: bool) -> &mut Self {
+        self.rebuild = value;
+        self
+    }
+
     /// Set the dist_dir value and return a mutable reference to
     /// self, per the builder pattern.
     pub fn dist_dir<I>(&mut self, input: Option<I>) -> &mut Self
@@ -941,16 +1725,17 @@ impl Install {
     /// self, per the builder pattern.
     pub fn level<I>(&mut self, input: Option<I>) -> Result<&mut Self, AnyError>
     where
-        I: Into<String>,
+        I: TryInto<Level>,
+        I::Error: std::fmt::Display,
     {
         match input {
-            
             None => self.level = None,
             Some(level) => {
                 if self.context.is_some() {
                     return Err(anyhow!("Cannot set level and context"));
                 }
-                self.level = Some(level.into())},
+                self.level = Some(level.try_into().map_err(|e| anyhow!("{}", e))?)
+            }
         }
         Ok(self)
     }
@@ -1066,6 +1851,132 @@ impl Install {
     }
 }
 
+/// A single destination reported by `install --preview`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PreviewEntry {
+    pub site: String,
+    pub platform: Platform,
+    pub flavor: Flavor,
+    /// The package id, ie `name-version` or `name-version_flavor`
+    pub package_id: String,
+    pub level: String,
+    /// Whether the manifest's os list supports the platform
+    pub supported: bool,
+}
+
+/// The destinations an install would write to, one per (site, platform, flavor)
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct InstallPreview {
+    pub entries: Vec<PreviewEntry>,
+    /// The manifest's os list
+    pub os: Vec<String>,
+}
+
+impl InstallPreview {
+    /// The entries whose platform the manifest does not support
+    pub fn unsupported(&self) -> impl Iterator<Item = &PreviewEntry> {
+        self.entries.iter().filter(|e| !e.supported)
+    }
+}
+
+impl Tabulate for InstallPreview {
+    fn create_table(&self) -> Table {
+        let mut table = Table::new();
+        table.add_row(row!["Site", "Platform", "Flavor", "Package", "Level", "Supported"]);
+        for entry in &self.entries {
+            let supported = if entry.supported {
+                "yes".to_string()
+            } else {
+                format!("NO (os: {})", self.os.join(", "))
+            };
+            table.add_row(row![
+                entry.site,
+                entry.platform,
+                entry.flavor.as_str(),
+                entry.package_id,
+                entry.level,
+                supported
+            ]);
+        }
+        table
+    }
+}
+
+// Performs the checks which policies require, on demand, remembering the results.
+// When defer_tests, the tests are not run, and are assumed to pass.
+struct InstallProbe<'a> {
+    install: &'a Install,
+    build_env: &'a BuildEnv,
+    vcs_clean: Option<bool>,
+    tests_pass: Option<bool>,
+    defer_tests: bool,
+    tests_deferred: bool,
+}
+
+impl<'a> InstallProbe<'a> {
+    fn new(install: &'a Install, build_env: &'a BuildEnv, defer_tests: bool) -> Self {
+        Self {
+            install,
+            build_env,
+            vcs_clean: None,
+            tests_pass: None,
+            defer_tests,
+            tests_deferred: false,
+        }
+    }
+}
+
+impl PolicyProbe for InstallProbe<'_> {
+    fn vcs_clean(&mut self) -> Result<bool, AnyError> {
+        if let Some(clean) = self.vcs_clean {
+            return Ok(clean);
+        }
+        let root = &self.build_env.package_root;
+        let clean = self
+            .build_env
+            .vcs
+            .as_ref()
+            .and_then(|vcs| vcs.is_clean(root))
+            .ok_or_else(|| {
+                anyhow!(
+                    "A policy requires a clean vcs state, but the vcs status of '{}' could not be determined",
+                    root.display()
+                )
+            })?;
+        self.vcs_clean = Some(clean);
+        Ok(clean)
+    }
+
+    fn tests_pass(&mut self) -> Result<bool, AnyError> {
+        if let Some(passed) = self.tests_pass {
+            return Ok(passed);
+        }
+        if self.defer_tests {
+            self.tests_deferred = true;
+            return Ok(true);
+        }
+        let mut test = Test {
+            dist_dir: self.install.dist_dir.clone(),
+            dry_run: false,
+            verbose: self.install.verbose,
+            defines: self.install.defines.clone(),
+            platforms: self.install.platforms.clone(),
+            flavors: self.install.flavors.clone(),
+            package_root: Some(self.build_env.package_root.clone()),
+            platform_registry: self.install.platform_registry.clone(),
+        };
+        let cmd = test.build_cmd()?.join(" ; ");
+        if self.install.dry_run {
+            println!("A policy requires the tests to pass. Would run: {}", cmd);
+            return Ok(true);
+        }
+        println!("A policy requires the tests to pass. Running: {}", cmd);
+        let passed = exec_cmd(cmd.as_str(), &self.build_env.package_root)?.success();
+        self.tests_pass = Some(passed);
+        Ok(passed)
+    }
+}
+
 //
 // Tabulate implementation
 //
@@ -1074,11 +1985,42 @@ impl Tabulate for Install {
         let mut table = Table::new();
         table.add_row(row!["Field", "Value"]);
         table.add_row(row!["dry_run", self.dry_run]);
+        if self.preview {
+            table.add_row(row!["preview", self.preview]);
+        }
+        table.add_row(row!["no_build", self.no_build]);
+        table.add_row(row!["rebuild", self.rebuild]);
+        if let Some(ref to_dir) = self.to_dir {
+            table.add_row(row!["to_dir", to_dir.display()]);
+        }
         table.add_row(row!["with_docs", self.with_docs]);
+        if let Some(ref journal) = self.journal {
+            table.add_row(row!["journal", journal.display()]);
+        }
+        for point in &HookPoint::ALL {
+            for cmd in self.hooks.get(*point) {
+                table.add_row(row![format!("hooks.{}", point), cmd]);
+            }
+        }
+        if !self.policies.is_empty() {
+            table.add_row(row![
+                "policies",
+                self.policies.iter().map(|p| p.id.as_str()).collect::<Vec<_>>().join("\n")
+            ]);
+        }
+        if !self.override_policies.is_empty() {
+            table.add_row(row!["override_policies", self.override_policies.join("\n")]);
+        }
         table.add_row(row!["verbose", self.verbose]);
         table.add_row(row!["dist_dir", self.dist_dir.as_deref().unwrap_or("None")]);
-        table.add_row(row!["level", self.level.as_deref().unwrap_or("None")]);
+        table.add_row(row![
+            "level",
+            self.level.as_ref().map(|l| l.to_string()).unwrap_or_else(|| "None".to_string())
+        ]);
         table.add_row(row!["work", self.work]);
+        if self.allow_unknown_show {
+            table.add_row(row!["allow_unknown_show", self.allow_unknown_show]);
+        }
         table.add_row(row![
             "Vcs",
             self.vcs.as_ref().map(|vcs| vcs.as_str()).unwrap_or("None")
@@ -1126,6 +2068,9 @@ impl Tabulate for Install {
                 .map(|v| v.iter().map(|x| x.as_str()).collect::<Vec<_>>().join("\n"))
                 .unwrap_or_else(|| "None".to_string())
         ]);
+        if !self.requested_sites.is_empty() {
+            table.add_row(row!["requested_sites", self.requested_sites.join("\n")]);
+        }
         table.add_row(row![
             "platforms",
             self.platforms
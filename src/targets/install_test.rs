@@ -5,6 +5,7 @@ use super::*;
 use std::env;
 use serial_test::serial;
 
+use crate::targets::Build;
 use crate::utils::setup_manifest_dir;
 use crate::utils::setup_manifest_dir2;
 
@@ -14,11 +15,15 @@ fn can_construct_default() {
     let result = Install::default();
     let expected = Install {
         dry_run: false,
+        preview: false,
+        no_build: false,
         with_docs: true,
         build_dir: None,
         context: None,
         show: None,
         sites: None,
+        requested_sites: Vec::new(),
+        allow_unknown_show: false,
         platforms: None,
         flavors: None,
         verbose: false,
@@ -32,6 +37,18 @@ fn can_construct_default() {
         logfile: None,
         max_jobs: None,
         package_root: None,
+        to_dir: None,
+        journal: None,
+        hooks: Hooks::default(),
+        policies: Vec::new(),
+        override_policies: Vec::new(),
+        authorization: Vec::new(),
+        site_registry: SiteRegistry::builtin(),
+        platform_registry: PlatformRegistry::builtin(),
+        show_patterns: ShowPattern::builtin(),
+        show_registry: None,
+        rebuild: false,
+        user_groups: None,
     };
     assert_eq!(result, expected);
 }
@@ -47,7 +64,6 @@ fn build_given_context_and_show() {
         .unwrap()
         .show(Some("dev01"))
         .sites(Some(vec!["all"]))
-        .unwrap()
         .platform(Some("cent7"))
         .unwrap()
         .flavor(Some("^"))
@@ -65,11 +81,8 @@ fn build_given_context_and_show() {
         .package_root(Some("./some/root"))
         .build();
 
-    let mut site_hs = HashSet::new();
-    site_hs.insert(Site::All);
-
     let mut platforms_hs = HashSet::new();
-    platforms_hs.insert(Platform::Cent7_64);
+    platforms_hs.insert(Platform::from("cent7_64").unwrap());
 
     let mut flavors_hs = HashSet::new();
     flavors_hs.insert(Flavor::Vanilla);
@@ -77,11 +90,16 @@ fn build_given_context_and_show() {
     let expected = Install {
         clean: true,
         dry_run: true,
+        preview: false,
+        no_build: false,
         with_docs: false,
         build_dir: Some("foo/bar".to_string()),
         context: Some(Context::Facility),
         show: Some("dev01".to_string()),
-        sites: Some(site_hs),
+        // the sites are expanded once the install is carried out
+        sites: None,
+        requested_sites: vec!["all".to_string()],
+        allow_unknown_show: false,
         platforms: Some(platforms_hs),
         flavors: Some(flavors_hs),
         verbose: true,
@@ -94,6 +112,18 @@ fn build_given_context_and_show() {
         logfile: Some(PathBuf::from("./log/logfile")),
         max_jobs: Some(8),
         package_root: Some(PathBuf::from("./some/root")),
+        to_dir: None,
+        journal: None,
+        hooks: Hooks::default(),
+        policies: Vec::new(),
+        override_policies: Vec::new(),
+        authorization: Vec::new(),
+        site_registry: SiteRegistry::builtin(),
+        platform_registry: PlatformRegistry::builtin(),
+        show_patterns: ShowPattern::builtin(),
+        show_registry: None,
+        rebuild: false,
+        user_groups: None,
     };
     assert_eq!(result, expected);
 }
@@ -118,7 +148,6 @@ fn build_given_level() {
         .with_docs(false)
         .build_dir(Some("foo/bar"))
         .sites(Some(vec!["all"]))
-        .unwrap()
         .platform(Some("cent7"))
         .unwrap()
         .flavor(Some("^"))
@@ -137,11 +166,8 @@ fn build_given_level() {
         .package_root(Some("./some/root"))
         .build();
 
-    let mut site_hs = HashSet::new();
-    site_hs.insert(Site::All);
-
     let mut platforms_hs = HashSet::new();
-    platforms_hs.insert(Platform::Cent7_64);
+    platforms_hs.insert(Platform::from("cent7_64").unwrap());
 
     let mut flavors_hs = HashSet::new();
     flavors_hs.insert(Flavor::Vanilla);
@@ -149,16 +175,21 @@ fn build_given_level() {
     let expected = Install {
         clean: true,
         dry_run: true,
+        preview: false,
+        no_build: false,
         with_docs: false,
         build_dir: Some("foo/bar".to_string()),
         context: None,
         show: None,
-        sites: Some(site_hs),
+        // the sites are expanded once the install is carried out
+        sites: None,
+        requested_sites: vec!["all".to_string()],
+        allow_unknown_show: false,
         platforms: Some(platforms_hs),
         flavors: Some(flavors_hs),
         verbose: true,
         dist_dir: Some("./foo/bar".to_string()),
-        level: Some("DEV01.RD.0001".into()),
+        level: Some(Level::from("DEV01.RD.0001").unwrap()),
         overrides: Some(vec![OverridePair::from("foo=1.2.3").unwrap()]),
         defines: Some(vec!["bla=mange".to_string()]),
         work: true,
@@ -166,6 +197,18 @@ fn build_given_level() {
         logfile: Some(PathBuf::from("./log/logfile")),
         max_jobs: Some(8),
         package_root: Some(PathBuf::from("./some/root")),
+        to_dir: None,
+        journal: None,
+        hooks: Hooks::default(),
+        policies: Vec::new(),
+        override_policies: Vec::new(),
+        authorization: Vec::new(),
+        site_registry: SiteRegistry::builtin(),
+        platform_registry: PlatformRegistry::builtin(),
+        show_patterns: ShowPattern::builtin(),
+        show_registry: None,
+        rebuild: false,
+        user_groups: None,
     };
     assert_eq!(result, expected);
 }
@@ -180,7 +223,6 @@ fn build_given_level_and_context_fails() {
         .with_docs(false)
         .build_dir(Some("foo/bar"))
         .sites(Some(vec!["all"]))
-        ?
         .platform(Some("cent7"))
         ?
         .flavor(Some("^"))
@@ -215,7 +257,6 @@ fn build_given_context_and_level_fails() {
         .with_docs(false)
         .build_dir(Some("foo/bar"))
         .sites(Some(vec!["all"]))
-        ?
         .platform(Some("cent7"))
         ?
         .flavor(Some("^"))
@@ -343,6 +384,32 @@ fn build_cmd_given_clean_platforms() {
     assert_eq!(result.unwrap(), expected);
 }
 
+#[test]
+#[serial]
+fn build_cmd_given_unbuildable_platform_only_fails_to_build() {
+    let root = setup_manifest_dir2("nonflavored_git");
+    let mut target = root.clone();
+    target.push("private"); target.push("dist"); target.push("makebridge-3.1.0");
+    env::set_var("DD_SHOW", "DEV01");
+    env::set_var("DD_OS", "cent7_64");
+
+    let result = Install::default()
+        .platforms(Some(vec!["cent8"]))
+        .unwrap()
+        .build_cmd();
+    assert!(result.is_err());
+    // an existing dist may still be installed
+    let result = Install::default()
+        .platforms(Some(vec!["cent8"]))
+        .unwrap()
+        .no_build(true)
+        .build_cmd();
+    let expected = vec![
+        format!("pk install --level=DEV01.work --site=local --platform=cent8_64 {}", target.to_str().unwrap()),
+    ];
+    assert_eq!(result.unwrap(), expected);
+}
+
 #[test]
 #[serial]
 fn build_cmd_given_showlevel() {
@@ -596,3 +663,825 @@ fn build_cmd_given_facility_level_both_set_git() {
     // }
     assert_eq!(result.unwrap(), expected);
 }
+
+#[test]
+fn sites_given_group_and_exclusion_expands() {
+    let mut registry = SiteRegistry::builtin();
+    registry
+        .add_group("na", &["playa".into(), "portland".into(), "vancouver".into()])
+        .unwrap();
+
+    // the registry may be set after the sites, as they are expanded lazily
+    let mut result = Install::default()
+        .sites(Some(vec!["@na", "!portland"]))
+        .site_registry(registry)
+        .build();
+    result.resolve_sites().unwrap();
+
+    assert_eq!(result.get_site_str(), " --site=playa,vancouver");
+    assert_eq!(
+        result.requested_sites,
+        vec!["@na".to_string(), "!portland".to_string()]
+    );
+}
+
+#[test]
+fn site_exclusion_applies_to_previous_sites() {
+    let mut result = Install::default()
+        .site(Some("all"))
+        .site(Some("!hyderabad"))
+        .build();
+    result.resolve_sites().unwrap();
+    assert_eq!(
+        result.get_site_str(),
+        " --site=playa,portland,montreal,vancouver"
+    );
+    assert!(result.sites_expanded());
+}
+
+#[test]
+fn sites_given_plain_sites_are_not_expanded() {
+    let result = Install::default()
+        .sites(Some(vec!["all"]))
+        .build();
+    assert_eq!(result.requested_sites, vec!["all".to_string()]);
+    assert!(!result.sites_expanded());
+
+    let result = Install::default()
+        .site(Some("!playa"))
+        .site::<&str>(None)
+        .build();
+    assert_eq!(result.sites, None);
+    assert!(result.requested_sites.is_empty());
+}
+
+#[test]
+fn sites_given_exclusions_leaving_nothing_errors() {
+    let mut registry = SiteRegistry::builtin();
+    registry
+        .add_group("na", &["playa".into(), "portland".into()])
+        .unwrap();
+    let result = Install::default()
+        .site_registry(registry)
+        .sites(Some(vec!["@na", "!playa", "!portland"]))
+        .resolve_sites();
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "The requested sites, '@na !playa !portland', leave no sites to install to"
+    );
+}
+
+#[test]
+fn resolve_sites_given_unknown_site_fails() {
+    let result = Install::default()
+        .sites(Some(vec!["portland", "atlantis"]))
+        .resolve_sites();
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "failed to convert one or more sites: Invalid Site:'atlantis'"
+    );
+}
+
+// an unknown show is rejected, with suggestions, unless explicitly allowed
+#[test]
+#[serial]
+fn build_cmd_given_unknown_show() {
+    let root = setup_manifest_dir2("nonflavored_git");
+    env::set_var("DD_OS", "cent7_64");
+    let registry = Some(ShowRegistry::new(vec!["dev01", "dev02"]));
+
+    let result = Install::default()
+        .show_registry(registry.clone())
+        .show(Some("dve01"))
+        .package_root(Some(&root))
+        .build_cmd();
+    let allowed = Install::default()
+        .show_registry(registry.clone())
+        .show(Some("dve01"))
+        .allow_unknown_show(true)
+        .package_root(Some(&root))
+        .build_cmd();
+    let level = Install::default()
+        .show_registry(registry.clone())
+        .level(Some("dev03.work"))
+        .unwrap()
+        .package_root(Some(&root))
+        .build_cmd();
+    let known = Install::default()
+        .show_registry(registry)
+        .show(Some("DEV01"))
+        .package_root(Some(&root))
+        .build_cmd();
+
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Unknown show 'dve01'. Did you mean dev01? Use --allow-unknown-show to install to a new show."
+    );
+    assert!(allowed.is_ok());
+    assert!(level.is_err());
+    assert!(known.is_ok());
+}
+
+// the preview reports each (site, platform, flavor) destination, flagging platforms
+// which the manifest's os list does not support
+#[test]
+#[serial]
+fn resolve_preview_given_sites_platforms_flavors() {
+    let root = setup_manifest_dir(true);
+    env::set_var("DD_SHOW", "DEV01");
+    env::set_var("DD_OS", "cent7_64");
+
+    let result = Install::default()
+        .sites(Some(vec!["portland", "vancouver"]))
+        .platforms(Some(vec!["cent7", "win10"]))
+        .unwrap()
+        .flavors(Some(vec!["^", "foo"]))
+        .unwrap()
+        .package_root(Some(&root))
+        .resolve_preview()
+        .unwrap();
+    assert_eq!(result.entries.len(), 8);
+    assert_eq!(result.os, vec!["linux".to_string()]);
+    let first = &result.entries[0];
+    assert_eq!(first.site, "portland");
+    assert_eq!(first.platform.as_str(), "cent7_64");
+    assert_eq!(first.package_id, "vrayddbase-5.0.8");
+    assert_eq!(first.level, "DEV01.work");
+    assert!(first.supported);
+    assert_eq!(result.entries[1].package_id, "vrayddbase-5.0.8_foo");
+    let unsupported = result.unsupported().collect::<Vec<_>>();
+    assert_eq!(unsupported.len(), 4);
+    assert!(unsupported.iter().all(|e| e.platform.as_str() == "win10_64"));
+}
+
+// --no-build omits the pk build step
+#[test]
+#[serial]
+fn build_cmd_given_no_build() {
+    let root = setup_manifest_dir2("nonflavored_git");
+    let mut target = root.clone();
+    target.push("private"); target.push("dist"); target.push("makebridge-3.1.0");
+    env::set_var("DD_SHOW", "DEV01");
+    env::set_var("DD_OS", "cent7_64");
+
+    let result = Install::default().no_build(true).package_root(Some(&root)).build_cmd();
+    let expected = vec![
+        format!("pk install --level=DEV01.work --site=local --platform=cent7_64 {}", target.to_str().unwrap()),
+    ];
+    assert_eq!(result.unwrap(), expected);
+}
+
+// an existing dist artifact does not skip the build, which may have been made
+// with other options
+#[test]
+#[serial]
+fn build_cmd_given_existing_dist_still_builds() {
+    let root = tempfile::tempdir().unwrap();
+    let root = std::fs::canonicalize(root.path()).unwrap();
+    let manifest = setup_manifest_dir2("nonflavored_git").join("manifest.yaml");
+    std::fs::copy(&manifest, root.join("manifest.yaml")).unwrap();
+    let target = root.join("private").join("dist").join("makebridge-3.1.0");
+    std::fs::create_dir_all(&target).unwrap();
+    env::set_var("DD_SHOW", "DEV01");
+    env::set_var("DD_OS", "cent7_64");
+
+    let result = Install::default().package_root(Some(&root)).build_cmd();
+    assert_eq!(
+        result.unwrap(),
+        vec![
+            "pk audit && pk build --with-docs".to_string(),
+            format!(
+                "pk install --level=DEV01.work --site=local --platform=cent7_64 {}",
+                target.to_str().unwrap()
+            ),
+        ]
+    );
+}
+
+// the build step shares pk-make build's cache keys, so flavors which are current
+// are not rebuilt, unless rebuild is requested
+#[test]
+#[serial]
+fn build_cmd_given_cached_flavors_skips_their_build() {
+    let root = tempfile::tempdir().unwrap();
+    let root = std::fs::canonicalize(root.path()).unwrap();
+    std::fs::write(
+        root.join("manifest.yaml"),
+        "name: foo\nversion: 1.0.0\nflavours:\n  - name: ^\n  - name: bar\n",
+    )
+    .unwrap();
+    env::set_current_dir(&root).unwrap();
+    env::set_var("DD_SHOW", "DEV01");
+    env::set_var("DD_OS", "cent7_64");
+    let dist = root.join("private").join("dist");
+    let install_cmd = |id: &str| {
+        format!(
+            "pk install --level=DEV01.work --site=local --platform=cent7_64 {}",
+            dist.join(id).to_str().unwrap()
+        )
+    };
+
+    let mut install = Install::default().package_root(Some(&root)).build();
+    assert_eq!(install.build_cmd().unwrap()[0], "pk audit && pk build --with-docs");
+
+    // simulate a successful build of both flavors
+    std::fs::create_dir_all(dist.join("foo-1.0.0")).unwrap();
+    std::fs::create_dir_all(dist.join("foo-1.0.0_bar")).unwrap();
+    let build_env = BuildEnv::new(&root).unwrap();
+    install.cache_plan(&build_env).unwrap().record().unwrap();
+    assert_eq!(
+        install.build_cmd().unwrap(),
+        vec![install_cmd("foo-1.0.0"), install_cmd("foo-1.0.0_bar")]
+    );
+
+    // the keys are those of pk-make build
+    let mut build = Build::default().package_root(Some(&root)).build();
+    assert!(build.build_cmd().unwrap().is_empty());
+
+    // only the flavor whose key has gone missing is built
+    std::fs::remove_file(dist.join("foo-1.0.0_bar.cachekey")).unwrap();
+    assert_eq!(
+        install.build_cmd().unwrap()[0],
+        "pk audit && pk build --with-docs --flavour=bar"
+    );
+
+    install.rebuild(true);
+    assert_eq!(install.build_cmd().unwrap()[0], "pk audit && pk build --with-docs");
+}
+
+// every flavor's artifact is verified, in the default dist dir or the supplied one
+#[test]
+#[serial]
+fn verify_artifacts_given_default_and_supplied_dist_dir() {
+    let root = tempfile::tempdir().unwrap();
+    let root = std::fs::canonicalize(root.path()).unwrap();
+    std::fs::write(
+        root.join("manifest.yaml"),
+        "name: foo\nversion: 1.0.0\nflavours:\n  - name: ^\n  - name: bar\n",
+    )
+    .unwrap();
+    env::set_current_dir(&root).unwrap();
+    env::set_var("DD_OS", "cent7_64");
+    let dist = root.join("private").join("dist");
+    std::fs::create_dir_all(dist.join("foo-1.0.0")).unwrap();
+    std::fs::write(dist.join("foo-1.0.0").join("foo.so"), "").unwrap();
+
+    let mut install = Install::default().package_root(Some(&root)).build();
+    let err = install.verify_artifacts().unwrap_err().to_string();
+    assert_eq!(
+        err,
+        format!(
+            "Missing dist artifacts for foo-1.0.0:\n  {} (does not exist)",
+            dist.join("foo-1.0.0_bar").display()
+        )
+    );
+    install.flavors(Some(vec!["^"])).unwrap();
+    assert!(install.verify_artifacts().is_ok());
+
+    install.dist_dir(Some("staging"));
+    let err = install.verify_artifacts().unwrap_err().to_string();
+    assert!(err.contains(&root.join("staging").join("foo-1.0.0").display().to_string()));
+}
+
+// --to-dir copies each flavor's dist, per platform, instead of calling pk install
+#[test]
+#[serial]
+fn doit_given_to_dir_stages_the_dist() {
+    let root = tempfile::tempdir().unwrap();
+    let root = std::fs::canonicalize(root.path()).unwrap();
+    std::fs::write(
+        root.join("manifest.yaml"),
+        "name: foo\nversion: 1.0.0\nflavours:\n  - name: ^\n  - name: bar\n",
+    )
+    .unwrap();
+    env::set_current_dir(&root).unwrap();
+    env::set_var("DD_OS", "cent7_64");
+    env::remove_var("DD_SHOW");
+    let dist = root.join("private").join("dist");
+    for artifact in &["foo-1.0.0", "foo-1.0.0_bar"] {
+        std::fs::create_dir_all(dist.join(artifact)).unwrap();
+        std::fs::write(dist.join(artifact).join("foo.so"), *artifact).unwrap();
+    }
+    let scratch = root.join("scratch");
+
+    let mut install = Install::default()
+        .to_dir(Some(&scratch))
+        .package_root(Some(&root))
+        .build();
+    assert_eq!(
+        install.build_cmd().unwrap(),
+        vec!["pk audit && pk build --with-docs".to_string()]
+    );
+
+    install.no_build(true);
+    assert!(install.build_cmd().unwrap().is_empty());
+    install.doit().unwrap();
+    assert_eq!(
+        std::fs::read_to_string(scratch.join("foo/1.0.0_bar/cent7_64/foo.so")).unwrap(),
+        "foo-1.0.0_bar"
+    );
+    let index = StagingIndex::load(&scratch).unwrap();
+    assert_eq!(index.staged.len(), 2);
+    assert_eq!(index.staged[0].destination, scratch.join("foo/1.0.0/cent7_64"));
+    assert_eq!(index.staged[0].source, dist.join("foo-1.0.0"));
+
+    install.level(Some("dev01")).unwrap();
+    assert!(install.build_cmd().is_err());
+}
+
+// each install which is not a dry run is recorded in the history journal
+#[test]
+#[serial]
+fn doit_given_journal_records_the_install() {
+    let root = tempfile::tempdir().unwrap();
+    let root = std::fs::canonicalize(root.path()).unwrap();
+    std::fs::write(
+        root.join("manifest.yaml"),
+        "name: foo\nversion: 1.0.0\nflavours:\n  - name: ^\n  - name: bar\n",
+    )
+    .unwrap();
+    env::set_current_dir(&root).unwrap();
+    env::set_var("DD_OS", "cent7_64");
+    env::remove_var("DD_SHOW");
+    let artifact = root.join("private").join("dist").join("foo-1.0.0_bar");
+    std::fs::create_dir_all(&artifact).unwrap();
+    std::fs::write(artifact.join("foo.so"), "bar").unwrap();
+    let journal = root.join("history.jsonl");
+
+    let mut install = Install::default()
+        .no_build(true)
+        .to_dir(Some(root.join("scratch")))
+        .flavors(Some(vec!["bar"]))
+        .unwrap()
+        .journal(Some(&journal))
+        .package_root(Some(&root))
+        .build();
+    install.dry_run(true);
+    install.doit().unwrap();
+    assert!(!journal.exists());
+
+    install.dry_run(false);
+    install.doit().unwrap();
+    install.flavors(None::<Vec<&str>>).unwrap().flavors(Some(vec!["^"])).unwrap();
+    assert!(install.doit().is_err());
+
+    let records = Journal::new(&journal).records().unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].package, "foo");
+    assert_eq!(records[0].version, "1.0.0");
+    assert_eq!(records[0].flavors, vec!["bar".to_string()]);
+    assert_eq!(records[0].platforms, vec!["cent7_64".to_string()]);
+    assert!(records[0].succeeded());
+    assert_eq!(records[1].flavors, vec!["^".to_string()]);
+    assert!(records[1].error.as_ref().unwrap().contains("foo-1.0.0 (does not exist)"));
+}
+
+// a facility install is journaled at the facility level, whether it was
+// requested via the context or the level
+#[test]
+#[serial]
+#[cfg(unix)]
+fn doit_given_facility_context_journals_the_facility_level() {
+    let root = tempfile::tempdir().unwrap();
+    let root = std::fs::canonicalize(root.path()).unwrap();
+    std::fs::write(
+        root.join("manifest.yaml"),
+        "name: foo\nversion: 1.0.0\nflavours:\n  - name: ^\n",
+    )
+    .unwrap();
+    std::fs::create_dir_all(root.join(".svn")).unwrap();
+    env::set_current_dir(&root).unwrap();
+    env::set_var("DD_OS", "cent7_64");
+    let path = fake_command(&root, "svn-tag", 0);
+    let journal = root.join("history.jsonl");
+
+    let by_context = Install::default()
+        .context(Some("facility"))
+        .unwrap()
+        .journal(Some(&journal))
+        .package_root(Some(&root))
+        .build()
+        .execute();
+    let by_level = Install::default()
+        .level(Some("facility"))
+        .unwrap()
+        .journal(Some(&journal))
+        .package_root(Some(&root))
+        .build()
+        .execute();
+    env::set_var("PATH", path);
+
+    assert!(by_context.unwrap().unwrap().success());
+    assert!(by_level.unwrap().unwrap().success());
+    let records = Journal::new(&journal).records().unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].level.as_deref(), Some("facility"));
+    assert_eq!(records[1].level, records[0].level);
+}
+
+// hooks run around the install, and a failing hook aborts it
+#[test]
+#[serial]
+fn doit_given_hooks_runs_them_around_the_install() {
+    let root = tempfile::tempdir().unwrap();
+    let root = std::fs::canonicalize(root.path()).unwrap();
+    std::fs::write(
+        root.join("manifest.yaml"),
+        "name: foo\nversion: 1.0.0\nflavours:\n  - name: ^\n",
+    )
+    .unwrap();
+    env::set_current_dir(&root).unwrap();
+    env::set_var("DD_OS", "cent7_64");
+    env::remove_var("DD_SHOW");
+    let artifact = root.join("private").join("dist").join("foo-1.0.0");
+    std::fs::create_dir_all(&artifact).unwrap();
+    std::fs::write(artifact.join("foo.so"), "foo").unwrap();
+    let scratch = root.join("scratch");
+
+    let mut install = Install::default()
+        .no_build(true)
+        .to_dir(Some(&scratch))
+        .hooks(Hooks {
+            pre_install: vec!["echo $PK_HOOK $PK_HOOK_PACKAGE $PK_HOOK_FLAVORS >> hooks.log".to_string()],
+            post_install: vec!["echo $PK_HOOK $PK_HOOK_DIST_DIR >> hooks.log".to_string()],
+            on_failure: vec!["echo $PK_HOOK >> hooks.log".to_string()],
+            ..Hooks::default()
+        })
+        .package_root(Some(&root))
+        .build();
+    install.doit().unwrap();
+    assert_eq!(
+        std::fs::read_to_string(root.join("hooks.log")).unwrap(),
+        format!(
+            "pre-install foo ^\npost-install {}\n",
+            root.join("private").join("dist").display()
+        )
+    );
+    assert!(scratch.join("foo/1.0.0/cent7_64/foo.so").exists());
+
+    std::fs::remove_file(root.join("hooks.log")).unwrap();
+    std::fs::remove_dir_all(&scratch).unwrap();
+    install.hooks.pre_install = vec!["exit 1".to_string()];
+    let err = install.doit().unwrap_err().to_string();
+    assert!(err.contains("pre-install hook 'exit 1' failed"), "{}", err);
+    assert!(!scratch.exists());
+    assert_eq!(
+        std::fs::read_to_string(root.join("hooks.log")).unwrap(),
+        "on-failure\n"
+    );
+}
+
+// put a command which exits with code first on the PATH, returning the PATH to
+// restore
+#[cfg(unix)]
+fn fake_command(root: &std::path::Path, name: &str, code: i32) -> String {
+    use std::os::unix::fs::PermissionsExt;
+    let bin = root.join("bin");
+    std::fs::create_dir_all(&bin).unwrap();
+    std::fs::write(bin.join(name), format!("#!/bin/sh\nexit {}\n", code)).unwrap();
+    std::fs::set_permissions(bin.join(name), std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = env::var("PATH").unwrap_or_default();
+    env::set_var("PATH", format!("{}:{}", bin.display(), path));
+    path
+}
+
+// a failed build stops the install, rather than installing an earlier build's
+// artifacts
+#[test]
+#[serial]
+#[cfg(unix)]
+fn doit_given_failed_build_installs_nothing() {
+    let root = tempfile::tempdir().unwrap();
+    let root = std::fs::canonicalize(root.path()).unwrap();
+    std::fs::write(
+        root.join("manifest.yaml"),
+        "name: foo\nversion: 1.0.0\nflavours:\n  - name: ^\n",
+    )
+    .unwrap();
+    env::set_current_dir(&root).unwrap();
+    env::set_var("DD_OS", "cent7_64");
+    env::remove_var("DD_SHOW");
+    let artifact = root.join("private").join("dist").join("foo-1.0.0");
+    std::fs::create_dir_all(&artifact).unwrap();
+    std::fs::write(artifact.join("foo.so"), "foo").unwrap();
+    let path = fake_command(&root, "pk", 1);
+    let scratch = root.join("scratch");
+
+    let status = Install::default()
+        .clean(true)
+        .to_dir(Some(&scratch))
+        .hooks(Hooks {
+            pre_install: vec!["echo $PK_HOOK >> hooks.log".to_string()],
+            ..Hooks::default()
+        })
+        .package_root(Some(&root))
+        .build()
+        .execute();
+    env::set_var("PATH", path);
+
+    assert!(!status.unwrap().unwrap().success());
+    assert!(!scratch.exists());
+    assert!(!root.join("hooks.log").exists());
+}
+
+// a facility install's vcs tag is an install step, so the install hooks run
+// around it, and no dist artifacts are required
+#[test]
+#[serial]
+#[cfg(unix)]
+fn doit_given_facility_level_runs_install_hooks_around_the_tag() {
+    let root = tempfile::tempdir().unwrap();
+    let root = std::fs::canonicalize(root.path()).unwrap();
+    std::fs::write(
+        root.join("manifest.yaml"),
+        "name: foo\nversion: 1.0.0\nflavours:\n  - name: ^\n",
+    )
+    .unwrap();
+    std::fs::create_dir_all(root.join(".svn")).unwrap();
+    env::set_current_dir(&root).unwrap();
+    env::set_var("DD_OS", "cent7_64");
+    let path = fake_command(&root, "svn-tag", 0);
+
+    let status = Install::default()
+        .level(Some("facility"))
+        .unwrap()
+        .hooks(Hooks {
+            pre_build: vec!["echo $PK_HOOK >> hooks.log".to_string()],
+            pre_install: vec!["echo $PK_HOOK >> hooks.log".to_string()],
+            post_install: vec!["echo $PK_HOOK >> hooks.log".to_string()],
+            ..Hooks::default()
+        })
+        .package_root(Some(&root))
+        .build()
+        .execute();
+    env::set_var("PATH", path);
+
+    assert!(status.unwrap().unwrap().success());
+    assert_eq!(
+        std::fs::read_to_string(root.join("hooks.log")).unwrap(),
+        "pre-install\npost-install\n"
+    );
+}
+
+// the hooks of a facility install see the facility level, whether it was
+// requested via the context or the level
+#[test]
+#[serial]
+#[cfg(unix)]
+fn doit_given_facility_context_exports_the_facility_level_to_hooks() {
+    let root = tempfile::tempdir().unwrap();
+    let root = std::fs::canonicalize(root.path()).unwrap();
+    std::fs::write(
+        root.join("manifest.yaml"),
+        "name: foo\nversion: 1.0.0\nflavours:\n  - name: ^\n",
+    )
+    .unwrap();
+    std::fs::create_dir_all(root.join(".svn")).unwrap();
+    env::set_current_dir(&root).unwrap();
+    env::set_var("DD_OS", "cent7_64");
+    let path = fake_command(&root, "svn-tag", 0);
+
+    let status = Install::default()
+        .context(Some("facility"))
+        .unwrap()
+        .hooks(Hooks {
+            pre_install: vec!["echo level=$PK_HOOK_LEVEL >> hooks.log".to_string()],
+            ..Hooks::default()
+        })
+        .package_root(Some(&root))
+        .build()
+        .execute();
+    env::set_var("PATH", path);
+
+    assert!(status.unwrap().unwrap().success());
+    assert_eq!(
+        std::fs::read_to_string(root.join("hooks.log")).unwrap(),
+        "level=facility\n"
+    );
+}
+
+// configured policies are enforced before any commands are built, and only
+// overridable policies may be overridden
+#[test]
+#[serial]
+fn build_cmd_given_policies() {
+    let root = setup_manifest_dir2("nonflavored_git");
+    env::set_var("DD_SHOW", "DEV01");
+    env::set_var("DD_OS", "cent7_64");
+    let policies = crate::config::ConfigFile::parse(
+        r#"
+        [[policies]]
+        id = "shared-rocky9"
+        when = { context = "shared" }
+        require = { platforms = ["cent7_64", "rocky9_64"] }
+        overridable = true
+
+        [[policies]]
+        id = "no-work-at-show"
+        description = "--work is not permitted at show level"
+        when = { context = "shared", work = true }
+        require = { deny = true }
+        "#,
+    )
+    .unwrap()
+    .policies;
+    let install = |context: &str, work: bool, overrides: Option<Vec<&str>>| {
+        Install::default()
+            .no_build(true)
+            .context(Some(context))
+            .unwrap()
+            .work(work)
+            .policies(policies.clone())
+            .override_policies(overrides)
+            .package_root(Some(&root))
+            .build()
+            .build_cmd()
+    };
+
+    assert!(install("user", true, None).is_ok());
+
+    let err = install("shared", true, None).unwrap_err().to_string();
+    assert!(err.contains("[shared-rocky9] missing required platforms: rocky9_64"), "{}", err);
+    assert!(err.contains("[no-work-at-show] --work is not permitted at show level"), "{}", err);
+
+    let err = install("shared", true, Some(vec!["shared-rocky9", "no-work-at-show"]))
+        .unwrap_err()
+        .to_string();
+    assert!(!err.contains("shared-rocky9"), "{}", err);
+    assert!(err.contains("(this policy may not be overridden)"), "{}", err);
+
+    assert!(install("shared", false, Some(vec!["shared-rocky9"])).is_ok());
+    assert!(install("shared", false, Some(vec!["unknown"])).is_err());
+}
+
+// a policy for all sites also applies to all sites but one, and to a request
+// which covers every site
+#[test]
+#[serial]
+fn build_cmd_given_policy_for_all_sites_matches_exclusions() {
+    let root = setup_manifest_dir2("nonflavored_git");
+    env::set_var("DD_SHOW", "DEV01");
+    env::set_var("DD_OS", "cent7_64");
+    let policies = crate::config::ConfigFile::parse(
+        r#"
+        [[policies]]
+        id = "no-all-sites"
+        when = { sites = ["all"] }
+        require = { deny = true }
+        "#,
+    )
+    .unwrap()
+    .policies;
+    let install = |sites: Vec<&str>| {
+        Install::default()
+            .no_build(true)
+            .sites(Some(sites))
+            .policies(policies.clone())
+            .package_root(Some(&root))
+            .build()
+            .build_cmd()
+    };
+
+    assert!(install(vec!["all"]).is_err());
+    assert!(install(vec!["all", "!playa"]).is_err());
+    assert!(install(vec!["!playa"]).is_err());
+    assert!(install(vec!["hyderabad", "playa", "portland", "montreal", "vancouver"]).is_err());
+    assert!(install(vec!["playa", "portland"]).is_ok());
+}
+
+// the tests which a policy requires are run when the install is executed, not
+// when its commands are built
+#[test]
+#[serial]
+#[cfg(unix)]
+fn doit_given_tests_pass_policy_runs_the_tests() {
+    let root = tempfile::tempdir().unwrap();
+    let root = std::fs::canonicalize(root.path()).unwrap();
+    std::fs::write(
+        root.join("manifest.yaml"),
+        "name: foo\nversion: 1.0.0\nflavours:\n  - name: ^\n",
+    )
+    .unwrap();
+    env::set_current_dir(&root).unwrap();
+    env::set_var("DD_SHOW", "DEV01");
+    env::set_var("DD_OS", "cent7_64");
+    let policies = crate::config::ConfigFile::parse(
+        r#"
+        [[policies]]
+        id = "user-tested"
+        when = { context = "user" }
+        require = { tests_pass = true }
+        "#,
+    )
+    .unwrap()
+    .policies;
+    let path = fake_command(&root, "pk", 1);
+    let mut install = Install::default()
+        .no_build(true)
+        .policies(policies)
+        .package_root(Some(&root))
+        .build();
+
+    let cmd = install.build_cmd();
+    let result = install.execute();
+    env::set_var("PATH", path);
+
+    assert!(cmd.is_ok());
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("[user-tested] the package's tests did not pass"), "{}", err);
+}
+
+// authorization rules are checked against the injected user's groups before any
+// commands are built
+#[test]
+#[serial]
+fn build_cmd_given_authorization() {
+    let root = setup_manifest_dir2("nonflavored_git");
+    env::set_var("DD_SHOW", "DEV01");
+    env::set_var("DD_OS", "cent7_64");
+    let rules = crate::config::ConfigFile::parse(
+        r#"
+        [[authorization]]
+        context = "shared"
+        groups = ["show-leads"]
+        "#,
+    )
+    .unwrap()
+    .authorization;
+    let install = |context: &str, groups: Vec<&str>| {
+        Install::default()
+            .no_build(true)
+            .context(Some(context))
+            .unwrap()
+            .authorization(rules.clone())
+            .user_groups(Some(UserGroups::new("jdoe", groups)))
+            .package_root(Some(&root))
+            .build()
+    };
+
+    assert!(install("user", vec!["artists"]).build_cmd().is_ok());
+    let err = install("shared", vec!["artists"]).build_cmd().unwrap_err().to_string();
+    assert!(err.contains("User 'jdoe' is not authorized to install to level DEV01"), "{}", err);
+    assert!(err.ends_with("required: show-leads"), "{}", err);
+    assert!(install("shared", vec!["artists"]).uninstall_cmd().is_err());
+
+    assert!(install("shared", vec!["artists", "show-leads"]).build_cmd().is_ok());
+}
+
+// a rule for all sites also applies to all sites but one
+#[test]
+#[serial]
+fn build_cmd_given_authorization_for_all_sites_matches_exclusions() {
+    let root = setup_manifest_dir2("nonflavored_git");
+    env::set_var("DD_SHOW", "DEV01");
+    env::set_var("DD_OS", "cent7_64");
+    let rules = crate::config::ConfigFile::parse(
+        r#"
+        [[authorization]]
+        sites = ["all"]
+        groups = ["pipeline"]
+        "#,
+    )
+    .unwrap()
+    .authorization;
+    let install = |sites: Vec<&str>| {
+        Install::default()
+            .no_build(true)
+            .sites(Some(sites))
+            .authorization(rules.clone())
+            .user_groups(Some(UserGroups::new("jdoe", vec!["artists"])))
+            .package_root(Some(&root))
+            .build()
+            .build_cmd()
+    };
+
+    assert!(install(vec!["all"]).is_err());
+    assert!(install(vec!["all", "!hyderabad"]).is_err());
+    assert!(install(vec!["playa", "portland"]).is_ok());
+}
+
+#[test]
+#[serial]
+fn build_cmd_given_show_patterns_infers_show_from_cwd() {
+    let root = setup_manifest_dir2("nonflavored_git");
+    env::set_var("DD_OS", "cent7_64");
+    env::remove_var("DD_SHOW");
+    let pattern = format!("{}/egs/{{show}}/...", env::var("CARGO_MANIFEST_DIR").unwrap());
+
+    let result = Install::default()
+        .show_patterns(vec![ShowPattern::from(&pattern).unwrap()])
+        .context(Some("shared"))
+        .unwrap()
+        .package_root(Some(&root))
+        .build_cmd()
+        .unwrap();
+    assert!(result.iter().any(|c| c.contains("--level=manifests")), "{:?}", result);
+
+    // the builtin patterns know nothing of it
+    let result = Install::default()
+        .context(Some("shared"))
+        .unwrap()
+        .package_root(Some(&root))
+        .build_cmd();
+    assert!(result.is_err());
+}
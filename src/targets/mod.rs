@@ -10,5 +10,17 @@ pub use docs::Docs;
 pub mod test;
 pub use test::Test;
 
+pub mod promote;
+pub use promote::Promote;
+
 pub mod run;
 pub use run::Run;
+
+pub mod uninstall;
+pub use uninstall::Uninstall;
+
+pub mod pipeline;
+pub use pipeline::Pipeline;
+
+pub mod clean;
+pub use clean::Clean;
@@ -0,0 +1,324 @@
+//! Pipeline target
+//!
+//! Run several targets in one invocation, eg `pk-make pipeline build test install`
+//! or `pk-make pipeline build+test+install`. The stages share one set of options,
+//! and run in the order given, stopping at the first which fails. When the install
+//! follows a build, it installs the dist just built rather than building again.
+//! A summary of every stage is printed once the pipeline finishes.
+
+// Internal crate imports
+use crate::targets::{Build, Install, Test};
+use crate::traits::{Doit, Tabulate};
+use crate::utils::ExitStatus;
+use crate::PkMakeError;
+
+// External crate imports
+use anyhow::anyhow;
+use anyhow::Error as AnyError;
+use prettytable::{row, Table};
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// A stage of the pipeline
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Stage {
+    Build,
+    Test,
+    Install,
+}
+
+impl Stage {
+    /// Retrieve the name of the stage
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Build => "build",
+            Self::Test => "test",
+            Self::Install => "install",
+        }
+    }
+
+    /// Overrides the auto generated trait impl of from to provide a
+    /// fallible version
+    pub fn from<I>(input: I) -> Result<Self, PkMakeError>
+    where
+        I: AsRef<str>,
+    {
+        match input.as_ref().to_lowercase().as_str() {
+            "build" => Ok(Self::Build),
+            "test" => Ok(Self::Test),
+            "install" => Ok(Self::Install),
+            _ => Err(PkMakeError::InvalidStage(input.as_ref().to_string())),
+        }
+    }
+
+    /// Parse a list of stages, each of which may join several with `+`, eg
+    /// `["build+test", "install"]`. Stages may not repeat.
+    pub fn parse_list<I, S>(input: I) -> Result<Vec<Self>, PkMakeError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut stages = Vec::new();
+        for arg in input {
+            for name in arg.as_ref().split('+') {
+                let stage = Self::from(name)?;
+                if stages.contains(&stage) {
+                    return Err(PkMakeError::InvalidStage(format!("{} (repeated)", name)));
+                }
+                stages.push(stage);
+            }
+        }
+        if stages.is_empty() {
+            return Err(PkMakeError::InvalidStage(String::new()));
+        }
+        Ok(stages)
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Stage {
+    type Err = PkMakeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from(s)
+    }
+}
+
+impl TryFrom<&str> for Stage {
+    type Error = PkMakeError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::from(value)
+    }
+}
+
+impl TryFrom<String> for Stage {
+    type Error = PkMakeError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::from(value)
+    }
+}
+
+/// The outcome of a single stage
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Outcome {
+    /// The stage succeeded, or had nothing to do
+    Passed,
+    /// The pk commands exited unsuccessfully
+    Failed(String),
+    /// The stage could not be run
+    Error(String),
+    /// An earlier stage failed
+    Skipped,
+}
+
+impl Outcome {
+    fn from_result(result: &Result<Option<ExitStatus>, AnyError>) -> Self {
+        match result {
+            Ok(Some(status)) if !status.success() => Self::Failed(format!("{:?}", status)),
+            Ok(_) => Self::Passed,
+            Err(e) => Self::Error(e.to_string()),
+        }
+    }
+
+    /// Whether the stage passed
+    pub fn passed(&self) -> bool {
+        self == &Self::Passed
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Passed => write!(f, "passed"),
+            Self::Failed(status) => write!(f, "failed: {}", status),
+            Self::Error(e) => write!(f, "error: {}", e),
+            Self::Skipped => write!(f, "skipped"),
+        }
+    }
+}
+
+/// The summary of a pipeline run
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct PipelineSummary {
+    pub stages: Vec<(Stage, Outcome, Duration)>,
+}
+
+impl PipelineSummary {
+    /// The first stage which did not pass, along with its outcome
+    pub fn failure(&self) -> Option<(Stage, &Outcome)> {
+        self.stages
+            .iter()
+            .find(|(_, outcome, _)| !outcome.passed() && outcome != &Outcome::Skipped)
+            .map(|(stage, outcome, _)| (*stage, outcome))
+    }
+}
+
+impl Tabulate for PipelineSummary {
+    fn create_table(&self) -> Table {
+        let mut table = Table::new();
+        table.add_row(row!["Stage", "Outcome", "Duration"]);
+        for (stage, outcome, duration) in &self.stages {
+            table.add_row(row![
+                stage,
+                outcome,
+                format!("{:.1}s", duration.as_secs_f64())
+            ]);
+        }
+        table
+    }
+}
+
+/// Models a request to run several targets in turn
+#[derive(Debug, PartialEq, Eq, Default)]
+pub struct Pipeline {
+    pub stages: Vec<Stage>,
+    pub build: Build,
+    pub test: Test,
+    pub install: Install,
+    pub verbose: bool,
+}
+
+impl Doit for Pipeline {
+    type Err = AnyError;
+    /// Run each stage in order, stopping at the first failure, and print a summary
+    fn doit(&mut self) -> Result<(), Self::Err> {
+        if self.verbose {
+            self.tabulate();
+        }
+        let summary = self.run();
+        println!();
+        summary.tabulate();
+        match summary.failure() {
+            Some((stage, outcome)) => Err(anyhow!("Pipeline stopped at {}: {}", stage, outcome)),
+            None => Ok(()),
+        }
+    }
+
+    /// The commands of each stage, in order
+    fn build_cmd(&mut self) -> Result<Vec<String>, Self::Err> {
+        self.prepare();
+        let mut cmd = Vec::new();
+        for stage in self.stages.clone() {
+            cmd.extend(match stage {
+                Stage::Build => self.build.build_cmd()?,
+                Stage::Test => self.test.build_cmd()?,
+                Stage::Install => self.install.build_cmd()?,
+            });
+        }
+        Ok(cmd)
+    }
+}
+
+//
+// Public methods
+//
+impl Pipeline {
+    /// Run each stage in order, returning the summary. Once a stage fails, the
+    /// remaining stages are skipped.
+    pub fn run(&mut self) -> PipelineSummary {
+        self.prepare();
+        let mut summary = PipelineSummary::default();
+        let mut failed = false;
+        for stage in self.stages.clone() {
+            if failed {
+                summary.stages.push((stage, Outcome::Skipped, Duration::default()));
+                continue;
+            }
+            println!("==> {}", stage);
+            let clock = Instant::now();
+            let result = match stage {
+                Stage::Build => self.build.execute(),
+                Stage::Test => self.test.execute(),
+                Stage::Install => self.install.execute(),
+            };
+            let outcome = Outcome::from_result(&result);
+            failed = !outcome.passed();
+            summary.stages.push((stage, outcome, clock.elapsed()));
+        }
+        summary
+    }
+
+    /// Set the stages to run
+    pub fn stages(&mut self, input: Vec<Stage>) -> &mut Self {
+        self.stages = input;
+        self
+    }
+
+    /// Set the build stage
+    pub fn build_target(&mut self, input: Build) -> &mut Self {
+        self.build = input;
+        self
+    }
+
+    /// Set the test stage
+    pub fn test_target(&mut self, input: Test) -> &mut Self {
+        self.test = input;
+        self
+    }
+
+    /// Set the install stage
+    pub fn install_target(&mut self, input: Install) -> &mut Self {
+        self.install = input;
+        self
+    }
+
+    /// Set verbose
+    pub fn verbose(&mut self, input: bool) -> &mut Self {
+        self.verbose = input;
+        self
+    }
+
+    /// Terminate a chain of calls with a build to return an owned instance.
+    pub fn build(&mut self) -> Self {
+        let mut default = Self::default();
+        std::mem::swap(self, &mut default);
+        default
+    }
+}
+
+//
+// Private methods
+//
+impl Pipeline {
+    // An install which follows the build installs the dist just built
+    fn prepare(&mut self) {
+        let position = |target| self.stages.iter().position(|s| s == &target);
+        if let (Some(build), Some(install)) = (position(Stage::Build), position(Stage::Install)) {
+            if build < install {
+                self.install.no_build(true);
+            }
+        }
+    }
+}
+
+//
+// Tabulate implementation
+//
+impl Tabulate for Pipeline {
+    fn create_table(&self) -> Table {
+        let mut table = Table::new();
+        table.add_row(row!["Field", "Value"]);
+        table.add_row(row![
+            "stages",
+            self.stages.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(" ")
+        ]);
+        table.add_row(row!["verbose", self.verbose]);
+        table
+    }
+}
+
+//
+// Import Tests
+//
+#[cfg(test)]
+#[path = "./pipeline_test.rs"]
+mod pipeline_test;
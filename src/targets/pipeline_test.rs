@@ -0,0 +1,146 @@
+use super::*;
+use crate::policy::{Policy, PolicyRequirement};
+use crate::utils::setup_manifest_dir;
+use serial_test::serial;
+use std::env;
+
+//
+// Stage
+//
+
+#[test]
+fn stage_parse_list_given_separate_args() {
+    let result = Stage::parse_list(vec!["build", "test", "install"]).unwrap();
+    assert_eq!(result, vec![Stage::Build, Stage::Test, Stage::Install]);
+}
+
+#[test]
+fn stage_parse_list_given_joined_args() {
+    let result = Stage::parse_list(vec!["Build+test", "install"]).unwrap();
+    assert_eq!(result, vec![Stage::Build, Stage::Test, Stage::Install]);
+}
+
+#[test]
+fn stage_parse_list_preserves_order() {
+    let result = Stage::parse_list(vec!["test+build"]).unwrap();
+    assert_eq!(result, vec![Stage::Test, Stage::Build]);
+}
+
+#[test]
+fn stage_parse_list_given_bad_input_errors() {
+    assert!(Stage::parse_list(vec!["build", "docs"]).is_err());
+    assert!(Stage::parse_list(vec!["build+"]).is_err());
+    assert!(Stage::parse_list(Vec::<&str>::new()).is_err());
+}
+
+#[test]
+fn stage_parse_list_given_repeat_errors() {
+    let err = Stage::parse_list(vec!["build+test", "build"]).unwrap_err();
+    assert_eq!(err.to_string(), PkMakeError::InvalidStage("build (repeated)".to_string()).to_string());
+}
+
+//
+// Pipeline
+//
+
+#[test]
+fn can_modify_and_build() {
+    let result = Pipeline::default()
+        .stages(vec![Stage::Build, Stage::Install])
+        .verbose(true)
+        .build();
+    let expected = Pipeline {
+        stages: vec![Stage::Build, Stage::Install],
+        build: Build::default(),
+        test: Test::default(),
+        install: Install::default(),
+        verbose: true,
+    };
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn prepare_given_build_before_install_skips_install_build() {
+    let mut pipeline = Pipeline::default()
+        .stages(vec![Stage::Build, Stage::Test, Stage::Install])
+        .build();
+    pipeline.prepare();
+    assert!(pipeline.install.no_build);
+}
+
+#[test]
+fn prepare_given_install_alone_builds() {
+    let mut pipeline = Pipeline::default()
+        .stages(vec![Stage::Test, Stage::Install])
+        .build();
+    pipeline.prepare();
+    assert!(!pipeline.install.no_build);
+}
+
+#[test]
+#[serial]
+fn build_cmd_chains_stage_commands() -> Result<(), Box<dyn std::error::Error>> {
+    setup_manifest_dir(false);
+    env::set_var("DD_SHOW", "DEV01");
+    env::set_var("DD_OS", "cent7_64");
+
+    let result = Pipeline::default()
+        .stages(vec![Stage::Test, Stage::Test])
+        .build()
+        .build_cmd()?;
+    assert_eq!(
+        result,
+        vec!["pk run-recipe test".to_string(), "pk run-recipe test".to_string()]
+    );
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn run_given_failing_stage_skips_the_rest() {
+    setup_manifest_dir(false);
+    env::set_var("DD_SHOW", "DEV01");
+    env::set_var("DD_OS", "cent7_64");
+    let deny = Policy {
+        id: "frozen".to_string(),
+        description: Some("installs are frozen".to_string()),
+        when: Default::default(),
+        require: PolicyRequirement {
+            deny: true,
+            ..Default::default()
+        },
+        overridable: false,
+    };
+    let mut pipeline = Pipeline::default()
+        .stages(vec![Stage::Test, Stage::Install, Stage::Build])
+        .test_target(Test::default().dry_run(true).build())
+        .install_target(Install::default().dry_run(true).policies(vec![deny]).build())
+        .build();
+
+    let summary = pipeline.run();
+    let outcomes = summary
+        .stages
+        .iter()
+        .map(|(stage, outcome, _)| (*stage, outcome.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(outcomes[0], (Stage::Test, Outcome::Passed));
+    assert_eq!(outcomes[1].0, Stage::Install);
+    assert!(
+        outcomes[1].1.to_string().contains("installs are frozen"),
+        "{}",
+        outcomes[1].1
+    );
+    assert_eq!(outcomes[2], (Stage::Build, Outcome::Skipped));
+    assert_eq!(summary.failure().map(|(stage, _)| stage), Some(Stage::Install));
+}
+
+#[test]
+fn summary_failure_given_all_passed_is_none() {
+    let summary = PipelineSummary {
+        stages: vec![
+            (Stage::Build, Outcome::Passed, Duration::default()),
+            (Stage::Test, Outcome::Passed, Duration::default()),
+        ],
+    };
+    assert_eq!(summary.failure(), None);
+}
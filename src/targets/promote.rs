@@ -0,0 +1,453 @@
+//! Promote target
+//!
+//! Install an already validated work install at show level, reusing the
+//! existing dist artifacts rather than rebuilding them. Only the `pk install`
+//! steps are run.
+//!
+//! The install itself is carried out by an `Install` without a build step, so a
+//! promote is subject to the same show registry, authorization and policy checks,
+//! runs the same install hooks, and is recorded in the history journal.
+
+// Internal crate imports
+use crate::authorization::{AuthRule, UserGroups};
+use crate::BuildEnv;
+use crate::dist::verify_artifacts;
+use crate::Flavor;
+use crate::hooks::Hooks;
+use crate::Level;
+use crate::ManifestInfo;
+use crate::Platform;
+use crate::PlatformRegistry;
+use crate::policy::Policy;
+use crate::named_site::SiteRegistry;
+use crate::show_registry::ShowRegistry;
+use crate::targets::Install;
+use crate::traits::{Doit, Tabulate};
+
+// External crate imports
+use anyhow::anyhow;
+use anyhow::Error as AnyError;
+// IndexSet preserves insertion order
+use indexmap::IndexSet as HashSet;
+use prettytable::{row, Table};
+use std::convert::TryInto;
+use std::path::PathBuf;
+
+/// Models a request to promote a work install to show level
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Promote {
+    pub from: Option<Level>,
+    pub to: Option<Level>,
+    pub dry_run: bool,
+    pub verbose: bool,
+    pub dist_dir: Option<String>,
+    /// The sites as the user supplied them. The install expands groups and
+    /// exclusions against the site registry.
+    pub requested_sites: Vec<String>,
+    pub platforms: Option<HashSet<Platform>>,
+    pub flavors: Option<HashSet<Flavor>>,
+    pub max_jobs: Option<u8>,
+    pub package_root: Option<PathBuf>,
+    pub site_registry: SiteRegistry,
+    pub platform_registry: PlatformRegistry,
+    pub show_registry: Option<ShowRegistry>,
+    pub allow_unknown_show: bool,
+    pub policies: Vec<Policy>,
+    pub override_policies: Vec<String>,
+    pub authorization: Vec<AuthRule>,
+    pub user_groups: Option<UserGroups>,
+    pub hooks: Hooks,
+    pub journal: Option<PathBuf>,
+}
+
+impl Doit for Promote {
+    type Err = AnyError;
+    /// Verify the promote, then carry out the install of the dist artifacts at the
+    /// target level
+    fn doit(&mut self) -> Result<(), Self::Err> {
+        if self.verbose {
+            self.tabulate();
+        }
+        self.install()?.execute()?;
+        Ok(())
+    }
+
+    /// Generate a pk install command for each flavor, at the target level, after
+    /// verifying that the dist artifacts exist, and are not empty, for the manifest's
+    /// version.
+    fn build_cmd(&mut self) -> Result<Vec<String>, Self::Err> {
+        self.install()?.build_cmd()
+    }
+}
+
+//
+// Private Methods
+//
+impl Promote {
+    // Verify the levels and dist artifacts, and return the Install which carries out
+    // the promote, without a build step
+    fn install(&self) -> Result<Install, AnyError> {
+        let to = self.verify_levels()?;
+        let build_env = BuildEnv::with_platforms(self.get_package_root(), &self.platform_registry)?;
+        let manifest_info = ManifestInfo::from_path(build_env.manifest.as_ref())?;
+        let flavors = self.get_flavors(&manifest_info)?;
+        // pk install runs from the package root, so relative dist dirs are too
+        let dist_dir = self
+            .dist_dir
+            .as_ref()
+            .map(|d| build_env.package_root.join(d))
+            .unwrap_or_else(|| build_env.dist_dir.clone());
+
+        let artifacts = flavors
+            .iter()
+            .map(|f| dist_dir.join(manifest_info.package_id(f)))
+            .collect::<Vec<_>>();
+        let package = format!("{}-{}", manifest_info.name(), manifest_info.version());
+        verify_artifacts(&package, &artifacts)
+            .map_err(|e| anyhow!("Unable to promote {}. {}", package, e))?;
+
+        Ok(Install {
+            dry_run: self.dry_run,
+            verbose: self.verbose,
+            no_build: true,
+            dist_dir: Some(dist_dir.display().to_string()),
+            level: Some(to.clone()),
+            sites: None,
+            requested_sites: self.requested_sites.clone(),
+            platforms: self.platforms.clone(),
+            flavors: Some(flavors.into_iter().collect()),
+            max_jobs: self.max_jobs,
+            package_root: Some(build_env.package_root.clone()),
+            site_registry: self.site_registry.clone(),
+            platform_registry: self.platform_registry.clone(),
+            show_registry: self.show_registry.clone(),
+            allow_unknown_show: self.allow_unknown_show,
+            policies: self.policies.clone(),
+            override_policies: self.override_policies.clone(),
+            authorization: self.authorization.clone(),
+            user_groups: self.user_groups.clone(),
+            hooks: self.hooks.clone(),
+            journal: self.journal.clone(),
+            ..Install::default()
+        })
+    }
+
+    // the from level must be a work level, and the to level the same level without .work.
+    // returns the to level.
+    fn verify_levels(&self) -> Result<&Level, AnyError> {
+        let from = self
+            .from
+            .as_ref()
+            .ok_or_else(|| anyhow!("The level to promote from must be supplied"))?;
+        let to = self
+            .to
+            .as_ref()
+            .ok_or_else(|| anyhow!("The level to promote to must be supplied"))?;
+        let expected = match from {
+            Level::Show {
+                show,
+                path,
+                work: true,
+            } => Level::Show {
+                show: show.clone(),
+                path: path.clone(),
+                work: false,
+            },
+            _ => return Err(anyhow!("Can only promote from a work level, not '{}'", from)),
+        };
+        if to != &expected {
+            return Err(anyhow!(
+                "Cannot promote from '{}' to '{}'. Expected to promote to '{}'",
+                from,
+                to,
+                expected
+            ));
+        }
+        Ok(to)
+    }
+
+    // the requested flavors, which must be defined by the manifest, or all of the
+    // manifest's flavors
+    fn get_flavors(&self, manifest_info: &ManifestInfo) -> Result<Vec<Flavor>, AnyError> {
+        match self.flavors {
+            None => Ok(manifest_info.flavors.clone()),
+            Some(ref flavors) => {
+                for flavor in flavors {
+                    if !manifest_info.flavors.contains(flavor) {
+                        return Err(anyhow!(
+                            "Flavor '{}' is not defined by the manifest for {}-{}",
+                            flavor.as_str(),
+                            manifest_info.name(),
+                            manifest_info.version()
+                        ));
+                    }
+                }
+                Ok(flavors.iter().cloned().collect())
+            }
+        }
+    }
+
+    // retreive the package root directory
+    fn get_package_root(&self) -> &std::path::Path {
+        self.package_root
+            .as_deref()
+            .unwrap_or_else(|| std::path::Path::new("."))
+    }
+}
+
+//
+// public methods - primarily setters
+//
+impl Promote {
+    /// Set the work level to promote from, eg `dev01.work`
+    pub fn from<I>(&mut self, input: Option<I>) -> Result<&mut Self, AnyError>
+    where
+        I: TryInto<Level>,
+        I::Error: std::fmt::Display,
+    {
+        self.from = match input {
+            None => None,
+            Some(level) => Some(level.try_into().map_err(|e| anyhow!("{}", e))?),
+        };
+        Ok(self)
+    }
+
+    /// Set the level to promote to, eg `dev01`
+    pub fn to<I>(&mut self, input: Option<I>) -> Result<&mut Self, AnyError>
+    where
+        I: TryInto<Level>,
+        I::Error: std::fmt::Display,
+    {
+        self.to = match input {
+            None => None,
+            Some(level) => Some(level.try_into().map_err(|e| anyhow!("{}", e))?),
+        };
+        Ok(self)
+    }
+
+    /// Set the dry_run field.
+    pub fn dry_run(&mut self, input: bool) -> &mut Self {
+        self.dry_run = input;
+        self
+    }
+
+    /// Set verbose state
+    pub fn verbose(&mut self, input: bool) -> &mut Self {
+        self.verbose = input;
+        self
+    }
+
+    /// Optionally set the directory holding the dist artifacts. Defaults to the
+    /// dist_dir of the BuildEnv.
+    pub fn dist_dir<I>(&mut self, input: Option<I>) -> &mut Self
+    where
+        I: Into<String>,
+    {
+        self.dist_dir = input.map(|dir| dir.into());
+        self
+    }
+
+    /// Set the registry which sites are looked up in, and groups expanded against.
+    pub fn site_registry(&mut self, input: SiteRegistry) -> &mut Self {
+        self.site_registry = input;
+        self
+    }
+
+    /// Set the sites to install to. Groups and exclusions are expanded via the
+    /// SiteRegistry when the promote is carried out.
+    pub fn sites<I>(&mut self, value: Option<Vec<I>>) -> &mut Self
+    where
+        I: AsRef<str>,
+    {
+        self.requested_sites = value
+            .map(|sites| sites.iter().map(|s| s.as_ref().to_string()).collect())
+            .unwrap_or_default();
+        self
+    }
+
+    /// Set the platforms to install
+    pub fn platforms<I>(&mut self, value: Option<Vec<I>>) -> Result<&mut Self, AnyError>
+    where
+        I: AsRef<str>,
+    {
+        self.platforms = match value {
+            None => None,
+            Some(plats) => Some(
+                plats
+                    .iter()
+                    .map(|i_val| self.platform_registry.parse(i_val.as_ref()))
+                    .collect::<Result<HashSet<_>, _>>()
+                    .map_err(|e| anyhow!("failed to convert to platform: {}", e))?,
+            ),
+        };
+        Ok(self)
+    }
+
+    /// Set the flavors to promote. Defaults to all of the manifest's flavors.
+    pub fn flavors<I>(&mut self, value: Option<Vec<I>>) -> Result<&mut Self, AnyError>
+    where
+        I: TryInto<Flavor>,
+    {
+        self.flavors = match value {
+            None => None,
+            Some(flavors) => Some(
+                flavors
+                    .into_iter()
+                    .map(|i_val| i_val.try_into())
+                    .collect::<Result<HashSet<_>, _>>()
+                    .map_err(|_| anyhow!("failed to convert one or more flavors"))?,
+            ),
+        };
+        Ok(self)
+    }
+
+    /// Set the maximum number of workers used by pk install
+    pub fn max_jobs(&mut self, input: Option<u8>) -> &mut Self {
+        self.max_jobs = input;
+        self
+    }
+
+    /// Update the package root, which is where we look for the manifest and vcs directories. By
+    /// default, we look in the current working directory...
+    pub fn package_root<I>(&mut self, input: Option<I>) -> &mut Self
+    where
+        I: Into<std::path::PathBuf>,
+    {
+        self.package_root = input.map(|proot| proot.into());
+        self
+    }
+
+    /// New up a Promote whose platforms, including the host platform, are looked up
+    /// in the supplied registry, rather than the builtin one.
+    pub fn with_platform_registry(platform_registry: PlatformRegistry) -> Self {
+        Self {
+            platform_registry,
+            ..Self::default()
+        }
+    }
+
+    /// Set the registry of known shows which the target level's show is verified
+    /// against, and return a mutable reference to self, per the builder pattern.
+    pub fn show_registry(&mut self, input: Option<ShowRegistry>) -> &mut Self {
+        self.show_registry = input;
+        self
+    }
+
+    /// Permit a show which is not in the show registry
+    pub fn allow_unknown_show(&mut self, value: bool) -> &mut Self {
+        self.allow_unknown_show = value;
+        self
+    }
+
+    /// Set the policies which the promote must satisfy, and return a mutable
+    /// reference to self, per the builder pattern.
+    pub fn policies(&mut self, input: Vec<Policy>) -> &mut Self {
+        self.policies = input;
+        self
+    }
+
+    /// Set the ids of the overridable policies whose violations are waived
+    pub fn override_policies<I>(&mut self, input: Option<Vec<I>>) -> &mut Self
+    where
+        I: Into<String>,
+    {
+        self.override_policies = input
+            .map(|ids| ids.into_iter().map(|id| id.into()).collect())
+            .unwrap_or_default();
+        self
+    }
+
+    /// Set the rules restricting which Unix groups may install to each level and
+    /// site, and return a mutable reference to self, per the builder pattern.
+    pub fn authorization(&mut self, input: Vec<AuthRule>) -> &mut Self {
+        self.authorization = input;
+        self
+    }
+
+    /// Set the user and groups which the authorization rules are checked against.
+    /// None, the default, looks up the current user via `id`.
+    pub fn user_groups(&mut self, input: Option<UserGroups>) -> &mut Self {
+        self.user_groups = input;
+        self
+    }
+
+    /// Set the hooks which run around the install, and return a mutable reference
+    /// to self, per the builder pattern.
+    pub fn hooks(&mut self, input: Hooks) -> &mut Self {
+        self.hooks = input;
+        self
+    }
+
+    /// Set the history journal which the promote (other than a dry run) is recorded
+    /// in. None disables the journal.
+    pub fn journal<I>(&mut self, input: Option<I>) -> &mut Self
+    where
+        I: Into<PathBuf>,
+    {
+        self.journal = input.map(|path| path.into());
+        self
+    }
+
+    /// Terminate a chain of calls with a build to return an owned instance.
+    pub fn build(&mut self) -> Self {
+        let mut default = Self::default();
+        std::mem::swap(self, &mut default);
+        default
+    }
+}
+
+//
+// Tabulate implementation
+//
+impl Tabulate for Promote {
+    fn create_table(&self) -> Table {
+        let level = |l: &Option<Level>| {
+            l.as_ref()
+                .map(|l| l.to_string())
+                .unwrap_or_else(|| "None".to_string())
+        };
+        let mut table = Table::new();
+        table.add_row(row!["Field", "Value"]);
+        table.add_row(row!["from", level(&self.from)]);
+        table.add_row(row!["to", level(&self.to)]);
+        table.add_row(row!["dry_run", self.dry_run]);
+        table.add_row(row!["verbose", self.verbose]);
+        table.add_row(row!["dist_dir", self.dist_dir.as_deref().unwrap_or("None")]);
+        table.add_row(row![
+            "sites",
+            if self.requested_sites.is_empty() {
+                "None".to_string()
+            } else {
+                self.requested_sites.join("\n")
+            }
+        ]);
+        table.add_row(row![
+            "platforms",
+            self.platforms
+                .as_ref()
+                .map(|v| v.iter().map(|x| x.as_str()).collect::<Vec<_>>().join("\n"))
+                .unwrap_or_else(|| "None".to_string())
+        ]);
+        table.add_row(row![
+            "flavors",
+            self.flavors
+                .as_ref()
+                .map(|v| v.iter().map(|x| x.as_str()).collect::<Vec<_>>().join("\n"))
+                .unwrap_or_else(|| "None".to_string())
+        ]);
+        table.add_row(row![
+            "max_jobs",
+            self.max_jobs
+                .map(|v| v.to_string())
+                .unwrap_or_else(|| "None".to_string())
+        ]);
+        table
+    }
+}
+
+//
+// Import Tests
+//
+#[cfg(test)]
+#[path = "./promote_test.rs"]
+mod promote_test;
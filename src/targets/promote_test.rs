@@ -0,0 +1,255 @@
+use super::*;
+use crate::utils::{setup_manifest_dir, setup_manifest_dir2};
+use serial_test::serial;
+use std::env;
+use tempfile::tempdir;
+
+#[test]
+fn can_build_default() {
+    let result = Promote::default();
+    let expected = Promote {
+        from: None,
+        to: None,
+        dry_run: false,
+        verbose: false,
+        dist_dir: None,
+        requested_sites: Vec::new(),
+        platforms: None,
+        flavors: None,
+        max_jobs: None,
+        package_root: None,
+        site_registry: SiteRegistry::builtin(),
+        platform_registry: PlatformRegistry::builtin(),
+        show_registry: None,
+        allow_unknown_show: false,
+        policies: Vec::new(),
+        override_policies: Vec::new(),
+        authorization: Vec::new(),
+        user_groups: None,
+        hooks: Hooks::default(),
+        journal: None,
+    };
+    assert_eq!(result, expected);
+}
+
+#[test]
+#[serial]
+fn build_cmd_given_existing_artifacts() {
+    let root = setup_manifest_dir2("nonflavored_git");
+    env::set_var("DD_OS", "cent7_64");
+    let dist = tempdir().unwrap();
+    std::fs::create_dir(dist.path().join("makebridge-3.1.0")).unwrap();
+    std::fs::write(dist.path().join("makebridge-3.1.0").join("manifest.yaml"), "").unwrap();
+
+    let result = Promote::default()
+        .from(Some("DEV01.work"))
+        .unwrap()
+        .to(Some("DEV01"))
+        .unwrap()
+        .dist_dir(Some(dist.path().to_str().unwrap()))
+        .sites(Some(vec!["portland"]))
+        .package_root(Some(&root))
+        .build_cmd();
+    let expected = vec![format!(
+        "pk install --level=DEV01 --site=portland --platform=cent7_64 {}",
+        dist.path().join("makebridge-3.1.0").display()
+    )];
+    assert_eq!(result.unwrap(), expected);
+}
+
+#[test]
+#[serial]
+fn build_cmd_given_missing_artifacts_fails() {
+    let root = setup_manifest_dir(true);
+    env::set_var("DD_OS", "cent7_64");
+    let dist = tempdir().unwrap();
+    let built = dist.path().join("vrayddbase-5.0.8_vray4.0.29259_for_maya2018");
+    std::fs::create_dir(&built).unwrap();
+    std::fs::write(built.join("manifest.yaml"), "").unwrap();
+    std::fs::create_dir(dist.path().join("vrayddbase-5.0.8_vray4.0.29635_for_maya2018")).unwrap();
+
+    let result = Promote::default()
+        .from(Some("DEV01.work"))
+        .unwrap()
+        .to(Some("DEV01"))
+        .unwrap()
+        .dist_dir(Some(dist.path().to_str().unwrap()))
+        .flavors(Some(vec![
+            "vray4.0.29259_for_maya2018",
+            "vray4.0.29567_for_maya2018",
+            "vray4.0.29635_for_maya2018",
+        ]))
+        .unwrap()
+        .package_root(Some(&root))
+        .build_cmd();
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("vrayddbase-5.0.8_vray4.0.29567_for_maya2018 (does not exist)"));
+    assert!(err.contains("vrayddbase-5.0.8_vray4.0.29635_for_maya2018 (is empty)"));
+    assert!(!err.contains("29259"));
+}
+
+#[test]
+#[serial]
+fn build_cmd_given_flavor_not_in_manifest_fails() {
+    let root = setup_manifest_dir(true);
+    env::set_var("DD_OS", "cent7_64");
+
+    let result = Promote::default()
+        .from(Some("DEV01.work"))
+        .unwrap()
+        .to(Some("DEV01"))
+        .unwrap()
+        .flavors(Some(vec!["foo"]))
+        .unwrap()
+        .package_root(Some(&root))
+        .build_cmd();
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("Flavor 'foo' is not defined by the manifest"));
+}
+
+#[test]
+fn build_cmd_given_mismatched_levels_fails() {
+    for (from, to) in &[
+        ("DEV01", "DEV01"),
+        ("DEV01.work", "DEV02"),
+        ("DEV01.work", "DEV01.work"),
+        ("DEV01.rd.work", "DEV01"),
+        ("facility", "DEV01"),
+    ] {
+        let result = Promote::default()
+            .from(Some(*from))
+            .unwrap()
+            .to(Some(*to))
+            .unwrap()
+            .build_cmd();
+        assert!(result.is_err(), "expected {} -> {} to fail", from, to);
+    }
+}
+
+// a package with built dist artifacts, to promote from DEV01.work
+fn setup_promote() -> (tempfile::TempDir, PathBuf) {
+    let dir = tempdir().unwrap();
+    let root = std::fs::canonicalize(dir.path()).unwrap();
+    std::fs::write(
+        root.join("manifest.yaml"),
+        "name: foo\nversion: 1.0.0\nflavours:\n  - name: ^\n",
+    )
+    .unwrap();
+    let artifact = root.join("private").join("dist").join("foo-1.0.0");
+    std::fs::create_dir_all(&artifact).unwrap();
+    std::fs::write(artifact.join("foo.so"), "foo").unwrap();
+    env::set_current_dir(&root).unwrap();
+    env::set_var("DD_OS", "cent7_64");
+    (dir, root)
+}
+
+fn promote(root: &std::path::Path) -> Promote {
+    Promote::default()
+        .from(Some("DEV01.work"))
+        .unwrap()
+        .to(Some("DEV01"))
+        .unwrap()
+        .package_root(Some(root))
+        .build()
+}
+
+// a relative dist dir is resolved against the package root, as pk install
+// resolves it, rather than the current directory
+#[test]
+#[serial]
+fn build_cmd_given_relative_dist_dir_resolves_it_against_the_package_root() {
+    let (_dir, root) = setup_promote();
+    let elsewhere = tempdir().unwrap();
+    env::set_current_dir(elsewhere.path()).unwrap();
+    std::fs::create_dir_all(elsewhere.path().join("private").join("dist")).unwrap();
+
+    let result = promote(&root)
+        .dist_dir(Some("private/dist"))
+        .sites(Some(vec!["portland"]))
+        .build_cmd();
+    let expected = vec![format!(
+        "pk install --level=DEV01 --site=portland --platform=cent7_64 {}",
+        root.join("private/dist/foo-1.0.0").display()
+    )];
+    assert_eq!(result.unwrap(), expected);
+}
+
+// a promote is subject to the show registry, authorization and policies of an
+// install at the target level
+#[test]
+#[serial]
+fn build_cmd_given_install_checks_enforces_them() {
+    let (_dir, root) = setup_promote();
+    let config = crate::config::ConfigFile::parse(
+        r#"
+        [[authorization]]
+        context = "shared"
+        groups = ["show-leads"]
+
+        [[policies]]
+        id = "no-shared-promotes"
+        when = { context = "shared" }
+        require = { deny = true }
+        overridable = true
+        "#,
+    )
+    .unwrap();
+
+    let mut unknown = promote(&root);
+    unknown.show_registry(Some(ShowRegistry::new(vec!["dev02"])));
+    assert!(unknown.build_cmd().is_err());
+    unknown.allow_unknown_show(true);
+    assert!(unknown.build_cmd().is_ok());
+
+    let mut unauthorized = promote(&root);
+    unauthorized
+        .authorization(config.authorization.clone())
+        .user_groups(Some(UserGroups::new("jdoe", vec!["artists"])));
+    let err = unauthorized.build_cmd().unwrap_err().to_string();
+    assert!(err.contains("User 'jdoe' is not authorized to install to level DEV01"), "{}", err);
+
+    let mut denied = promote(&root);
+    denied.policies(config.policies.clone());
+    let err = denied.build_cmd().unwrap_err().to_string();
+    assert!(err.contains("[no-shared-promotes]"), "{}", err);
+    denied.override_policies(Some(vec!["no-shared-promotes"]));
+    assert!(denied.build_cmd().is_ok());
+}
+
+// a promote runs the install hooks, and is recorded in the history journal
+#[test]
+#[serial]
+#[cfg(unix)]
+fn doit_runs_install_hooks_and_records_history() {
+    use std::os::unix::fs::PermissionsExt;
+    let (_dir, root) = setup_promote();
+    let bin = root.join("bin");
+    std::fs::create_dir_all(&bin).unwrap();
+    std::fs::write(bin.join("pk"), "#!/bin/sh\nexit 0\n").unwrap();
+    std::fs::set_permissions(bin.join("pk"), std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = env::var("PATH").unwrap_or_default();
+    env::set_var("PATH", format!("{}:{}", bin.display(), path));
+    let journal = root.join("history.jsonl");
+
+    let result = promote(&root)
+        .hooks(Hooks {
+            pre_install: vec!["echo $PK_HOOK >> hooks.log".to_string()],
+            post_install: vec!["echo $PK_HOOK >> hooks.log".to_string()],
+            ..Hooks::default()
+        })
+        .journal(Some(&journal))
+        .doit();
+    env::set_var("PATH", path);
+
+    result.unwrap();
+    assert_eq!(
+        std::fs::read_to_string(root.join("hooks.log")).unwrap(),
+        "pre-install\npost-install\n"
+    );
+    let records = crate::history::Journal::new(&journal).records().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].level, Some("DEV01".to_string()));
+    assert!(records[0].succeeded());
+}
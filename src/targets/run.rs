@@ -9,6 +9,7 @@ use crate::traits::Tabulate;
 use crate::utils::exec_cmd;
 use crate::Flavor;
 use crate::Platform;
+use crate::PlatformRegistry;
 use crate::BuildEnv;
 
 // External crate imports
@@ -28,6 +29,7 @@ pub struct Run {
     platforms: Option<HashSet<Platform>>,
     flavors: Option<HashSet<Flavor>>,
     vars: Vec<String>,
+    pub platform_registry: PlatformRegistry,
 }
 
 //
@@ -126,7 +128,7 @@ impl Doit for Run {
             .package_root
             .as_deref()
             .unwrap_or_else(|| std::path::Path::new("."));
-        let build_env = BuildEnv::new(package_root)?;
+        let build_env = BuildEnv::with_platforms(package_root, &self.platform_registry)?;
 
         let recipe_target = self.get_recipe_target_str();
         let recipe_args_str = self.get_recipe_args_str();
@@ -149,6 +151,7 @@ impl Default for Run {
             platforms: None,
             flavors: None,
             vars: Vec::new(),
+            platform_registry: PlatformRegistry::builtin(),
         }
     }
 }
@@ -180,34 +183,15 @@ impl Run {
     /// ```
     pub fn platform<I>(&mut self, value: Option<I>) -> Result<&mut Self, AnyError>
     where
-        I: TryInto<Platform> + std::fmt::Debug + Clone,
+        I: AsRef<str>,
     {
         match value {
-            Some(val) => match self.platforms {
-                Some(ref mut platforms) => {
-                    let val_cpy = val.clone();
-                    //platforms.insert(val.into());
-                    match val.try_into() {
-                        Ok(v) => platforms.insert(v),
-                        Err(_) => {
-                            return Err(anyhow!("Error converting {:?} into Platform", val_cpy))
-                        }
-                    };
-                }
-                None => {
-                    let val_cpy = val.clone();
-                    let mut hset = HashSet::new();
-                    //hset.insert(val.into());
-                    //
-                    match val.try_into() {
-                        Ok(v) => hset.insert(v),
-                        Err(_) => {
-                            return Err(anyhow!("Error converting {:?} into Platform", val_cpy))
-                        }
-                    };
-                    self.platforms = Some(hset);
-                }
-            },
+            Some(val) => {
+                let platform = self.platform_registry.parse(val.as_ref())?;
+                self.platforms
+                    .get_or_insert_with(HashSet::new)
+                    .insert(platform);
+            }
             None => self.platforms = None,
         }
         Ok(self)
@@ -227,15 +211,17 @@ impl Run {
     /// ```
     pub fn platforms<I>(&mut self, value: Option<Vec<I>>) -> Result<&mut Self, AnyError>
     where
-        I: TryInto<Platform>,
+        I: AsRef<str>,
     {
         match value {
             None => self.platforms = None,
             Some(plats) => {
-                let plats: Result<Vec<_>, _> =
-                    plats.into_iter().map(|i_val| i_val.try_into()).collect();
+                let plats: Result<Vec<_>, _> = plats
+                    .iter()
+                    .map(|i_val| self.platform_registry.parse(i_val.as_ref()))
+                    .collect();
                 match plats {
-                    Err(_) => return Err(anyhow!("failed to convert to platform")),
+                    Err(e) => return Err(anyhow!("failed to convert to platform: {}", e)),
                     Ok(val) => match self.platforms {
                         Some(ref mut platforms) => {
                             for v in val {
@@ -377,6 +363,15 @@ impl Run {
         self
     }
 
+    /// New up a Run whose platforms, including the host platform, are looked up
+    /// in the supplied registry, rather than the builtin one.
+    pub fn with_platform_registry(platform_registry: PlatformRegistry) -> Self {
+        Self {
+            platform_registry,
+            ..Self::default()
+        }
+    }
+
     pub fn build(&mut self) -> Self {
         let mut default = Self::default();
         std::mem::swap(&mut default, self);
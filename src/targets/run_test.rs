@@ -14,6 +14,7 @@ fn can_build_default() {
         flavors: None,
         platforms: None,
         vars: Vec::new(),
+        platform_registry: PlatformRegistry::builtin(),
     };
     assert_eq!(result, expected);
 }
@@ -32,6 +33,7 @@ fn can_update_build() {
         flavors: None,
         platforms: None,
         vars: vec!["foo", "bar"].into_iter().map(|x| x.to_string()).collect::<Vec<_>>(),
+        platform_registry: PlatformRegistry::builtin(),
     };
     assert_eq!(result, expected);
 }
@@ -55,6 +57,7 @@ fn propagates_dry_run_and_verbose_to_vars_if_set() {
             "--verbose",
             "--dry-run",
         ].into_iter().map(|x| x.to_string()).collect::<Vec<_>>(),
+        platform_registry: PlatformRegistry::builtin(),
     };
     assert_eq!(result, expected);
 }
@@ -83,6 +86,7 @@ fn propagates_dry_run_and_verbose_to_struct_if_set_in_vars() {
             "--verbose",
             "--dry-run",
         ].into_iter().map(|x| x.to_string()).collect::<Vec<_>>(),
+        platform_registry: PlatformRegistry::builtin(),
     };
     assert_eq!(result, expected);
 }
@@ -101,6 +105,7 @@ fn propagates_dry_run_and_verbose_to_struct_if_set_in_vars_as_short_flags() {
         flavors: None,
         platforms: None,
         vars: vec!["foo", "-n", "-v", "bar"].into_iter().map(|x| x.to_string()).collect::<Vec<_>>(),
+        platform_registry: PlatformRegistry::builtin(),
     };
     assert_eq!(result, expected);
 }
@@ -121,6 +126,7 @@ fn propagates_verbose_to_struct_if_set_in_vars_as_short_flags() {
         flavors: None,
         platforms: None,
         vars: vec!["foo", "-v", "bar"].into_iter().map(|x| x.to_string()).collect::<Vec<_>>(),
+        platform_registry: PlatformRegistry::builtin(),
     };
     assert_eq!(result, expected);
 }
@@ -140,6 +146,7 @@ fn propagates_dry_run_to_struct_if_set_in_vars_as_short_flags() {
         flavors: None,
         platforms: None,
         vars: vec!["foo", "-n", "bar"].into_iter().map(|x| x.to_string()).collect::<Vec<_>>(),
+        platform_registry: PlatformRegistry::builtin(),
     };
     assert_eq!(result, expected);
 }
@@ -11,9 +11,10 @@
 use crate::BuildEnv;
 use crate::Flavor;
 use crate::Platform;
+use crate::PlatformRegistry;
 use crate::traits::Doit;
 use crate::traits::Tabulate;
-use crate::utils::exec_cmd;
+use crate::utils::{exec_cmd, ExitStatus};
 
 // external crate imports
 use anyhow::anyhow;
@@ -33,6 +34,7 @@ pub struct Test {
     pub platforms: Option<HashSet<Platform>>,
     pub flavors: Option<HashSet<Flavor>>,
     pub package_root: Option<PathBuf>,
+    pub platform_registry: PlatformRegistry,
 }
 //
 // Private Methods - used to construct pk test arguements
@@ -125,30 +127,11 @@ impl Doit for Test {
     type Err = AnyError;
 
     fn doit(&mut self) -> Result<(), Self::Err> {
-        if self.verbose {
-            self.tabulate();
-        }
-        let cmd = self.build_cmd()?;
-        if self.dry_run {
-            for c in cmd {
-                println!("{}", c);
-            }
-        } else {
-            if self.verbose {
-                for c in &cmd {
-                    println!("{}", c);
-                }
-            }
-            let cmd = cmd.join(" ; ");
-
-            let exit_status = exec_cmd(cmd.as_str(), self.get_package_root())?;
-            println!("\nExit Status: {:?}", exit_status);
-        }
-        Ok(())
+        self.execute().map(|_| ())
     }
 
     fn build_cmd(&mut self) -> Result<Vec<String>, Self::Err> {
-        let build_env = BuildEnv::new(self.get_package_root())?;
+        let build_env = BuildEnv::with_platforms(self.get_package_root(), &self.platform_registry)?;
 
         let dist_dir_str = self.dist_dir_str(&build_env)?;
         let defines_str = self.get_defines_str();
@@ -163,6 +146,36 @@ impl Doit for Test {
     }
 }
 
+//
+// Execution
+//
+impl Test {
+    /// Execute the tests as doit does, returning the exit status of the pk
+    /// commands, or None for a dry run.
+    pub fn execute(&mut self) -> Result<Option<ExitStatus>, AnyError> {
+        if self.verbose {
+            self.tabulate();
+        }
+        let cmd = self.build_cmd()?;
+        if self.dry_run {
+            for c in cmd {
+                println!("{}", c);
+            }
+            return Ok(None);
+        }
+        if self.verbose {
+            for c in &cmd {
+                println!("{}", c);
+            }
+        }
+        let cmd = cmd.join(" ; ");
+
+        let exit_status = exec_cmd(cmd.as_str(), self.get_package_root())?;
+        println!("\nExit Status: {:?}", exit_status);
+        Ok(Some(exit_status))
+    }
+}
+
 //
 // Default Trait Implementation
 //
@@ -176,6 +189,7 @@ impl Default for Test {
             platforms: None,
             flavors: None,
             package_root: None,
+            platform_registry: PlatformRegistry::builtin(),
         }
     }
 }
@@ -251,34 +265,15 @@ impl Test {
     /// ```
     pub fn platform<I>(&mut self, value: Option<I>) -> Result<&mut Self, AnyError>
     where
-        I: TryInto<Platform> + std::fmt::Debug + Clone,
+        I: AsRef<str>,
     {
         match value {
-            Some(val) => match self.platforms {
-                Some(ref mut platforms) => {
-                    let val_cpy = val.clone();
-                    //platforms.insert(val.into());
-                    match val.try_into() {
-                        Ok(v) => platforms.insert(v),
-                        Err(_) => {
-                            return Err(anyhow!("Error converting {:?} into Platform", val_cpy))
-                        }
-                    };
-                }
-                None => {
-                    let val_cpy = val.clone();
-                    let mut hset = HashSet::new();
-                    //hset.insert(val.into());
-                    //
-                    match val.try_into() {
-                        Ok(v) => hset.insert(v),
-                        Err(_) => {
-                            return Err(anyhow!("Error converting {:?} into Platform", val_cpy))
-                        }
-                    };
-                    self.platforms = Some(hset);
-                }
-            },
+            Some(val) => {
+                let platform = self.platform_registry.parse(val.as_ref())?;
+                self.platforms
+                    .get_or_insert_with(HashSet::new)
+                    .insert(platform);
+            }
             None => self.platforms = None,
         }
         Ok(self)
@@ -298,15 +293,17 @@ impl Test {
     /// ```
     pub fn platforms<I>(&mut self, value: Option<Vec<I>>) -> Result<&mut Self, AnyError>
     where
-        I: TryInto<Platform>,
+        I: AsRef<str>,
     {
         match value {
             None => self.platforms = None,
             Some(plats) => {
-                let plats: Result<Vec<_>, _> =
-                    plats.into_iter().map(|i_val| i_val.try_into()).collect();
+                let plats: Result<Vec<_>, _> = plats
+                    .iter()
+                    .map(|i_val| self.platform_registry.parse(i_val.as_ref()))
+                    .collect();
                 match plats {
-                    Err(_) => return Err(anyhow!("failed to convert to platform")),
+                    Err(e) => return Err(anyhow!("failed to convert to platform: {}", e)),
                     Ok(val) => match self.platforms {
                         Some(ref mut platforms) => {
                             for v in val {
@@ -428,6 +425,15 @@ impl Test {
         Ok(self)
     }
 
+    /// New up a Test whose platforms, including the host platform, are looked up
+    /// in the supplied registry, rather than the builtin one.
+    pub fn with_platform_registry(platform_registry: PlatformRegistry) -> Self {
+        Self {
+            platform_registry,
+            ..Self::default()
+        }
+    }
+
     /// Finalize a chain of calls by returning a modified instance of the Test instance.
     ///
     /// # Example
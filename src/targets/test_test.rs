@@ -15,6 +15,7 @@ fn can_construct_default() {
         platforms: None,
         flavors: None,
         package_root: None,
+        platform_registry: PlatformRegistry::builtin(),
     };
 
     assert_eq!(result, expected);
@@ -33,8 +34,8 @@ fn can_modify_and_build() {
         .build();
 
     let mut platform_hset = HashSet::new();
-    platform_hset.insert(Platform::Cent7_64);
-    platform_hset.insert(Platform::Cent6_64);
+    platform_hset.insert(Platform::from("cent7_64").unwrap());
+    platform_hset.insert(Platform::from("cent6_64").unwrap());
 
     let mut flavor_hset = HashSet::new();
     flavor_hset.insert(Flavor::Vanilla);
@@ -48,6 +49,7 @@ fn can_modify_and_build() {
         flavors: Some(flavor_hset),
         defines: Some(vec!["foo=bar".to_string()]),
         package_root: Some(std::path::PathBuf::from("./foo/bar")),
+        platform_registry: PlatformRegistry::builtin(),
     };
     assert_eq!(result, expected);
 }
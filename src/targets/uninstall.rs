@@ -0,0 +1,290 @@
+//! Uninstall target
+//!
+//! Undo an install, by removing the manifest's version of the package from the
+//! level, sites, platforms and flavors that the equivalent Install request would
+//! install to. A rollback is an uninstall which first verifies that the version
+//! being rolled back to is older than the manifest's version, and that the history
+//! journal records a successful install of it at the level, sites and platforms,
+//! so that removing the manifest's version leaves the former current. Installs
+//! which were not journaled, or were since removed by other means, are not known
+//! to the rollback.
+//!
+//! Facility installs may not be removed, and removing from all sites, including
+//! all but some of them, must be confirmed.
+
+// Internal crate imports
+use crate::history::Journal;
+use crate::named_site::SiteRegistry;
+use crate::site::Site;
+use crate::targets::Install;
+use crate::traits::{Doit, Tabulate};
+use crate::utils::exec_cmd;
+use crate::BuildEnv;
+use crate::ManifestInfo;
+
+// External crate imports
+use anyhow::anyhow;
+use anyhow::Error as AnyError;
+use prettytable::{row, Table};
+use std::cmp::Ordering;
+use std::io::BufRead;
+
+/// Models a request to remove an install, optionally as a rollback to an earlier
+/// version
+#[derive(Debug, PartialEq, Eq, Default)]
+pub struct Uninstall {
+    /// The install being undone, which supplies the level, sites, platforms,
+    /// flavors, dry_run and verbose settings
+    pub install: Install,
+    /// The version being rolled back to, if this is a rollback
+    pub to: Option<String>,
+    /// Whether removal from all sites has been confirmed up front
+    pub yes: bool,
+}
+
+impl Doit for Uninstall {
+    type Err = AnyError;
+    /// Calculate the pk uninstall commands, confirm them if necessary, and execute
+    /// them in a subshell
+    fn doit(&mut self) -> Result<(), Self::Err> {
+        if self.install.verbose {
+            self.tabulate();
+        }
+        let cmd = self.build_cmd()?;
+        if self.install.dry_run {
+            for c in cmd {
+                println!("{}", c);
+            }
+            return Ok(());
+        }
+        if !self.confirmed(&mut std::io::stdin().lock())? {
+            return Err(anyhow!("Uninstall from all sites was not confirmed"));
+        }
+        if self.install.verbose {
+            for c in &cmd {
+                println!("{}", c);
+            }
+        }
+        let cmd = cmd.join(" ; ");
+        let root = self
+            .install
+            .package_root
+            .clone()
+            .unwrap_or_else(|| std::path::PathBuf::from("."));
+        let exit_status = exec_cmd(cmd.as_str(), root)?;
+        println!("\nExit Status: {:?}", exit_status);
+        Ok(())
+    }
+
+    /// Generate a pk uninstall command for each flavor, after verifying the rollback
+    /// version, if any
+    fn build_cmd(&mut self) -> Result<Vec<String>, Self::Err> {
+        let cmd = self.install.uninstall_cmd()?;
+        if let Some(ref to) = self.to {
+            let root = self
+                .install
+                .package_root
+                .clone()
+                .unwrap_or_else(|| std::path::PathBuf::from("."));
+            let build_env = BuildEnv::new(root)?;
+            let manifest_info = ManifestInfo::from_path(build_env.manifest.as_ref())?;
+            match compare_versions(to, manifest_info.version()) {
+                Ordering::Less => (),
+                Ordering::Equal => {
+                    return Err(anyhow!(
+                        "Cannot roll back to {}, which is the current version of {}",
+                        to,
+                        manifest_info.name()
+                    ))
+                }
+                Ordering::Greater => {
+                    return Err(anyhow!(
+                        "Cannot roll back to {}, which is newer than the current version of {} ({})",
+                        to,
+                        manifest_info.name(),
+                        manifest_info.version()
+                    ))
+                }
+            }
+            self.verify_installed(to, &manifest_info, &build_env)?;
+        }
+        Ok(cmd)
+    }
+}
+
+//
+// Private methods
+//
+impl Uninstall {
+    // Removing from all sites requires confirmation, either up front via yes, or by
+    // answering y when prompted.
+    // A request for all sites but some counts, as does one which names every site.
+    fn confirmed(&self, input: &mut dyn BufRead) -> Result<bool, AnyError> {
+        let all_sites = self.install.sites_as_requested().contains(&Site::All);
+        if !all_sites || self.yes {
+            return Ok(true);
+        }
+        println!("This will uninstall from all sites. Continue? [y/N]");
+        let mut answer = String::new();
+        input.read_line(&mut answer)?;
+        Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+    }
+}
+
+//
+// Rollback verification
+//
+impl Uninstall {
+    // Verify that the history journal records a successful install of version to,
+    // at the reconciled level, covering every site and platform being uninstalled
+    // from. The installs may be spread over several records.
+    fn verify_installed(
+        &self,
+        to: &str,
+        manifest_info: &ManifestInfo,
+        build_env: &BuildEnv,
+    ) -> Result<(), AnyError> {
+        let install = &self.install;
+        let journal = install.journal.as_ref().map(Journal::new).ok_or_else(|| {
+            anyhow!(
+                "Unable to verify that {} is installed: no history journal is configured",
+                to
+            )
+        })?;
+        let level = install
+            .level
+            .as_ref()
+            .map(|l| l.to_string())
+            .unwrap_or_default();
+        let records = journal
+            .query(Some(manifest_info.name()), None)?
+            .into_iter()
+            .filter(|r| r.succeeded() && r.version == to)
+            .filter(|r| {
+                r.level
+                    .as_deref()
+                    .map(|l| l.eq_ignore_ascii_case(&level))
+                    .unwrap_or(false)
+            })
+            .collect::<Vec<_>>();
+        let sites = match install.sites {
+            Some(ref sites) => sites.iter().cloned().collect::<Vec<_>>(),
+            None => vec![Site::Local],
+        };
+        let platforms = match install.platforms {
+            Some(ref platforms) => platforms.iter().map(|p| p.as_str()).collect::<Vec<_>>(),
+            None => vec![build_env.dd_os.as_str()],
+        };
+        let mut missing = Vec::new();
+        for platform in platforms {
+            let installed = records
+                .iter()
+                .filter(|r| r.platforms.iter().any(|p| p == platform))
+                .flat_map(|r| r.sites.iter())
+                .filter_map(|s| Site::parse(s, &install.site_registry).ok())
+                .collect::<Vec<_>>();
+            for site in &sites {
+                if !covers(&installed, site, &install.site_registry) {
+                    missing.push(format!("{} on {}", site.as_str(), platform));
+                }
+            }
+        }
+        if missing.is_empty() {
+            return Ok(());
+        }
+        Err(anyhow!(
+            "Cannot roll back to {}: '{}' records no successful install of {}-{} at level {} for {}",
+            to,
+            journal.path().display(),
+            manifest_info.name(),
+            to,
+            level,
+            missing.join(", ")
+        ))
+    }
+}
+
+// whether the installed sites include site. `all` covers every site, and every
+// site in the registry covers `all`.
+fn covers(installed: &[Site], site: &Site, registry: &SiteRegistry) -> bool {
+    installed.contains(site)
+        || installed.contains(&Site::All)
+        || (site == &Site::All
+            && registry
+                .sites()
+                .iter()
+                .all(|named| installed.contains(&Site::Named(named.clone()))))
+}
+
+// compare dotted versions component by component, numerically where both
+// components are numbers
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let mut a_parts = a.split(['.', '-']);
+    let mut b_parts = b.split(['.', '-']);
+    loop {
+        let ordering = match (a_parts.next(), b_parts.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b)) => match (a.parse::<u64>(), b.parse::<u64>()) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                _ => a.cmp(b),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+//
+// public methods - primarily setters
+//
+impl Uninstall {
+    /// Set the install to undo
+    pub fn install(&mut self, input: Install) -> &mut Self {
+        self.install = input;
+        self
+    }
+
+    /// Set the version to roll back to, making this a rollback
+    pub fn to<I>(&mut self, input: Option<I>) -> &mut Self
+    where
+        I: Into<String>,
+    {
+        self.to = input.map(|v| v.into());
+        self
+    }
+
+    /// Confirm removal from all sites up front
+    pub fn yes(&mut self, input: bool) -> &mut Self {
+        self.yes = input;
+        self
+    }
+
+    /// Terminate a chain of calls with a build to return an owned instance.
+    pub fn build(&mut self) -> Self {
+        let mut default = Self::default();
+        std::mem::swap(self, &mut default);
+        default
+    }
+}
+
+//
+// Tabulate implementation
+//
+impl Tabulate for Uninstall {
+    fn create_table(&self) -> Table {
+        let mut table = self.install.create_table();
+        table.add_row(row!["rollback_to", self.to.as_deref().unwrap_or("None")]);
+        table.add_row(row!["yes", self.yes]);
+        table
+    }
+}
+
+//
+// Import Tests
+//
+#[cfg(test)]
+#[path = "./uninstall_test.rs"]
+mod uninstall_test;
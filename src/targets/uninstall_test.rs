@@ -0,0 +1,164 @@
+//
+// Unit tests for uninstall.rs
+//
+use super::*;
+use serial_test::serial;
+use std::env;
+
+use crate::history::HistoryRecord;
+use crate::utils::setup_manifest_dir2;
+
+fn install() -> Install {
+    let root = setup_manifest_dir2("nonflavored_git");
+    env::set_var("DD_SHOW", "DEV01");
+    env::set_var("DD_OS", "cent7_64");
+    Install::default().package_root(Some(root)).build()
+}
+
+#[test]
+#[serial]
+fn build_cmd_given_default() {
+    let result = Uninstall::default().install(install()).build_cmd();
+    let expected = vec![
+        "pk uninstall --level=DEV01.work --site=local --platform=cent7_64 makebridge-3.1.0"
+            .to_string(),
+    ];
+    assert_eq!(result.unwrap(), expected);
+}
+
+#[test]
+#[serial]
+fn build_cmd_given_sites_and_level() {
+    let mut install = install();
+    install
+        .sites(Some(vec!["portland", "vancouver"]))
+        .level(Some("dev01"))
+        .unwrap();
+    let result = Uninstall::default().install(install).build_cmd();
+    let expected = vec![
+        "pk uninstall --level=dev01 --site=portland,vancouver --platform=cent7_64 makebridge-3.1.0"
+            .to_string(),
+    ];
+    assert_eq!(result.unwrap(), expected);
+}
+
+#[test]
+#[serial]
+fn build_cmd_given_facility_fails() {
+    let mut install = install();
+    install.level(Some("facility")).unwrap();
+    let result = Uninstall::default().install(install).build_cmd();
+    assert!(result.unwrap_err().to_string().contains("facility"));
+}
+
+// a journaled install of makebridge at dev01, for the local site and cent7_64
+fn journal_with(version: &str, exit_status: u32) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    Journal::new(dir.path().join("history.jsonl"))
+        .append(&HistoryRecord {
+            package: "makebridge".to_string(),
+            version: version.to_string(),
+            level: Some("dev01".to_string()),
+            sites: vec!["local".to_string()],
+            platforms: vec!["cent7_64".to_string()],
+            exit_status: Some(exit_status),
+            ..HistoryRecord::default()
+        })
+        .unwrap();
+    dir
+}
+
+fn rollback(to: &str, journal: Option<&tempfile::TempDir>) -> Result<Vec<String>, AnyError> {
+    let mut install = install();
+    install
+        .level(Some("dev01"))
+        .unwrap()
+        .journal(journal.map(|dir| dir.path().join("history.jsonl")));
+    Uninstall::default().install(install).to(Some(to)).build_cmd()
+}
+
+#[test]
+#[serial]
+fn build_cmd_given_rollback() {
+    let journal = journal_with("3.0.10", 0);
+    assert_eq!(rollback("3.0.10", Some(&journal)).unwrap().len(), 1);
+
+    let result = rollback("3.1.0", Some(&journal));
+    assert!(result.unwrap_err().to_string().contains("current version"));
+
+    let result = rollback("3.10.0", Some(&journal));
+    assert!(result.unwrap_err().to_string().contains("newer"));
+}
+
+#[test]
+#[serial]
+fn build_cmd_given_rollback_to_version_not_installed_fails() {
+    let err = rollback("3.0.10", None).unwrap_err().to_string();
+    assert!(err.contains("no history journal"), "{}", err);
+
+    let err = rollback("3.0.9", Some(&journal_with("3.0.10", 0)))
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.contains("no successful install of makebridge-3.0.9 at level dev01 for local on cent7_64"),
+        "{}",
+        err
+    );
+
+    let err = rollback("3.0.10", Some(&journal_with("3.0.10", 1)))
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("no successful install"), "{}", err);
+
+    let journal = journal_with("3.0.10", 0);
+    let mut install = install();
+    install
+        .level(Some("dev01"))
+        .unwrap()
+        .sites(Some(vec!["local", "playa"]))
+        .journal(Some(journal.path().join("history.jsonl")));
+    let result = Uninstall::default().install(install).to(Some("3.0.10")).build_cmd();
+    let err = result.unwrap_err().to_string();
+    assert!(err.ends_with("for playa on cent7_64"), "{}", err);
+}
+
+#[test]
+fn all_sites_requires_confirmation() {
+    let mut uninstall = Uninstall::default();
+    assert!(uninstall.confirmed(&mut "".as_bytes()).unwrap());
+
+    uninstall.install.sites = Some(vec![Site::All].into_iter().collect());
+    assert!(!uninstall.confirmed(&mut "".as_bytes()).unwrap());
+    assert!(!uninstall.confirmed(&mut "n\n".as_bytes()).unwrap());
+    assert!(uninstall.confirmed(&mut "y\n".as_bytes()).unwrap());
+
+    uninstall.yes(true);
+    assert!(uninstall.confirmed(&mut "".as_bytes()).unwrap());
+}
+
+#[test]
+fn all_sites_but_some_requires_confirmation() {
+    let mut uninstall = Uninstall::default();
+    uninstall.install.sites(Some(vec!["all", "!playa"])).resolve_sites().unwrap();
+    assert!(!uninstall.confirmed(&mut "".as_bytes()).unwrap());
+
+    uninstall
+        .install
+        .sites(None::<Vec<&str>>)
+        .sites(Some(vec!["hyderabad", "playa", "portland", "montreal", "vancouver"]))
+        .resolve_sites()
+        .unwrap();
+    assert!(!uninstall.confirmed(&mut "".as_bytes()).unwrap());
+
+    uninstall.install.sites(None::<Vec<&str>>);
+    uninstall.install.sites(Some(vec!["playa"])).resolve_sites().unwrap();
+    assert!(uninstall.confirmed(&mut "".as_bytes()).unwrap());
+}
+
+#[test]
+fn can_compare_versions() {
+    assert_eq!(compare_versions("3.0.10", "3.1.0"), Ordering::Less);
+    assert_eq!(compare_versions("3.10.0", "3.9.0"), Ordering::Greater);
+    assert_eq!(compare_versions("1.2", "1.2.1"), Ordering::Less);
+    assert_eq!(compare_versions("1.2.0", "1.2.0"), Ordering::Equal);
+}
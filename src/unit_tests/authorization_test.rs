@@ -0,0 +1,78 @@
+use super::*;
+
+fn rules() -> Vec<AuthRule> {
+    #[derive(Deserialize)]
+    struct File {
+        authorization: Vec<AuthRule>,
+    }
+    toml::from_str::<File>(
+        r#"
+        [[authorization]]
+        context = "facility"
+        groups = ["pipeline"]
+
+        [[authorization]]
+        context = "shared"
+        sites = ["all"]
+        groups = ["pipeline", "show-leads"]
+
+        [[authorization]]
+        level = "dev01"
+        groups = ["dev01-td"]
+        "#,
+    )
+    .unwrap()
+    .authorization
+}
+
+fn subject(level: Option<&str>, sites: Vec<Site>) -> AuthSubject {
+    let level = level.map(|l| Level::from(l).unwrap());
+    AuthSubject {
+        context: level.as_ref().map(|l| l.context()).unwrap_or(Context::Facility),
+        level,
+        sites,
+    }
+}
+
+#[test]
+fn within_matches_the_level_and_those_beneath_it() {
+    assert!(within("DEV01", "dev01"));
+    assert!(within("DEV01.rd.9999.work", "dev01"));
+    assert!(within("dev01.rd", "dev01.rd"));
+    assert!(!within("dev01", "dev01.rd"));
+    assert!(!within("dev012", "dev01"));
+}
+
+#[test]
+fn facility_requires_the_facility_group() {
+    let rules = rules();
+    let facility = subject(None, vec![Site::Local]);
+    assert!(authorize(&rules, &facility, &UserGroups::new("jdoe", vec!["pipeline"])).is_ok());
+
+    let err = authorize(&rules, &facility, &UserGroups::new("jdoe", vec!["artists"]))
+        .unwrap_err()
+        .to_string();
+    assert_eq!(
+        err,
+        "User 'jdoe' is not authorized to install to the facility level (facility context) at local. Membership of one of these groups is required: pipeline"
+    );
+}
+
+#[test]
+fn every_applicable_rule_must_be_satisfied() {
+    let rules = rules();
+    let shared_all = subject(Some("dev01"), vec![Site::All]);
+    let lead = UserGroups::new("lead", vec!["show-leads"]);
+    let err = authorize(&rules, &shared_all, &lead).unwrap_err().to_string();
+    assert!(err.contains("level dev01 (shared context) at all"), "{}", err);
+    assert!(err.ends_with("required: dev01-td"), "{}", err);
+
+    let td_lead = UserGroups::new("lead", vec!["show-leads", "dev01-td"]);
+    assert!(authorize(&rules, &shared_all, &td_lead).is_ok());
+
+    // only the level rule applies to a work level at the local site
+    let work = subject(Some("dev01.work"), vec![Site::Local]);
+    assert!(authorize(&rules, &work, &UserGroups::new("td", vec!["dev01-td"])).is_ok());
+    let other = subject(Some("dev02.work"), vec![Site::Local]);
+    assert!(authorize(&rules, &other, &UserGroups::default()).is_ok());
+}
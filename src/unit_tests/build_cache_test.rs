@@ -0,0 +1,98 @@
+use super::*;
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn key_depends_on_every_input() {
+    let base = key("abc", &[("flavor", vec!["^"]), ("defines", vec![])]);
+    assert_eq!(base.len(), 64);
+    assert_eq!(base, key("abc", &[("flavor", vec!["^"]), ("defines", vec![])]));
+    assert_ne!(base, key("abd", &[("flavor", vec!["^"]), ("defines", vec![])]));
+    assert_ne!(base, key("abc", &[("flavor", vec!["foo"]), ("defines", vec![])]));
+    assert_ne!(base, key("abc", &[("flavor", vec!["^"]), ("defines", vec!["A=1"])]));
+}
+
+#[test]
+fn key_distinguishes_list_items_from_their_joined_value() {
+    assert_ne!(
+        key("abc", &[("defines", vec!["a,b"])]),
+        key("abc", &[("defines", vec!["a", "b"])])
+    );
+}
+
+#[test]
+fn sources_digest_tracks_package_sources_only() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    let manifest = root.join("manifest.yaml");
+    let dist_dir = root.join("dist");
+    fs::write(&manifest, "name: foo").unwrap();
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("src").join("lib.py"), "pass").unwrap();
+    let digest = sources_digest(root, &manifest, None, &dist_dir).unwrap();
+
+    // the private, vcs, and dist directories are not package sources
+    for ignored in &["private", ".git", "dist"] {
+        fs::create_dir_all(root.join(ignored)).unwrap();
+        fs::write(root.join(ignored).join("file"), "ignored").unwrap();
+    }
+    assert_eq!(sources_digest(root, &manifest, None, &dist_dir).unwrap(), digest);
+
+    fs::write(root.join("src").join("lib.py"), "print()").unwrap();
+    let changed = sources_digest(root, &manifest, None, &dist_dir).unwrap();
+    assert_ne!(changed, digest);
+
+    fs::write(&manifest, "name: bar").unwrap();
+    assert_ne!(sources_digest(root, &manifest, None, &dist_dir).unwrap(), changed);
+}
+
+#[test]
+fn recorded_keys_persist() {
+    let dir = tempdir().unwrap();
+    let private_dir = dir.path().join("private");
+    let artifact = private_dir.join("dist").join("foo-1.0.0");
+    fs::create_dir_all(&artifact).unwrap();
+
+    let mut cache = BuildCache::load(&private_dir).unwrap();
+    assert!(!cache.is_current("foo-1.0.0", &artifact, "abc"));
+    cache.record("foo-1.0.0", &artifact, "abc").unwrap();
+    cache.save().unwrap();
+    assert_eq!(
+        fs::read_to_string(private_dir.join("dist").join("foo-1.0.0.cachekey")).unwrap(),
+        "abc\n"
+    );
+
+    let cache = BuildCache::load(&private_dir).unwrap();
+    assert_eq!(
+        cache.get("foo-1.0.0"),
+        Some(&CacheEntry {
+            key: "abc".to_string(),
+            artifact: artifact.clone()
+        })
+    );
+    assert!(cache.is_current("foo-1.0.0", &artifact, "abc"));
+    assert!(!cache.is_current("foo-1.0.0", &artifact, "abd"));
+
+    // a removed artifact is never current
+    fs::remove_dir(&artifact).unwrap();
+    assert!(!cache.is_current("foo-1.0.0", &artifact, "abc"));
+}
+
+#[test]
+fn forgotten_keys_are_not_current() {
+    let dir = tempdir().unwrap();
+    let private_dir = dir.path().join("private");
+    let artifact = private_dir.join("dist").join("foo-1.0.0");
+    fs::create_dir_all(&artifact).unwrap();
+    let mut cache = BuildCache::load(&private_dir).unwrap();
+    cache.record("foo-1.0.0", &artifact, "abc").unwrap();
+    cache.save().unwrap();
+
+    // as before a rebuild, which may fail and leave a partial artifact behind
+    cache.forget("foo-1.0.0", &artifact).unwrap();
+    cache.save().unwrap();
+    assert!(!private_dir.join("dist").join("foo-1.0.0.cachekey").exists());
+    let cache = BuildCache::load(&private_dir).unwrap();
+    assert_eq!(cache.get("foo-1.0.0"), None);
+    assert!(!cache.is_current("foo-1.0.0", &artifact, "abc"));
+}
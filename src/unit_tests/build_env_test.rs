@@ -0,0 +1,116 @@
+use super::*;
+
+const CENTOS7: &str = r#"NAME="CentOS Linux"
+VERSION="7 (Core)"
+ID="centos"
+ID_LIKE="rhel fedora"
+VERSION_ID="7"
+"#;
+
+const ROCKY8: &str = r#"NAME="Rocky Linux"
+ID="rocky"
+VERSION_ID="8.6"
+"#;
+
+#[test]
+fn host_platform_prefers_dd_os() {
+    let registry = PlatformRegistry::builtin();
+    let result = BuildEnv::resolve_host_platform(
+        Some("cent6".into()),
+        Some((PathBuf::from("/etc/os-release"), CENTOS7)),
+        &registry,
+    )
+    .unwrap();
+    assert_eq!(result.0.as_str(), "cent6_64");
+    assert_eq!(result.1, PlatformSource::DdOs);
+}
+
+#[test]
+fn host_platform_given_invalid_dd_os_is_err() {
+    let registry = PlatformRegistry::builtin();
+    let result = BuildEnv::resolve_host_platform(
+        Some("cent99".into()),
+        Some((PathBuf::from("/etc/os-release"), CENTOS7)),
+        &registry,
+    );
+    assert_eq!(result, Err(PkMakeError::InvalidPlatform("cent99".into())));
+}
+
+#[test]
+fn host_platform_falls_back_on_os_release() {
+    let registry = PlatformRegistry::builtin();
+    let path = PathBuf::from("/etc/os-release");
+    let result = BuildEnv::resolve_host_platform(None, Some((path.clone(), CENTOS7)), &registry)
+        .unwrap();
+    assert_eq!(result.0.as_str(), "cent7_64");
+    assert_eq!(result.1, PlatformSource::OsRelease(path.clone()));
+
+    let result =
+        BuildEnv::resolve_host_platform(None, Some((path, ROCKY8)), &registry).unwrap();
+    assert_eq!(result.0.as_str(), "cent8_64");
+}
+
+#[test]
+fn host_platform_given_unknown_os_release_is_err() {
+    let registry = PlatformRegistry::builtin();
+    let contents = "ID=debian\nVERSION_ID=\"12\"\n";
+    let result = BuildEnv::resolve_host_platform(
+        None,
+        Some((PathBuf::from("/etc/os-release"), contents)),
+        &registry,
+    );
+    assert!(matches!(result, Err(PkMakeError::UndetectedPlatform(_))));
+}
+
+#[test]
+fn host_platform_given_neither_source_is_err() {
+    let registry = PlatformRegistry::builtin();
+    let result = BuildEnv::resolve_host_platform(None, None, &registry);
+    assert!(matches!(result, Err(PkMakeError::UndetectedPlatform(_))));
+}
+
+#[test]
+fn host_platform_uses_configured_os_release() {
+    let mut registry = PlatformRegistry::builtin();
+    registry
+        .extend(&[Platform::new("deb12_64", Vec::new(), crate::platform::OsFamily::Linux, true)
+            .with_os_release(vec!["debian-12".into()])])
+        .unwrap();
+    let contents = "ID=debian\nVERSION_ID=\"12\"\n";
+    let result = BuildEnv::resolve_host_platform(
+        None,
+        Some((PathBuf::from("/etc/os-release"), contents)),
+        &registry,
+    )
+    .unwrap();
+    assert_eq!(result.0.as_str(), "deb12_64");
+}
+
+#[test]
+fn show_prefers_dd_show() {
+    let result = BuildEnv::resolve_show(
+        Some("dev01".into()),
+        &PathBuf::from("/jobs/dev02/rd"),
+        &ShowPattern::builtin(),
+    );
+    assert_eq!(result, Some(("dev01".to_string(), ShowSource::DdShow)));
+}
+
+#[test]
+fn show_falls_back_on_path_patterns() {
+    let patterns = vec![
+        ShowPattern::from("/mnt/*/{show}/...").unwrap(),
+        ShowPattern::builtin().remove(0),
+    ];
+    let (show, source) =
+        BuildEnv::resolve_show(None, &PathBuf::from("/jobs/dev02/rd"), &patterns).unwrap();
+    assert_eq!(show, "dev02");
+    assert_eq!(
+        source.to_string(),
+        "/jobs/dev02/rd (matching /jobs/{show}/...)"
+    );
+    assert_eq!(
+        BuildEnv::resolve_show(None, &PathBuf::from("/home/someone"), &patterns),
+        None
+    );
+}
@@ -0,0 +1,62 @@
+use super::*;
+
+fn args(expected: &[&str]) -> Vec<String> {
+    expected.iter().map(|a| a.to_string()).collect()
+}
+
+#[test]
+fn can_translate_install_vars() {
+    let result = MakeArgs::translate(
+        "install",
+        &["CONTEXT=shared", "SITES=all", "FLAVOUR=a,b", "WITH_DOCS=0", "DRY_RUN=1"],
+    )
+    .unwrap();
+    assert_eq!(
+        result.args,
+        args(&[
+            "pk-make",
+            "install",
+            "--context",
+            "shared",
+            "--site",
+            "all",
+            "--flavor",
+            "a",
+            "--flavor",
+            "b",
+            "--skip-docs",
+            "--dry-run",
+        ])
+    );
+    assert!(result.unsupported.is_empty());
+    assert!(result.is_chatty());
+}
+
+#[test]
+fn false_flags_and_true_with_docs_add_nothing() {
+    let result =
+        MakeArgs::translate("build", &["VERBOSE=no", "WITH_DOCS=yes", "DRY_RUN="]).unwrap();
+    assert_eq!(result.args, args(&["pk-make", "build"]));
+    assert!(!result.is_chatty());
+}
+
+#[test]
+fn unsupported_vars_are_reported() {
+    let result =
+        MakeArgs::translate("build", &["SITES=all", "FOO=bar", "PLATFORMS=cent7"]).unwrap();
+    assert_eq!(
+        result.args,
+        args(&["pk-make", "build", "--platform", "cent7"])
+    );
+    assert_eq!(result.unsupported, args(&["SITES=all", "FOO=bar"]));
+}
+
+#[test]
+fn malformed_vars_are_errors() {
+    assert_eq!(
+        MakeArgs::translate("install", &["SITES"]),
+        Err(PkMakeError::InvalidMakeVar("SITES".to_string()))
+    );
+    assert!(MakeArgs::translate("install", &["DRY_RUN=maybe"]).is_err());
+    assert!(MakeArgs::translate("deploy", &["SITES=all"]).is_err());
+}
@@ -0,0 +1,232 @@
+use super::*;
+use crate::{Context, Platform, Site};
+
+fn layer(source: ConfigSource, contents: &str) -> ConfigLayer {
+    ConfigLayer {
+        source,
+        path: PathBuf::from(format!("/tmp/{}.toml", source)),
+        file: ConfigFile::parse(contents).unwrap(),
+    }
+}
+
+#[test]
+fn can_parse_config_file() {
+    let result = ConfigFile::parse(
+        r#"
+        [install]
+        context = "shared"
+        sites = ["portland", "vancouver"]
+        max_jobs = 8
+
+        [build]
+        with_docs = false
+        "#,
+    )
+    .unwrap();
+    assert_eq!(result.install.context, Some("shared".to_string()));
+    assert_eq!(
+        result.install.sites,
+        Some(vec!["portland".to_string(), "vancouver".to_string()])
+    );
+    assert_eq!(result.install.max_jobs, Some(8));
+    assert_eq!(result.build.with_docs, Some(false));
+    assert_eq!(result.docs, TargetDefaults::default());
+}
+
+#[test]
+fn unknown_fields_are_errors() {
+    assert!(ConfigFile::parse("[install]\nsitez = [\"local\"]").is_err());
+    assert!(ConfigFile::parse("[instal]\nsites = [\"local\"]").is_err());
+}
+
+#[test]
+fn package_overrides_user_overrides_system() {
+    let config = Config::from_layers(vec![
+        layer(ConfigSource::Package, "[install]\ncontext = \"facility\""),
+        layer(
+            ConfigSource::System,
+            "[install]\ncontext = \"shared\"\nmax_jobs = 4\nsites = [\"all\"]",
+        ),
+        layer(ConfigSource::User, "[install]\ncontext = \"user\"\nmax_jobs = 2"),
+    ]);
+    let defaults = config.defaults(Section::Install);
+    assert_eq!(defaults.context::<Context>().unwrap(), Some(Context::Facility));
+    assert_eq!(defaults.max_jobs(), Some(2));
+    assert_eq!(defaults.sites::<Site>().unwrap(), Some(vec![Site::All]));
+    assert_eq!(defaults.with_docs(), None);
+}
+
+#[test]
+fn sections_are_independent() {
+    let config = Config::from_layers(vec![layer(
+        ConfigSource::User,
+        "[build]\nplatforms = [\"cent7\"]",
+    )]);
+    assert_eq!(
+        config.defaults(Section::Build).platforms::<Platform>().unwrap(),
+        Some(vec![Platform::from("cent7_64").unwrap()])
+    );
+    assert_eq!(
+        config.defaults(Section::Install).platforms::<Platform>().unwrap(),
+        None
+    );
+}
+
+#[test]
+fn invalid_values_are_errors() {
+    let config = Config::from_layers(vec![layer(
+        ConfigSource::Package,
+        "[install]\nplatforms = [\"cent99\"]",
+    )]);
+    assert!(config
+        .defaults(Section::Install)
+        .platforms::<Platform>()
+        .is_err());
+}
+
+#[test]
+fn entries_report_source() {
+    let config = Config::from_layers(vec![
+        layer(ConfigSource::System, "[install]\nmax_jobs = 4"),
+        layer(ConfigSource::Package, "[install]\nwith_docs = false"),
+    ]);
+    let entries = config
+        .defaults(Section::Install)
+        .entries()
+        .into_iter()
+        .map(|(field, value, origin)| (field, value, origin.layer.source))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        entries,
+        vec![
+            ("max_jobs", "4".to_string(), ConfigSource::System),
+            ("with_docs", "false".to_string(), ConfigSource::Package),
+        ]
+    );
+}
+
+#[test]
+fn site_registry_includes_configured_sites() {
+    let config = Config::from_layers(vec![layer(
+        ConfigSource::User,
+        "[[sites]]\nid = \"london\"\nname = \"London\"\naliases = [\"ldn\"]",
+    )]);
+    let registry = config.site_registry().unwrap();
+    assert_eq!(registry.get("ldn").unwrap().as_str(), "london");
+    assert_eq!(registry.get("vancouver").unwrap().as_str(), "vancouver");
+}
+
+#[test]
+fn platform_registry_includes_configured_platforms() {
+    let config = Config::from_layers(vec![layer(
+        ConfigSource::System,
+        "[[platforms]]\nname = \"rocky9_64\"\naliases = [\"rocky9\"]\nos = \"linux\"",
+    )]);
+    let registry = config.platform_registry().unwrap();
+    let rocky = registry.get("rocky9").unwrap();
+    assert_eq!(rocky.as_str(), "rocky9_64");
+    assert!(rocky.is_buildable());
+    assert!(registry.get("cent7").is_some());
+}
+
+#[test]
+fn profile_overrides_section_defaults() {
+    let config = Config::from_layers(vec![
+        layer(
+            ConfigSource::Package,
+            "[install]\ncontext = \"facility\"\nmax_jobs = 2",
+        ),
+        layer(
+            ConfigSource::User,
+            "[profiles.show-release.install]\ncontext = \"shared\"\nsites = [\"all\"]",
+        ),
+    ]);
+    let defaults = config
+        .profile_defaults(Section::Install, "show-release")
+        .unwrap();
+    assert_eq!(defaults.profile(), Some("show-release"));
+    assert_eq!(defaults.context::<Context>().unwrap(), Some(Context::Shared));
+    assert_eq!(defaults.sites::<Site>().unwrap(), Some(vec![Site::All]));
+    assert_eq!(defaults.max_jobs(), Some(2));
+    let from_profile = defaults
+        .entries()
+        .into_iter()
+        .filter(|(_, _, origin)| origin.profile.is_some())
+        .map(|(field, _, _)| field)
+        .collect::<Vec<_>>();
+    assert_eq!(from_profile, vec!["sites", "context"]);
+}
+
+#[test]
+fn unknown_profile_is_an_error() {
+    let config = Config::from_layers(vec![layer(
+        ConfigSource::User,
+        "[profiles.nightly.build]\nwith_docs = false",
+    )]);
+    assert!(config.profile_defaults(Section::Build, "nightly").is_ok());
+    assert!(matches!(
+        config.profile_defaults(Section::Build, "weekly"),
+        Err(PkMakeError::UnknownProfile(_))
+    ));
+    assert!(matches!(
+        config.profile_defaults(Section::Install, "nightly"),
+        Err(PkMakeError::UnknownProfile(_))
+    ));
+    assert_eq!(config.profile_names(), vec!["nightly"]);
+}
+
+#[test]
+fn configured_show_patterns_precede_builtin() {
+    let config = Config::from_layers(vec![
+        layer(ConfigSource::System, "show_patterns = [\"/mnt/{show}/...\"]"),
+        layer(ConfigSource::User, "show_patterns = [\"/home/*/{show}\"]"),
+    ]);
+    let patterns = config
+        .show_patterns()
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        patterns,
+        vec!["/home/*/{show}", "/mnt/{show}/...", "/jobs/{show}/..."]
+    );
+    assert!(ConfigFile::parse("show_patterns = [\"/mnt/...\"]").is_err());
+}
+
+#[test]
+fn hooks_of_every_layer_run_in_order_of_precedence() {
+    let config = Config::from_layers(vec![
+        layer(ConfigSource::Package, "[hooks]\npost-build = [\"cp LICENSE dist\"]"),
+        layer(
+            ConfigSource::User,
+            "[hooks]\npre-install = [\"notify\"]\npost-build = [\"echo built\"]",
+        ),
+    ]);
+    let hooks = config.hooks();
+    assert_eq!(hooks.post_build, vec!["echo built", "cp LICENSE dist"]);
+    assert_eq!(hooks.pre_install, vec!["notify"]);
+    assert!(hooks.pre_build.is_empty());
+    assert!(ConfigFile::parse("[hooks]\npre-deploy = [\"x\"]").is_err());
+}
+
+#[test]
+fn module_doc_example_parses() {
+    let source = include_str!("../config.rs");
+    let example = source
+        .split("```toml\n")
+        .nth(1)
+        .and_then(|s| s.split("//! ```").next())
+        .unwrap()
+        .lines()
+        .map(|l| l.trim_start_matches("//!").trim_start())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let file = ConfigFile::parse(&example).unwrap();
+    assert_eq!(file.shows, vec!["dev01".to_string()]);
+    assert_eq!(file.shows_root, Some(PathBuf::from("/jobs")));
+    assert_eq!(
+        file.history_file,
+        Some(PathBuf::from("/var/tmp/pk-make-history.jsonl"))
+    );
+    assert_eq!(file.show_patterns.len(), 1);
+}
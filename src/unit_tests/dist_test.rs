@@ -0,0 +1,28 @@
+use super::*;
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn verify_artifacts_reports_every_missing_or_empty_artifact() {
+    let dir = tempdir().unwrap();
+    let built = dir.path().join("foo-1.0.0");
+    let empty = dir.path().join("foo-1.0.0_bar");
+    let missing = dir.path().join("foo-1.0.0_baz");
+    fs::create_dir_all(&built).unwrap();
+    fs::create_dir_all(&empty).unwrap();
+    fs::write(built.join("foo.so"), "").unwrap();
+
+    assert_eq!(verify_artifacts("foo-1.0.0", std::slice::from_ref(&built)), Ok(()));
+    let result = verify_artifacts("foo-1.0.0", &[built, empty.clone(), missing.clone()]);
+    assert_eq!(
+        result,
+        Err(PkMakeError::MissingArtifacts {
+            package: "foo-1.0.0".to_string(),
+            missing: format!(
+                "{} (is empty)\n  {} (does not exist)",
+                empty.display(),
+                missing.display()
+            ),
+        })
+    );
+}
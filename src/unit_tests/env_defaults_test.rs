@@ -0,0 +1,106 @@
+use super::*;
+
+fn env(vars: &[(&str, &str)]) -> EnvDefaults {
+    EnvDefaults::from_vars(
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string())),
+    )
+}
+
+#[test]
+fn ignores_unrelated_and_empty_vars() {
+    let env = env(&[("DD_SHOW", "dev01"), ("PK_MAKE_LEVEL", "  ")]);
+    assert_eq!(env.get("DD_SHOW"), None);
+    assert_eq!(env.get("PK_MAKE_LEVEL"), None);
+}
+
+#[test]
+fn can_parse_flags() {
+    let env = env(&[
+        ("PK_MAKE_DRY_RUN", "1"),
+        ("PK_MAKE_VERBOSE", "Yes"),
+        ("PK_MAKE_WORK", "off"),
+    ]);
+    assert_eq!(env.flag("PK_MAKE_DRY_RUN"), Ok(true));
+    assert_eq!(env.flag("PK_MAKE_VERBOSE"), Ok(true));
+    assert_eq!(env.flag("PK_MAKE_WORK"), Ok(false));
+    assert_eq!(env.flag("PK_MAKE_CLEAN"), Ok(false));
+}
+
+#[test]
+fn invalid_flag_names_the_variable() {
+    let env = env(&[("PK_MAKE_DRY_RUN", "maybe")]);
+    let err = env.flag("PK_MAKE_DRY_RUN").unwrap_err();
+    assert!(err.to_string().contains("PK_MAKE_DRY_RUN"));
+}
+
+#[test]
+fn can_split_lists() {
+    let env = env(&[("PK_MAKE_FLAVORS", "^, foo,,"), ("PK_MAKE_MAX_JOBS", " 8 ")]);
+    assert_eq!(env.items("PK_MAKE_FLAVORS"), Some(vec!["^", "foo"]));
+    assert_eq!(env.items("PK_MAKE_MAX_JOBS"), Some(vec!["8"]));
+    assert_eq!(env.items("PK_MAKE_SITES"), None);
+}
+
+#[test]
+fn vars_are_trimmed() {
+    let env = env(&[("PK_MAKE_SHOW", " dev01 ")]);
+    assert_eq!(env.vars().collect::<Vec<_>>(), vec![("PK_MAKE_SHOW", "dev01")]);
+}
+
+#[test]
+fn invalid_value_names_the_variable() {
+    let env = env(&[("PK_MAKE_MAX_JOBS", "lots")]);
+    let err = env.value::<u8>("PK_MAKE_MAX_JOBS").unwrap_err();
+    assert!(err.to_string().contains("PK_MAKE_MAX_JOBS"));
+}
+
+#[test]
+fn can_convert_lists() {
+    let env = env(&[("PK_MAKE_MAX_JOBS", "2, 4")]);
+    assert_eq!(env.list::<u8>("PK_MAKE_MAX_JOBS"), Ok(Some(vec![2, 4])));
+    assert_eq!(env.list::<u8>("PK_MAKE_FLAVORS"), Ok(None));
+}
+
+#[test]
+fn sites_are_checked_against_the_registry() {
+    let registry = SiteRegistry::builtin();
+    let env = env(&[("PK_MAKE_SITES", "portland,!vancouver")]);
+    assert_eq!(
+        env.sites(&registry),
+        Ok(Some(vec!["portland".to_string(), "!vancouver".to_string()]))
+    );
+    let env = self::env(&[("PK_MAKE_SITES", "portland,bogus")]);
+    let err = env.sites(&registry).unwrap_err();
+    assert!(err.to_string().contains("PK_MAKE_SITES"));
+    assert!(err.to_string().contains("bogus"));
+}
+
+#[test]
+fn platforms_are_checked_against_the_registry() {
+    let registry = PlatformRegistry::builtin();
+    let env = env(&[("PK_MAKE_PLATFORMS", "cent7")]);
+    assert_eq!(env.platforms(&registry), Ok(Some(vec!["cent7".to_string()])));
+    let env = self::env(&[("PK_MAKE_PLATFORMS", "cent99")]);
+    let err = env.platforms(&registry).unwrap_err();
+    assert!(err.to_string().contains("PK_MAKE_PLATFORMS"));
+    assert!(err.to_string().contains("cent99"));
+}
+
+#[test]
+fn fill_does_not_replace_command_line_values() {
+    let env = env(&[
+        ("PK_MAKE_SHOW", "dev01"),
+        ("PK_MAKE_LEVEL", "dev02"),
+        ("PK_MAKE_DRY_RUN", "yes"),
+    ]);
+    let mut show: Option<String> = None;
+    let mut level = Some("dev03".to_string());
+    let mut dry_run = false;
+    env.fill(&mut show, "PK_MAKE_SHOW").unwrap();
+    env.fill(&mut level, "PK_MAKE_LEVEL").unwrap();
+    env.fill_flag(&mut dry_run, "PK_MAKE_DRY_RUN").unwrap();
+    assert_eq!(show.as_deref(), Some("dev01"));
+    assert_eq!(level.as_deref(), Some("dev03"));
+    assert!(dry_run);
+}
@@ -0,0 +1,112 @@
+use super::*;
+use std::time::Duration;
+use tempfile::tempdir;
+
+fn record(package: &str, timestamp: &str, exit_status: Option<u32>) -> HistoryRecord {
+    HistoryRecord {
+        timestamp: timestamp.to_string(),
+        user: "jdoe".to_string(),
+        package: package.to_string(),
+        version: "1.0.0".to_string(),
+        flavors: vec!["^".to_string()],
+        sites: vec!["local".to_string()],
+        platforms: vec!["cent7_64".to_string()],
+        level: Some("dev01".to_string()),
+        revision: Some("abc123".to_string()),
+        exit_status,
+        error: None,
+        duration_ms: 1500,
+    }
+}
+
+#[test]
+fn timestamp_formats_utc() {
+    assert_eq!(timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+    let time = UNIX_EPOCH + Duration::from_secs(1_582_934_400 + 46_923);
+    assert_eq!(timestamp(time), "2020-02-29T13:02:03Z");
+    let time = UNIX_EPOCH + Duration::from_secs(1_609_459_199);
+    assert_eq!(timestamp(time), "2020-12-31T23:59:59Z");
+}
+
+#[test]
+fn validate_date_given_dates_and_timestamps() {
+    assert!(validate_date("2020-06-01").is_ok());
+    assert!(validate_date("2020-06-01T12:30:00").is_ok());
+    assert!(validate_date("2020-6-1").is_err());
+    assert!(validate_date("06/01/2020").is_err());
+    assert!(validate_date("2020-06-01T12").is_err());
+}
+
+#[test]
+fn journal_appends_and_reads_records() {
+    let dir = tempdir().unwrap();
+    let journal = Journal::new(dir.path().join("pk-make").join("history.jsonl"));
+    assert!(journal.records().unwrap().is_empty());
+
+    let first = record("foo", "2020-06-01T12:00:00Z", Some(0));
+    let mut second = record("bar", "2020-06-02T12:00:00Z", None);
+    second.error = Some("Missing dist artifacts".to_string());
+    journal.append(&first).unwrap();
+    journal.append(&second).unwrap();
+
+    assert_eq!(journal.records().unwrap(), vec![first.clone(), second.clone()]);
+    assert!(first.succeeded());
+    assert!(!second.succeeded());
+    assert_eq!(
+        std::fs::read_to_string(journal.path()).unwrap().lines().count(),
+        2
+    );
+}
+
+#[test]
+fn journal_reports_invalid_records() {
+    let dir = tempdir().unwrap();
+    let journal = Journal::new(dir.path().join("history.jsonl"));
+    journal.append(&record("foo", "2020-06-01T12:00:00Z", Some(0))).unwrap();
+    let mut contents = std::fs::read_to_string(journal.path()).unwrap();
+    contents.push_str("not json\n");
+    std::fs::write(journal.path(), contents).unwrap();
+
+    let err = journal.records().unwrap_err().to_string();
+    assert!(err.contains("history.jsonl:2: invalid record"), "{}", err);
+}
+
+#[test]
+fn query_given_package_and_since() {
+    let dir = tempdir().unwrap();
+    let journal = Journal::new(dir.path().join("history.jsonl"));
+    journal.append(&record("foo", "2020-05-31T23:59:59Z", Some(0))).unwrap();
+    journal.append(&record("bar", "2020-06-01T00:00:00Z", Some(1))).unwrap();
+    journal.append(&record("foo", "2020-06-02T08:00:00Z", Some(0))).unwrap();
+
+    assert_eq!(journal.query(None, None).unwrap().len(), 3);
+    let foo = journal.query(Some("foo"), None).unwrap();
+    assert_eq!(foo.len(), 2);
+    let since = journal.query(None, Some("2020-06-01")).unwrap();
+    assert_eq!(
+        since.iter().map(|r| r.package.as_str()).collect::<Vec<_>>(),
+        vec!["bar", "foo"]
+    );
+    let both = journal.query(Some("foo"), Some("2020-06-01T12:00:00")).unwrap();
+    assert_eq!(both, vec![record("foo", "2020-06-02T08:00:00Z", Some(0))]);
+    assert!(journal.query(None, Some("yesterday")).is_err());
+}
+
+#[test]
+fn history_table_reports_status() {
+    let mut failed = record("bar", "2020-06-01T00:00:00Z", None);
+    failed.error = Some("boom".to_string());
+    let history = History {
+        records: vec![
+            record("foo", "2020-06-01T00:00:00Z", Some(0)),
+            record("foo", "2020-06-01T00:00:00Z", Some(2)),
+            failed,
+        ],
+    };
+    let table = history.create_table().to_string();
+    assert!(table.contains("foo-1.0.0"));
+    assert!(table.contains("ok"));
+    assert!(table.contains("exit 2"));
+    assert!(table.contains("error: boom"));
+    assert!(table.contains("1.5s"));
+}
@@ -0,0 +1,110 @@
+use super::*;
+use std::fs;
+use tempfile::tempdir;
+
+fn context(root: &Path) -> HookContext {
+    HookContext {
+        package_root: root.to_path_buf(),
+        package: "foo".to_string(),
+        version: "1.0.0".to_string(),
+        flavors: vec!["^".to_string(), "bar".to_string()],
+        level: Some("dev01".to_string()),
+        dist_dir: root.join("private").join("dist"),
+        error: None,
+    }
+}
+
+#[test]
+fn hook_point_round_trips() {
+    for point in &HookPoint::ALL {
+        assert_eq!(&HookPoint::from(point.as_str()).unwrap(), point);
+    }
+    assert_eq!("on-failure".parse::<HookPoint>().unwrap(), HookPoint::OnFailure);
+    assert!(HookPoint::try_from("pre-deploy").is_err());
+}
+
+#[test]
+fn env_supplies_the_resolved_context() {
+    let mut context = context(Path::new("/packages/foo"));
+    let env = context.env(HookPoint::PreInstall);
+    let get = |env: &[(String, String)], key: &str| {
+        env.iter().find(|(k, _)| k == key).map(|(_, v)| v.clone())
+    };
+    assert_eq!(get(&env, "PK_HOOK"), Some("pre-install".to_string()));
+    assert_eq!(get(&env, "PK_HOOK_PACKAGE"), Some("foo".to_string()));
+    assert_eq!(get(&env, "PK_HOOK_VERSION"), Some("1.0.0".to_string()));
+    assert_eq!(get(&env, "PK_HOOK_FLAVORS"), Some("^,bar".to_string()));
+    assert_eq!(get(&env, "PK_HOOK_LEVEL"), Some("dev01".to_string()));
+    assert_eq!(
+        get(&env, "PK_HOOK_DIST_DIR"),
+        Some("/packages/foo/private/dist".to_string())
+    );
+    assert_eq!(get(&env, "PK_HOOK_ERROR"), None);
+
+    context.error = Some("boom".to_string());
+    let env = context.env(HookPoint::OnFailure);
+    assert_eq!(get(&env, "PK_HOOK_ERROR"), Some("boom".to_string()));
+}
+
+#[test]
+fn run_executes_hooks_in_order_from_the_package_root() {
+    let dir = tempdir().unwrap();
+    let hooks = Hooks {
+        post_build: vec![
+            "echo $PK_HOOK $PK_HOOK_PACKAGE-$PK_HOOK_VERSION > hook.log".to_string(),
+            "echo second >> hook.log".to_string(),
+        ],
+        ..Hooks::default()
+    };
+    let context = context(dir.path());
+
+    hooks.run(HookPoint::PostBuild, &context, true, false).unwrap();
+    assert!(!dir.path().join("hook.log").exists());
+
+    hooks.run(HookPoint::PostBuild, &context, false, false).unwrap();
+    assert_eq!(
+        fs::read_to_string(dir.path().join("hook.log")).unwrap(),
+        "post-build foo-1.0.0\nsecond\n"
+    );
+}
+
+#[test]
+fn failing_hook_aborts_the_run() {
+    let dir = tempdir().unwrap();
+    let hooks = Hooks {
+        pre_install: vec!["exit 3".to_string(), "touch never".to_string()],
+        on_failure: vec!["echo $PK_HOOK_ERROR > failure.log".to_string(), "exit 1".to_string()],
+        ..Hooks::default()
+    };
+    let context = context(dir.path());
+
+    let err = hooks
+        .run(HookPoint::PreInstall, &context, false, false)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("The pre-install hook 'exit 3' failed"), "{}", err);
+    assert!(!dir.path().join("never").exists());
+
+    // a failing on-failure hook only warns
+    hooks.run_on_failure(&context, "boom", false);
+    assert_eq!(
+        fs::read_to_string(dir.path().join("failure.log")).unwrap(),
+        "boom\n"
+    );
+}
+
+#[test]
+fn extend_appends_commands() {
+    let mut hooks = Hooks::default();
+    assert!(hooks.is_empty());
+    hooks.extend(&Hooks {
+        pre_build: vec!["a".to_string()],
+        ..Hooks::default()
+    });
+    hooks.extend(&Hooks {
+        pre_build: vec!["b".to_string()],
+        ..Hooks::default()
+    });
+    assert!(!hooks.is_empty());
+    assert_eq!(hooks.get(HookPoint::PreBuild), ["a".to_string(), "b".to_string()]);
+}
@@ -0,0 +1,109 @@
+use super::*;
+use crate::config::{ConfigFile, ConfigLayer, ConfigSource};
+use crate::utils::setup_manifest_dir;
+use serial_test::serial;
+use std::env;
+
+fn config() -> Config {
+    Config::from_layers(vec![ConfigLayer {
+        source: ConfigSource::User,
+        path: PathBuf::from("/tmp/user.toml"),
+        file: ConfigFile::parse(
+            "[install]\nsites = [\"local\"]\n\n\
+             [profiles.show-release.install]\ncontext = \"shared\"",
+        )
+        .unwrap(),
+    }])
+}
+
+#[test]
+#[serial]
+fn new_given_flavored_manifest() {
+    let root = setup_manifest_dir(true);
+    env::set_var("DD_OS", "cent7_64");
+    env::set_var("DD_SHOW", "DEV01");
+    let build_env = BuildEnv::new(&root).unwrap();
+    let vars = EnvDefaults::from_vars(vec![("PK_MAKE_VERBOSE".to_string(), "1".to_string())]);
+
+    let info = PackageInfo::new(&build_env, &config(), None, &vars).unwrap();
+
+    assert_eq!(info.package_root, std::fs::canonicalize(&root).unwrap());
+    assert_eq!(info.manifest, info.package_root.join("manifest.yaml"));
+    assert_eq!(info.dd_os, "cent7_64");
+    assert_eq!(info.dd_os_source, "DD_OS");
+    assert_eq!(info.dd_show, Some("DEV01".to_string()));
+    assert_eq!(info.name, "vrayddbase");
+    assert_eq!(info.version, "5.0.8");
+    assert_eq!(info.flavors.len(), 12);
+    assert_eq!(info.flavors[0], "vray4.0.29259_for_maya2018");
+    assert_eq!(
+        info.description,
+        Some("Digital Domain's collection of vray shaders and utilities".to_string())
+    );
+    assert!(info.url.is_some());
+    assert_eq!(
+        info.defaults,
+        vec![TargetDefault {
+            target: "install".to_string(),
+            field: "sites".to_string(),
+            value: "local".to_string(),
+            source: "user (/tmp/user.toml)".to_string(),
+        }]
+    );
+    assert_eq!(
+        info.environment,
+        vec![("PK_MAKE_VERBOSE".to_string(), "1".to_string())]
+    );
+}
+
+#[test]
+#[serial]
+fn new_given_profile_overlays_defaults() {
+    let root = setup_manifest_dir(false);
+    env::set_var("DD_OS", "cent7_64");
+    let build_env = BuildEnv::new(&root).unwrap();
+
+    let info = PackageInfo::new(
+        &build_env,
+        &config(),
+        Some("show-release"),
+        &EnvDefaults::default(),
+    )
+    .unwrap();
+
+    assert_eq!(info.flavors, vec!["^".to_string()]);
+    assert_eq!(info.profile, Some("show-release".to_string()));
+    let fields = info
+        .defaults
+        .iter()
+        .map(|d| (d.field.as_str(), d.value.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(fields, vec![("sites", "local"), ("context", "shared")]);
+}
+
+#[test]
+#[serial]
+fn new_given_unknown_profile_errors() {
+    let root = setup_manifest_dir(false);
+    env::set_var("DD_OS", "cent7_64");
+    let build_env = BuildEnv::new(&root).unwrap();
+
+    let result = PackageInfo::new(&build_env, &config(), Some("nope"), &EnvDefaults::default());
+
+    assert!(result.is_err());
+}
+
+#[test]
+#[serial]
+fn serializes_to_json() {
+    let root = setup_manifest_dir(false);
+    env::set_var("DD_OS", "cent7_64");
+    let build_env = BuildEnv::new(&root).unwrap();
+    let info = PackageInfo::new(&build_env, &config(), None, &EnvDefaults::default()).unwrap();
+
+    let json: serde_json::Value = serde_json::to_value(&info).unwrap();
+
+    assert_eq!(json["name"], "makebridge");
+    assert_eq!(json["version"], "3.1.0");
+    assert_eq!(json["defaults"][0]["target"], "install");
+}
@@ -0,0 +1,82 @@
+use super::*;
+
+#[test]
+fn can_parse_facility() {
+    for input in &["facility", "Facility", "FACILITY"] {
+        assert_eq!(Level::from_str(input).unwrap(), Level::Facility);
+    }
+}
+
+#[test]
+fn can_parse_show_and_work() {
+    assert_eq!(
+        Level::from("dev01").unwrap(),
+        Level::Show {
+            show: "dev01".to_string(),
+            path: Vec::new(),
+            work: false
+        }
+    );
+    let work = Level::from("DEV01.work").unwrap();
+    assert!(work.is_work());
+    assert_eq!(work.show(), Some("DEV01"));
+    assert_eq!(work.context(), Context::User);
+}
+
+#[test]
+fn can_parse_deeper_specs() {
+    let level = Level::from("dev01.rd.9999.work").unwrap();
+    assert_eq!(
+        level,
+        Level::Show {
+            show: "dev01".to_string(),
+            path: vec!["rd".to_string(), "9999".to_string()],
+            work: true
+        }
+    );
+    assert_eq!(level.to_string(), "dev01.rd.9999.work");
+    assert_eq!(Level::from("dev01.rd").unwrap().context(), Context::Shared);
+}
+
+#[test]
+fn malformed_levels_are_errors() {
+    for input in &[
+        "",
+        "work",
+        ".work",
+        "dev01.",
+        "dev01..rd",
+        "dev01.work.rd",
+        "facility.work",
+        "dev 01",
+        "dev01/rd",
+    ] {
+        assert_eq!(
+            Level::from_str(input),
+            Err(PkMakeError::InvalidLevel(input.to_string())),
+            "expected '{}' to be rejected",
+            input
+        );
+    }
+}
+
+#[test]
+fn can_convert_from_context_and_show() {
+    assert_eq!(
+        Level::from_context(&Context::Shared, "dev01").unwrap().to_string(),
+        "dev01"
+    );
+    assert_eq!(
+        Level::from_context(&Context::User, "dev01").unwrap().to_string(),
+        "dev01.work"
+    );
+    assert_eq!(
+        Level::from_context(&Context::Facility, "dev01").unwrap(),
+        Level::Facility
+    );
+    assert_eq!(
+        Level::from_context(&Context::User, "Facility").unwrap(),
+        Level::Facility
+    );
+    assert!(Level::from_context(&Context::User, "dev.01").is_err());
+}
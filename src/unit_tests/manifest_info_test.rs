@@ -38,6 +38,12 @@ fn can_make_manifest_info_frm_mani_with_flavors() {
             Flavor::Named("vray4.0.30046_for_maya2018".into()),
             Flavor::Named("vray4.0.30046_for_maya2020".into()),
         ],
+        os: vec!["linux".into()],
+        description: Some("Digital Domain's collection of vray shaders and utilities".into()),
+        url: Some(
+            "http://dd-svnmaster.d2.com:8000/projects/software/browser/package/vrayddbase/trunk"
+                .into(),
+        ),
     };
     assert_eq!(result.unwrap(), expected);
 }
@@ -65,6 +71,26 @@ fn can_make_manifest_info_frm_mani_without_flavors() {
         name: "makebridge".into(),
         version: "3.1.0".into(),
         flavors: vec![Flavor::Vanilla],
+        os: Vec::new(),
+        description: Some("Provides aux makefile to aid transition to pk based builds".into()),
+        url: None,
     };
     assert_eq!(result.unwrap(), expected);
 }
+
+#[test]
+fn supports_platforms_matching_os_list() {
+    let info = ManifestInfo {
+        name: "foo".into(),
+        version: "1.0.0".into(),
+        flavors: vec![Flavor::Vanilla],
+        os: vec!["linux".into(), "win10".into()],
+        description: None,
+        url: None,
+    };
+    assert!(info.supports(&Platform::from("cent7").unwrap()));
+    assert!(info.supports(&Platform::from("win10").unwrap()));
+    assert!(!info.supports(&Platform::from("win7").unwrap()));
+    let unrestricted = ManifestInfo { os: Vec::new(), ..info };
+    assert!(unrestricted.supports(&Platform::from("osx10").unwrap()));
+}
@@ -3,21 +3,21 @@ use super::*;
 #[test]
 fn instance_from_str() {
     let sites = vec![
-        ("hyderabad", NamedSite::Hyderabad),
-        ("HYDERABAD", NamedSite::Hyderabad),
-        ("playa", NamedSite::Playa),
-        ("Playa", NamedSite::Playa),
-        ("Playa Vista", NamedSite::Playa),
-        ("playavista", NamedSite::Playa),
-        ("portland", NamedSite::Portland),
-        ("Portland", NamedSite::Portland),
-        ("Montreal", NamedSite::Montreal),
-        ("vancouver", NamedSite::Vancouver),
-        ("Vancouver", NamedSite::Vancouver),
+        ("hyderabad", "hyderabad"),
+        ("HYDERABAD", "hyderabad"),
+        ("playa", "playa"),
+        ("Playa", "playa"),
+        ("Playa Vista", "playa"),
+        ("playavista", "playa"),
+        ("portland", "portland"),
+        ("Portland", "portland"),
+        ("Montreal", "montreal"),
+        ("vancouver", "vancouver"),
+        ("Vancouver", "vancouver"),
     ];
     for site in sites {
         let result = NamedSite::from_str(site.0);
-        assert_eq!(result.unwrap(), site.1);
+        assert_eq!(result.unwrap().as_str(), site.1);
     }
 }
 #[test]
@@ -29,21 +29,21 @@ fn err_from_bad_str() {
 #[test]
 fn instance_from() {
     let sites = vec![
-        ("hyderabad", NamedSite::Hyderabad),
-        ("HYDERABAD", NamedSite::Hyderabad),
-        ("playa", NamedSite::Playa),
-        ("Playa", NamedSite::Playa),
-        ("Playa Vista", NamedSite::Playa),
-        ("playavista", NamedSite::Playa),
-        ("portland", NamedSite::Portland),
-        ("Portland", NamedSite::Portland),
-        ("Montreal", NamedSite::Montreal),
-        ("vancouver", NamedSite::Vancouver),
-        ("Vancouver", NamedSite::Vancouver),
+        ("hyderabad", "hyderabad"),
+        ("HYDERABAD", "hyderabad"),
+        ("playa", "playa"),
+        ("Playa", "playa"),
+        ("Playa Vista", "playa"),
+        ("playavista", "playa"),
+        ("portland", "portland"),
+        ("Portland", "portland"),
+        ("Montreal", "montreal"),
+        ("vancouver", "vancouver"),
+        ("Vancouver", "vancouver"),
     ];
     for site in sites {
         let result = NamedSite::from(site.0);
-        assert_eq!(result.unwrap(), site.1);
+        assert_eq!(result.unwrap().as_str(), site.1);
     }
 }
 
@@ -52,3 +52,34 @@ fn instance_from_bad_input() {
     let result = NamedSite::from("fluboxland");
     assert!(result.is_err());
 }
+
+#[test]
+fn display_uses_name() {
+    let result = NamedSite::from("playa").unwrap();
+    assert_eq!(result.to_string(), "Playa Vista");
+}
+
+#[test]
+fn registry_extend_adds_and_replaces() {
+    let mut registry = SiteRegistry::builtin();
+    registry
+        .extend(&[
+            NamedSite::new("london", "London", vec!["ldn".into()], Vec::new()),
+            NamedSite::new("portland", "Portland, OR", Vec::new(), vec!["na".into()]),
+        ])
+        .unwrap();
+    assert_eq!(registry.sites().len(), 6);
+    assert_eq!(registry.get("LDN").unwrap().as_str(), "london");
+    let portland = registry.get("portland").unwrap();
+    assert_eq!(portland.name(), "Portland, OR");
+    assert_eq!(portland.groups(), &["na".to_string()]);
+}
+
+#[test]
+fn registry_extend_rejects_reserved_ids() {
+    let mut registry = SiteRegistry::builtin();
+    for id in &["local", "All", "", "foo bar"] {
+        let result = registry.extend(&[NamedSite::new(*id, "x", Vec::new(), Vec::new())]);
+        assert!(result.is_err());
+    }
+}
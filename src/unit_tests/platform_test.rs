@@ -5,7 +5,7 @@ fn from_win7() {
     let wins = vec!["win7", "WIN7", "win7_64", "WIN7_64"];
     for win in wins {
         let result = Platform::from_str(win);
-        assert_eq!(result.unwrap(), Platform::Win7_64);
+        assert_eq!(result.unwrap(), Platform::from("win7_64").unwrap());
     }
 }
 
@@ -14,7 +14,7 @@ fn from_win10() {
     let wins = vec!["win10", "WIN10", "win10_64", "WIN10_64"];
     for win in wins {
         let result = Platform::from_str(win);
-        assert_eq!(result.unwrap(), Platform::Win10_64);
+        assert_eq!(result.unwrap(), Platform::from("win10_64").unwrap());
     }
 }
 
@@ -23,7 +23,7 @@ fn from_cent6() {
     let wins = vec!["cent6", "CENT6", "cent6_64", "CENT6_64"];
     for win in wins {
         let result = Platform::from_str(win);
-        assert_eq!(result.unwrap(), Platform::Cent6_64);
+        assert_eq!(result.unwrap(), Platform::from("cent6_64").unwrap());
     }
 }
 
@@ -32,7 +32,7 @@ fn from_cent7() {
     let wins = vec!["cent7", "CENT7", "cent7_64", "CENT7_64"];
     for win in wins {
         let result = Platform::from_str(win);
-        assert_eq!(result.unwrap(), Platform::Cent7_64);
+        assert_eq!(result.unwrap(), Platform::from("cent7_64").unwrap());
     }
 }
 #[test]
@@ -40,7 +40,7 @@ fn from_cent8() {
     let wins = vec!["cent8", "CENT8", "cent8_64", "CENT8_64"];
     for win in wins {
         let result = Platform::from_str(win);
-        assert_eq!(result.unwrap(), Platform::Cent8_64);
+        assert_eq!(result.unwrap(), Platform::from("cent8_64").unwrap());
     }
 }
 
@@ -49,7 +49,7 @@ fn from_trait_impl() {
     let plats = vec!["cent8", "Cent8", "cent8_64"];
     for plat in plats {
         let result = Platform::from(plat).unwrap();
-        assert_eq!(result, Platform::Cent8_64);
+        assert_eq!(result, Platform::from("cent8_64").unwrap());
     }
     // test a bad input
     let result = Platform::from("foobarbla");
@@ -63,3 +63,52 @@ fn invalid_strs() {
         let result = Platform::from_str(invalid);
         assert!(result.is_err());
     }}
+
+#[test]
+fn builtin_os_families() {
+    assert_eq!(Platform::from("win10").unwrap().os(), OsFamily::Windows);
+    assert_eq!(Platform::from("osx10").unwrap().os(), OsFamily::Osx);
+    assert_eq!(Platform::from("cent7").unwrap().os(), OsFamily::Linux);
+}
+
+#[test]
+fn builtin_buildable() {
+    assert!(Platform::from("cent7").unwrap().is_buildable());
+    // we have no osx build machines, and cent8 is not in use yet
+    assert!(!Platform::from("osx10").unwrap().is_buildable());
+    assert!(!Platform::from("cent8").unwrap().is_buildable());
+}
+
+#[test]
+fn verify_buildable_given_unbuildable_platform_fails() {
+    let platforms = vec![Platform::from("cent7").unwrap(), Platform::from("osx10").unwrap()];
+    assert_eq!(
+        verify_buildable(&platforms[..1]),
+        Ok(())
+    );
+    assert_eq!(
+        verify_buildable(&platforms),
+        Err(PkMakeError::UnbuildablePlatform("osx10_64".to_string()))
+    );
+}
+
+#[test]
+fn registry_extend_adds_and_replaces() {
+    let mut registry = PlatformRegistry::builtin();
+    registry
+        .extend(&[
+            Platform::new("rocky9_64", vec!["rocky9".into()], OsFamily::Linux, true),
+            Platform::new("cent6_64", vec!["cent6".into()], OsFamily::Linux, false),
+        ])
+        .unwrap();
+    assert_eq!(registry.platforms().len(), 7);
+    assert_eq!(registry.get("ROCKY9").unwrap().as_str(), "rocky9_64");
+    assert!(!registry.get("cent6").unwrap().is_buildable());
+}
+
+#[test]
+fn registry_extend_rejects_invalid_names() {
+    let mut registry = PlatformRegistry::builtin();
+    let result = registry.extend(&[Platform::new("cent 9", Vec::new(), OsFamily::Linux, true)]);
+    assert!(result.is_err());
+}
@@ -0,0 +1,188 @@
+use super::*;
+
+// answers the policy checks without touching the vcs or running tests, counting
+// how often each is asked
+#[derive(Default)]
+struct FakeProbe {
+    clean: bool,
+    passes: bool,
+    asked: Vec<&'static str>,
+}
+
+impl PolicyProbe for FakeProbe {
+    fn vcs_clean(&mut self) -> Result<bool, AnyError> {
+        self.asked.push("vcs_clean");
+        Ok(self.clean)
+    }
+    fn tests_pass(&mut self) -> Result<bool, AnyError> {
+        self.asked.push("tests_pass");
+        Ok(self.passes)
+    }
+}
+
+fn policies(contents: &str) -> Vec<Policy> {
+    #[derive(Deserialize)]
+    struct File {
+        policies: Vec<Policy>,
+    }
+    toml::from_str::<File>(contents).unwrap().policies
+}
+
+fn subject(context: Context, sites: Vec<Site>, work: bool) -> PolicySubject {
+    PolicySubject {
+        context,
+        sites,
+        platforms: vec!["cent7_64".to_string()],
+        work,
+    }
+}
+
+const POLICIES: &str = r#"
+[[policies]]
+id = "shared-rocky9"
+when = { context = "shared" }
+require = { platforms = ["rocky9_64"] }
+overridable = true
+
+[[policies]]
+id = "all-sites-clean"
+when = { sites = ["all"] }
+require = { clean_vcs = true }
+
+[[policies]]
+id = "facility-tested"
+when = { context = "facility" }
+require = { tests_pass = true }
+
+[[policies]]
+id = "no-work-at-show"
+when = { context = "shared", work = true }
+require = { deny = true }
+"#;
+
+#[test]
+fn conditions_select_the_applicable_policies() {
+    let policies = policies(POLICIES);
+    let shared = subject(Context::Shared, vec![Site::Local], false);
+    let applicable = policies
+        .iter()
+        .filter(|p| p.applies_to(&shared).unwrap())
+        .map(|p| p.id.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(applicable, vec!["shared-rocky9"]);
+
+    let all = subject(Context::User, vec![Site::Local, Site::All], true);
+    let applicable = policies
+        .iter()
+        .filter(|p| p.applies_to(&all).unwrap())
+        .map(|p| p.id.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(applicable, vec!["all-sites-clean"]);
+}
+
+#[test]
+fn violations_are_reported_together_by_id() {
+    let policies = policies(POLICIES);
+    let mut probe = FakeProbe::default();
+    let subject = subject(Context::Shared, vec![Site::All], true);
+    let err = enforce(&policies, &subject, &mut probe, &[])
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("[shared-rocky9] missing required platforms: rocky9_64 (may be overridden with --override-policy)"), "{}", err);
+    assert!(err.contains("[all-sites-clean] the package has uncommitted changes"), "{}", err);
+    assert!(err.contains("[no-work-at-show] not permitted"), "{}", err);
+    assert_eq!(probe.asked, vec!["vcs_clean"]);
+}
+
+#[test]
+fn checks_are_only_probed_when_required() {
+    let policies = policies(POLICIES);
+    let mut probe = FakeProbe {
+        passes: true,
+        ..FakeProbe::default()
+    };
+    let user = subject(Context::User, vec![Site::Local], false);
+    assert!(enforce(&policies, &user, &mut probe, &[]).unwrap().is_empty());
+    assert!(probe.asked.is_empty());
+
+    let facility = subject(Context::Facility, vec![Site::Local], false);
+    assert!(enforce(&policies, &facility, &mut probe, &[]).is_ok());
+    assert_eq!(probe.asked, vec!["tests_pass"]);
+
+    probe.passes = false;
+    let err = enforce(&policies, &facility, &mut probe, &[]).unwrap_err().to_string();
+    assert!(err.contains("[facility-tested] the package's tests did not pass"), "{}", err);
+}
+
+#[test]
+fn only_overridable_policies_may_be_overridden() {
+    let policies = policies(POLICIES);
+    let mut probe = FakeProbe::default();
+    let shared = subject(Context::Shared, vec![Site::Local], false);
+    let waived = enforce(&policies, &shared, &mut probe, &["shared-rocky9".to_string()]).unwrap();
+    assert_eq!(waived.len(), 1);
+    assert_eq!(waived[0].id, "shared-rocky9");
+
+    let work = subject(Context::Shared, vec![Site::Local], true);
+    let err = enforce(
+        &policies,
+        &work,
+        &mut probe,
+        &["shared-rocky9".to_string(), "no-work-at-show".to_string()],
+    )
+    .unwrap_err()
+    .to_string();
+    assert!(!err.contains("shared-rocky9"), "{}", err);
+    assert!(err.contains("[no-work-at-show] not permitted (this policy may not be overridden)"), "{}", err);
+
+    let err = enforce(&policies, &shared, &mut probe, &["bogus".to_string()])
+        .unwrap_err()
+        .to_string();
+    assert_eq!(err, "Unknown Policy:'bogus'");
+}
+
+#[test]
+fn merge_keeps_the_policies_of_every_layer() {
+    let system = policies(POLICIES);
+    let package = policies(
+        r#"
+        [[policies]]
+        id = "shared-cent7"
+        when = { context = "shared" }
+        require = { platforms = ["cent7_64"] }
+        "#,
+    );
+    let merged = merge(vec![
+        ("system".to_string(), system.as_slice()),
+        ("package".to_string(), package.as_slice()),
+    ])
+    .unwrap();
+    assert_eq!(merged.len(), system.len() + 1);
+    assert_eq!(merged.last().unwrap().id, "shared-cent7");
+}
+
+#[test]
+fn merge_given_redefined_policy_fails() {
+    let system = policies(POLICIES);
+    let package = policies(
+        r#"
+        [[policies]]
+        id = "shared-rocky9"
+        require = {}
+        overridable = true
+        "#,
+    );
+    let err = merge(vec![
+        ("system".to_string(), system.as_slice()),
+        ("package".to_string(), package.as_slice()),
+    ])
+    .unwrap_err();
+    assert_eq!(
+        err,
+        PkMakeError::PolicyRedefined {
+            id: "shared-rocky9".to_string(),
+            layer: "package".to_string(),
+            original: "system".to_string(),
+        }
+    );
+}
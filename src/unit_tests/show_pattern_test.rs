@@ -0,0 +1,39 @@
+use super::*;
+use std::path::PathBuf;
+
+#[test]
+fn builtin_pattern_matches_jobs_tree() {
+    let pattern = &ShowPattern::builtin()[0];
+    assert_eq!(
+        pattern.show_from(&PathBuf::from("/jobs/dev01/rd/9999/work")),
+        Some("dev01".to_string())
+    );
+    assert_eq!(
+        pattern.show_from(&PathBuf::from("/jobs/dev01")),
+        Some("dev01".to_string())
+    );
+    assert_eq!(pattern.show_from(&PathBuf::from("/jobs")), None);
+    assert_eq!(pattern.show_from(&PathBuf::from("/home/dev01/jobs")), None);
+}
+
+#[test]
+fn wildcards_and_exact_patterns() {
+    let pattern = ShowPattern::from_str("/mnt/*/{show}").unwrap();
+    assert_eq!(
+        pattern.show_from(&PathBuf::from("/mnt/projects/dev02")),
+        Some("dev02".to_string())
+    );
+    // without a trailing ... the pattern must match the whole path
+    assert_eq!(pattern.show_from(&PathBuf::from("/mnt/projects/dev02/rd")), None);
+    assert_eq!(pattern.show_from(&PathBuf::from("/mnt/projects")), None);
+}
+
+#[test]
+fn invalid_patterns_are_errors() {
+    for input in &["jobs/{show}/...", "/jobs/...", "/jobs/{show}/{show}", "/jobs/.../{show}"] {
+        assert_eq!(
+            ShowPattern::from_str(input),
+            Err(PkMakeError::InvalidShowPattern(input.to_string()))
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use super::*;
+use tempfile::tempdir;
+
+#[test]
+fn can_check_shows() {
+    let registry = ShowRegistry::new(vec!["dev01", "DEV02", "dev01"]);
+    assert_eq!(registry.shows(), &["dev01".to_string(), "DEV02".to_string()]);
+    assert!(registry.contains("DEV01"));
+    assert!(registry.contains("dev02"));
+    assert!(!registry.contains("dev03"));
+    assert_eq!(registry.verify("dev01"), Ok(()));
+}
+
+#[test]
+fn suggests_close_matches() {
+    let registry = ShowRegistry::new(vec!["zebra", "dev10", "typo", "dev01"]);
+    assert_eq!(registry.suggestions("tyop"), vec!["typo"]);
+    assert_eq!(registry.suggestions("Dev1"), vec!["dev01", "dev10"]);
+    assert_eq!(
+        registry.verify("dev1"),
+        Err(PkMakeError::UnknownShow {
+            show: "dev1".to_string(),
+            suggestions: " Did you mean dev01, dev10?".to_string(),
+        })
+    );
+    assert_eq!(
+        registry.verify("unrelated"),
+        Err(PkMakeError::UnknownShow {
+            show: "unrelated".to_string(),
+            suggestions: String::new(),
+        })
+    );
+}
+
+#[test]
+fn can_compute_edit_distance() {
+    assert_eq!(edit_distance("", "abc"), 3);
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+    assert_eq!(edit_distance("dev01", "dev01"), 0);
+}
+
+#[test]
+fn can_scan_shows_root() {
+    let root = tempdir().unwrap();
+    for dir in &["dev01", "dev02", ".snapshot"] {
+        std::fs::create_dir(root.path().join(dir)).unwrap();
+    }
+    std::fs::write(root.path().join("README"), "not a show").unwrap();
+    let mut registry = ShowRegistry::new(vec!["dev02"]);
+    registry.scan(root.path()).unwrap();
+    assert_eq!(registry.shows(), &["dev02".to_string(), "dev01".to_string()]);
+    assert!(registry.scan(&root.path().join("missing")).is_err());
+}
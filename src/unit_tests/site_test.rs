@@ -20,16 +20,10 @@ fn can_build_all() {
 
 #[test]
 fn can_build_named() {
-    let named = vec![
-        ("hyderabad", NamedSite::Hyderabad),
-        ("playa", NamedSite::Playa),
-        ("portland", NamedSite::Portland),
-        ("montreal", NamedSite::Montreal),
-        ("vancouver", NamedSite::Vancouver),
-    ];
+    let named = vec!["hyderabad", "playa", "portland", "montreal", "vancouver"];
     for nm in named {
-        let result = Site::from_str(nm.0);
-        assert_eq!(result.unwrap(), Site::Named(nm.1));
+        let result = Site::from_str(nm);
+        assert_eq!(result.unwrap(), Site::Named(NamedSite::from(nm).unwrap()));
     }
 }
 
@@ -40,3 +34,142 @@ fn errors_when_invalid_chars_used() {
         let result = Site::from_str(invalid);
         assert!(result.is_err());
     }}
+
+fn registry_with_groups() -> SiteRegistry {
+    let mut registry = SiteRegistry::builtin();
+    registry
+        .add_group(
+            "na",
+            &["playa".into(), "portland".into(), "vancouver".into(), "montreal".into()],
+        )
+        .unwrap();
+    registry
+}
+
+fn named(sites: &[&str]) -> HashSet<Site> {
+    sites
+        .iter()
+        .map(|s| Site::Named(NamedSite::from(s).unwrap()))
+        .collect()
+}
+
+#[test]
+fn can_build_exclusion() {
+    let result = Site::from_str("!hyderabad").unwrap();
+    assert_eq!(
+        result,
+        Site::Exclude(Box::new(Site::Named(NamedSite::from("hyderabad").unwrap())))
+    );
+    assert_eq!(result.to_string(), "!hyderabad");
+    assert!(Site::from_str("!local").is_err());
+    assert!(Site::from_str("!fluboxland").is_err());
+}
+
+#[test]
+fn unknown_group_is_error() {
+    assert!(Site::from_str("@fluboxland").is_err());
+}
+
+#[test]
+fn parse_given_registry_finds_configured_sites_and_groups() {
+    let mut registry = registry_with_groups();
+    registry
+        .extend(&[NamedSite::new("london", "London", vec!["ldn".into()], vec!["eu".into()])])
+        .unwrap();
+    let london = Site::parse("LDN", &registry).unwrap();
+    assert_eq!(london.as_str(), "london");
+    assert_eq!(Site::parse("@eu", &registry).unwrap(), Site::Group("eu".to_string()));
+    assert_eq!(
+        Site::parse("!london", &registry).unwrap(),
+        Site::Exclude(Box::new(london))
+    );
+    // the builtin registry knows nothing of them
+    assert!(Site::from_str("london").is_err());
+    assert!(Site::from_str("@eu").is_err());
+}
+
+#[test]
+fn matches_config_spelling() {
+    let playa = Site::from_str("playa").unwrap();
+    assert!(playa.matches("Playa Vista"));
+    assert!(playa.matches("PLAYA"));
+    assert!(!playa.matches("portland"));
+    assert!(Site::All.matches("All"));
+    assert!(Site::Local.matches("local"));
+    assert!(!Site::Local.matches("all"));
+    assert!(Site::Group("na".to_string()).matches("@NA"));
+    assert!(!Site::Exclude(Box::new(playa)).matches("!playa"));
+}
+
+#[test]
+fn expand_leaves_plain_requests_alone() {
+    let registry = registry_with_groups();
+    let result = Site::expand(vec![Site::All], &registry).unwrap();
+    assert_eq!(result, vec![Site::All].into_iter().collect::<HashSet<_>>());
+}
+
+#[test]
+fn expand_all_minus_site() {
+    let registry = registry_with_groups();
+    let sites = vec![
+        Site::All,
+        Site::Exclude(Box::new(Site::Named(NamedSite::from("hyderabad").unwrap()))),
+    ];
+    let result = Site::expand(sites, &registry).unwrap();
+    assert_eq!(result, named(&["playa", "portland", "montreal", "vancouver"]));
+}
+
+#[test]
+fn expand_group_minus_site() {
+    let registry = registry_with_groups();
+    let sites = vec![
+        Site::Group("na".into()),
+        Site::Exclude(Box::new(Site::Named(NamedSite::from("playa").unwrap()))),
+    ];
+    let result = Site::expand(sites, &registry).unwrap();
+    assert_eq!(result, named(&["portland", "vancouver", "montreal"]));
+}
+
+#[test]
+fn expand_only_exclusions_applies_to_all() {
+    let registry = registry_with_groups();
+    let sites = vec![Site::Exclude(Box::new(Site::Group("na".into())))];
+    let result = Site::expand(sites, &registry).unwrap();
+    assert_eq!(result, named(&["hyderabad"]));
+}
+
+#[test]
+fn as_requested_keeps_all_and_groups() {
+    let registry = registry_with_groups();
+    let hyderabad = Site::Named(NamedSite::from("hyderabad").unwrap());
+    let requested = vec![Site::All, Site::Exclude(Box::new(hyderabad.clone()))];
+    let expanded = Site::expand(requested.clone(), &registry).unwrap();
+    let result = Site::as_requested(requested, &expanded, &registry);
+    assert!(result.contains(&Site::All));
+    assert!(!result.contains(&hyderabad));
+
+    let requested = vec![Site::Exclude(Box::new(hyderabad))];
+    let expanded = Site::expand(requested.clone(), &registry).unwrap();
+    assert!(Site::as_requested(requested, &expanded, &registry).contains(&Site::All));
+
+    let requested = vec![Site::Group("na".into())];
+    let expanded = Site::expand(requested.clone(), &registry).unwrap();
+    let result = Site::as_requested(requested, &expanded, &registry);
+    assert!(result.contains(&Site::Group("na".into())));
+    assert!(!result.contains(&Site::All));
+}
+
+#[test]
+fn as_requested_given_every_site_is_all() {
+    let registry = registry_with_groups();
+    let requested = vec![
+        Site::Group("na".into()),
+        Site::Named(NamedSite::from("hyderabad").unwrap()),
+    ];
+    let expanded = Site::expand(requested.clone(), &registry).unwrap();
+    assert!(Site::as_requested(requested, &expanded, &registry).contains(&Site::All));
+
+    let requested = vec![Site::Local];
+    let expanded = Site::expand(requested.clone(), &registry).unwrap();
+    assert_eq!(Site::as_requested(requested, &expanded, &registry), vec![Site::Local]);
+}
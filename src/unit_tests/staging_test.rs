@@ -0,0 +1,56 @@
+use super::*;
+use std::fs;
+use tempfile::tempdir;
+
+fn package(root: &Path, source: &Path, version: &str) -> StagedPackage {
+    StagedPackage {
+        name: "foo".to_string(),
+        version: version.to_string(),
+        platform: "cent7_64".to_string(),
+        source: source.to_path_buf(),
+        destination: root.join("foo").join(version).join("cent7_64"),
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn stage_copies_the_artifact() {
+    let dir = tempdir().unwrap();
+    let source = dir.path().join("dist").join("foo-1.0.0");
+    fs::create_dir_all(source.join("lib")).unwrap();
+    fs::write(source.join("lib").join("foo.py"), "pass").unwrap();
+    std::os::unix::fs::symlink("foo.py", source.join("lib").join("bar.py")).unwrap();
+    let staged = package(&dir.path().join("scratch"), &source, "1.0.0");
+
+    staged.stage().unwrap();
+    let lib = staged.destination.join("lib");
+    assert_eq!(fs::read_to_string(lib.join("foo.py")).unwrap(), "pass");
+    assert_eq!(fs::read_link(lib.join("bar.py")).unwrap(), PathBuf::from("foo.py"));
+
+    // restaging replaces the previous contents
+    fs::remove_file(source.join("lib").join("bar.py")).unwrap();
+    staged.stage().unwrap();
+    assert!(lib.join("foo.py").exists());
+    assert!(fs::symlink_metadata(lib.join("bar.py")).is_err());
+}
+
+#[test]
+fn index_replaces_entries_with_the_same_destination() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    let mut index = StagingIndex::load(root).unwrap();
+    assert!(index.staged.is_empty());
+    index.add(package(root, Path::new("/dist/foo-1.0.0"), "1.0.0"));
+    index.add(package(root, Path::new("/dist/foo-1.0.0_bar"), "1.0.0_bar"));
+    index.add(package(root, Path::new("/other/foo-1.0.0"), "1.0.0"));
+    index.save(root).unwrap();
+
+    let index = StagingIndex::load(root).unwrap();
+    assert_eq!(
+        index.staged,
+        vec![
+            package(root, Path::new("/dist/foo-1.0.0_bar"), "1.0.0_bar"),
+            package(root, Path::new("/other/foo-1.0.0"), "1.0.0"),
+        ]
+    );
+}
@@ -91,6 +91,26 @@ where
     Ok(exit_status)
 }
 
+/// As ```exec_cmd```, with additional environment variables set for the subprocess
+pub fn exec_cmd_with_env<I>(
+    cmds: &str,
+    cwd: I,
+    env: &[(String, String)],
+) -> Result<ExitStatus, AnyError>
+where
+    I: AsRef<std::path::Path>,
+{
+    if cmds.is_empty() {
+        return Err(anyhow!("must pass at least one command to exec_cmd"));
+    }
+
+    let exit_status = env
+        .iter()
+        .fold(Exec::shell(cmds).cwd(cwd.as_ref()), |exec, (k, v)| exec.env(k, v))
+        .join()?;
+    Ok(exit_status)
+}
+
 
 #[cfg(test)]
 /// setup function only available in tests
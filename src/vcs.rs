@@ -20,7 +20,8 @@ use crate::PkMakeError;
 // External crate imports
 use std::convert::TryFrom;
 use std::fmt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use subprocess::{Exec, Redirection};
 use std::str::FromStr;
 
 /// Enumerate the potential vcs systems found at the root
@@ -73,6 +74,49 @@ impl Vcs {
             _ => false,
         }
     }
+    /// The revision checked out at root, if the vcs may be queried. When both git
+    /// and svn are present, the git revision is reported.
+    pub fn revision(&self, root: &Path) -> Option<String> {
+        let cmd = match self {
+            Vcs::Git | Vcs::Both => "git rev-parse HEAD",
+            Vcs::Svn => "svn info --show-item revision",
+        };
+        let capture = Exec::shell(cmd)
+            .cwd(root)
+            .stdout(Redirection::Pipe)
+            .stderr(Redirection::Pipe)
+            .capture()
+            .ok()?;
+        let revision = capture.stdout_str().trim().to_string();
+        if capture.success() && !revision.is_empty() {
+            Some(revision)
+        } else {
+            None
+        }
+    }
+    /// Whether the working copy at root has no uncommitted changes, if the vcs may
+    /// be queried. When both git and svn are present, both must be clean.
+    pub fn is_clean(&self, root: &Path) -> Option<bool> {
+        let cmds: &[&str] = match self {
+            Vcs::Git => &["git status --porcelain"],
+            Vcs::Svn => &["svn status -q"],
+            Vcs::Both => &["git status --porcelain", "svn status -q"],
+        };
+        let mut clean = true;
+        for cmd in cmds {
+            let capture = Exec::shell(*cmd)
+                .cwd(root)
+                .stdout(Redirection::Pipe)
+                .stderr(Redirection::Pipe)
+                .capture()
+                .ok()?;
+            if !capture.success() {
+                return None;
+            }
+            clean &= capture.stdout_str().trim().is_empty();
+        }
+        Some(clean)
+    }
     /// Overrides the auto generated trait impl of from to provide a
     /// fallible version
     pub fn from<I>(input: I) -> Result<Self, PkMakeError>